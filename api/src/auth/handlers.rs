@@ -1,13 +1,80 @@
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode, header::USER_AGENT},
+    response::{IntoResponse, Redirect, Response},
+};
+use std::net::SocketAddr;
 
 use crate::{
+    auth::Role,
     config::AppState,
     errors::AppError,
-    models::{LoginRequest, LoginResponse, LogoutRequest},
-    services::auth::verify_password,
-    traits::Teachers,
+    models::{
+        AccountKind, LoginMfaRequest, LoginRequest, LoginResponse, LogoutRequest, MfaChallenge,
+        OidcCallbackQuery, ResetPasswordRequest, Teacher,
+    },
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{hash_password, verify_password},
+        oidc::{OIDC_STATE_TTL, resolve_teacher},
+        rate_limit::{check_login_lock, clear_login_failures, record_login_failure},
+        totp::verify_totp,
+    },
+    traits::{RecoveryCodes, Students, Teachers},
 };
 
+const MFA_CHALLENGE_TTL: u64 = 300;
+
+fn device(headers: &HeaderMap) -> &str {
+    headers
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("unknown")
+}
+
+async fn start_session(
+    app_state: &AppState,
+    teacher: Teacher,
+    addr: SocketAddr,
+    headers: &HeaderMap,
+) -> Result<LoginResponse, AppError> {
+    let session = app_state
+        .redis
+        .create_session(
+            AccountKind::Teacher,
+            teacher.id,
+            device(headers),
+            &addr.ip().to_string(),
+        )
+        .await?;
+
+    Ok(LoginResponse {
+        token: session.token,
+        role: teacher.role,
+        id: teacher.id,
+        must_change_password: teacher.must_change_password,
+        mfa_setup_required: app_state.mfa.is_required(teacher.role) && !teacher.totp_enabled,
+    })
+}
+
+async fn mfa_challenge(app_state: &AppState, teacher_id: i64) -> Result<Response, AppError> {
+    let mfa_token = app_state
+        .redis
+        .create_mfa_challenge(teacher_id, MFA_CHALLENGE_TTL)
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(MfaChallenge {
+            mfa_required: true,
+            mfa_token,
+            expires_in: MFA_CHALLENGE_TTL,
+        }),
+    )
+        .into_response())
+}
+
 #[utoipa::path(
     post,
     path = "/login",
@@ -15,30 +82,221 @@ use crate::{
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Succesfully logged in", body = LoginResponse),
+        (status = 202, description = "Password accepted, finish with /login/2fa", body = MfaChallenge),
         (status = 401, description = "Invalid login or password"),
+        (status = 429, description = "Too many failed attempts"),
     )
 )]
 pub async fn login(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
+) -> Result<Response, AppError> {
+    check_login_lock(&app_state, &payload.login, addr.ip()).await?;
+
+    let teacher = match app_state.db.get_teacher_by_login(&payload.login).await {
+        Ok(teacher) if verify_password(&payload.password, &teacher.password_hash) => teacher,
+        Ok(_) | Err(sqlx::Error::RowNotFound) => {
+            record_login_failure(&app_state, &payload.login, addr.ip()).await?;
+            return Err(AppError::InvalidCredentials);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if teacher.totp_enabled {
+        return mfa_challenge(&app_state, teacher.id).await;
+    }
+    clear_login_failures(&app_state, &payload.login).await?;
+
+    let response = start_session(&app_state, teacher, addr, &headers).await?;
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    tag = "Auth",
+    request_body = LoginMfaRequest,
+    responses(
+        (status = 200, description = "Succesfully logged in", body = LoginResponse),
+        (status = 400, description = "Invalid or expired code"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
+pub async fn login_mfa(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginMfaRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let teacher = app_state.db.get_teacher_by_login(&payload.login).await?;
+    let Some(user_id) = app_state
+        .redis
+        .get_mfa_challenge(&payload.mfa_token)
+        .await?
+    else {
+        return Err(AppError::BadRequest(String::from("Challenge expired")));
+    };
+    let teacher = app_state.db.get_teacher_by_id(user_id).await?;
+    check_login_lock(&app_state, &teacher.login, addr.ip()).await?;
 
-    if !verify_password(&payload.password, &teacher.password_hash) {
-        return Err(AppError::BadRequest(String::from("Invalid password")));
+    let valid = verify_totp(&app_state, &teacher, &payload.code).await?
+        || app_state
+            .db
+            .use_recovery_code(teacher.id, &payload.code)
+            .await?;
+    if !valid {
+        record_login_failure(&app_state, &teacher.login, addr.ip()).await?;
+        return Err(AppError::BadRequest(String::from("Invalid code")));
     }
 
-    let token = app_state.redis.create_session(teacher.id).await?;
+    app_state
+        .redis
+        .delete_mfa_challenge(&payload.mfa_token)
+        .await?;
+    clear_login_failures(&app_state, &teacher.login).await?;
+
+    let response = start_session(&app_state, teacher, addr, &headers).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/student/login",
+    tag = "Auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Succesfully logged in", body = LoginResponse),
+        (status = 401, description = "Invalid login or password"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
+pub async fn student_login(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // Student and teacher logins may coincide, so their failure counters are kept apart.
+    let lock_login = format!("student:{}", payload.login);
+    check_login_lock(&app_state, &lock_login, addr.ip()).await?;
+
+    let student = match app_state.db.get_student_by_login(&payload.login).await {
+        Ok(student)
+            if student
+                .password_hash
+                .as_deref()
+                .is_some_and(|hash| verify_password(&payload.password, hash)) =>
+        {
+            student
+        }
+        Ok(_) | Err(sqlx::Error::RowNotFound) => {
+            record_login_failure(&app_state, &lock_login, addr.ip()).await?;
+            return Err(AppError::InvalidCredentials);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    clear_login_failures(&app_state, &lock_login).await?;
+
+    let session = app_state
+        .redis
+        .create_session(
+            AccountKind::Student,
+            student.id,
+            device(&headers),
+            &addr.ip().to_string(),
+        )
+        .await?;
+
     Ok((
         StatusCode::OK,
         Json(LoginResponse {
-            token,
-            role: teacher.role,
-            id: teacher.id,
+            token: session.token,
+            role: Role::Student,
+            id: student.id,
+            must_change_password: student.must_change_password,
+            mfa_setup_required: false,
         }),
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/oidc/login",
+    tag = "Auth",
+    responses(
+        (status = 303, description = "Redirect to the identity provider"),
+        (status = 404, description = "Single sign-on is not configured"),
+    )
+)]
+pub async fn oidc_login(State(app_state): State<AppState>) -> Result<Redirect, AppError> {
+    let Some(oidc) = &app_state.oidc else {
+        return Err(AppError::NotFound);
+    };
+
+    let request = oidc.authorize();
+    app_state
+        .redis
+        .set_oidc_state(
+            &request.state,
+            &request.nonce,
+            &request.pkce_verifier,
+            OIDC_STATE_TTL,
+        )
+        .await?;
+
+    Ok(Redirect::to(&request.url))
+}
+
+#[utoipa::path(
+    get,
+    path = "/oidc/callback",
+    tag = "Auth",
+    params(OidcCallbackQuery),
+    responses(
+        (status = 200, description = "Succesfully logged in", body = LoginResponse),
+        (status = 202, description = "Provider login accepted, finish with /login/2fa", body = MfaChallenge),
+        (status = 400, description = "Unknown or expired login request"),
+        (status = 401, description = "The provider rejected the login or the account has no matching claim"),
+        (status = 403, description = "No teacher is linked to the provider account"),
+        (status = 404, description = "Single sign-on is not configured"),
+    )
+)]
+pub async fn oidc_callback(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    audit: AuditContext,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Response, AppError> {
+    let Some(oidc) = &app_state.oidc else {
+        return Err(AppError::NotFound);
+    };
+    let Some((nonce, pkce_verifier)) = app_state.redis.take_oidc_state(&query.state).await? else {
+        return Err(AppError::BadRequest(String::from("Login request expired")));
+    };
+    let Some(code) = query.code else {
+        eprintln!("OIDC provider returned an error: {:?}", query.error);
+        return Err(AppError::Unauthorized);
+    };
+
+    let identity = oidc
+        .exchange(code, nonce, pkce_verifier)
+        .await
+        .map_err(|e| {
+            eprintln!("OIDC login failed: {}", e);
+            AppError::Unauthorized
+        })?;
+    let teacher = resolve_teacher(&app_state, oidc, &identity, &audit).await?;
+
+    if teacher.totp_enabled {
+        return mfa_challenge(&app_state, teacher.id).await;
+    }
+
+    let response = start_session(&app_state, teacher, addr, &headers).await?;
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
 #[utoipa::path(
     post,
     path = "/logout",
@@ -56,3 +314,62 @@ pub async fn logout(
 
     Ok((StatusCode::OK, "Logged out".to_string()))
 }
+
+#[utoipa::path(
+    post,
+    path = "/reset_password",
+    tag = "Auth",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password replaced, all sessions revoked"),
+        (status = 400, description = "Invalid login or reset code"),
+        (status = 422, description = "Password does not satisfy the policy"),
+        (status = 429, description = "Too many failed attempts"),
+    )
+)]
+pub async fn reset_password(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    audit: AuditContext,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    check_login_lock(&app_state, &payload.login, addr.ip()).await?;
+
+    let teacher = match app_state.db.get_teacher_by_login(&payload.login).await {
+        Ok(teacher) => teacher,
+        Err(sqlx::Error::RowNotFound) => {
+            record_login_failure(&app_state, &payload.login, addr.ip()).await?;
+            return Err(AppError::BadRequest(String::from("Invalid reset code")));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let code = payload.code.trim().to_uppercase();
+    match app_state.redis.get_reset_code(teacher.id).await? {
+        Some(code_hash) if verify_password(&code, &code_hash) => {}
+        _ => {
+            record_login_failure(&app_state, &payload.login, addr.ip()).await?;
+            return Err(AppError::BadRequest(String::from("Invalid reset code")));
+        }
+    }
+    app_state
+        .password_policy
+        .validate(&payload.new_password, &teacher.login)
+        .map_err(AppError::Validation)?;
+
+    let new_hash = hash_password(&payload.new_password);
+    app_state
+        .db
+        .update_teacher_hash(teacher.id, &new_hash, false)
+        .await?;
+    app_state.redis.delete_reset_code(teacher.id).await?;
+    app_state.redis.revoke_sessions(teacher.id, None).await?;
+    clear_login_failures(&app_state, &payload.login).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("reset_password", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, "Password changed".to_string()))
+}
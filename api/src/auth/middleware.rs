@@ -1,11 +1,42 @@
 use axum::{
     body::Body,
     extract::{Request, State},
+    http::{HeaderValue, Method},
     middleware::Next,
     response::Response,
 };
 
-use crate::{config::AppState, errors::AppError, traits::Teachers};
+use crate::{
+    auth::{GroupScope, Permission, Role},
+    config::AppState,
+    errors::AppError,
+    models::{AccountKind, ApiKey, Session},
+    services::auth::{API_KEY_PREFIX, hash_api_key},
+    traits::{ApiKeys, GroupGrants, Students, Teachers},
+};
+
+/// Routes a teacher with `must_change_password` can still reach.
+const PASSWORD_CHANGE_PATHS: [&str; 2] = ["/me", "/me/password"];
+
+/// Read-only sessions, impersonations without writes, can only read.
+fn ensure_writable(session: &Session, method: &Method) -> Result<(), AppError> {
+    if session.read_only && ![Method::GET, Method::HEAD, Method::OPTIONS].contains(method) {
+        return Err(AppError::ReadOnlySession);
+    }
+    Ok(())
+}
+
+/// Routes a student with `must_change_password` can still reach.
+const STUDENT_PASSWORD_CHANGE_PATHS: [&str; 2] = ["/student/me", "/student/me/password"];
+
+/// Routes a teacher whose role requires 2FA can reach before enrolling.
+const MFA_SETUP_PATHS: [&str; 3] = ["/me", "/me/2fa/setup", "/me/2fa/enable"];
+
+/// Response header naming the admin behind an impersonation session.
+pub const IMPERSONATED_BY_HEADER: &str = "x-impersonated-by";
+
+type MiddlewareFuture =
+    std::pin::Pin<Box<dyn std::future::Future<Output = Result<Response, AppError>> + Send>>;
 
 pub async fn auth_middleware(
     State(app_state): State<AppState>,
@@ -20,34 +51,116 @@ pub async fn auth_middleware(
         .map(|s| s.to_string());
 
     let Some(token) = token else {
-        return Err(AppError::BadRequest(String::from("Missing token")));
+        return Err(AppError::Unauthorized);
     };
 
+    if token.starts_with(API_KEY_PREFIX) {
+        let key = match app_state
+            .db
+            .get_api_key_by_hash(&hash_api_key(&token))
+            .await
+        {
+            Ok(key) => key,
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(AppError::Unauthorized);
+            }
+            Err(_) => return Err(AppError::Internal),
+        };
+        if key.is_expired() {
+            return Err(AppError::Unauthorized);
+        }
+
+        app_state
+            .db
+            .touch_api_key(key.id)
+            .await
+            .map_err(|_| AppError::Internal)?;
+        req.extensions_mut().insert(key);
+        return Ok(next.run(req).await);
+    }
+
     match app_state.redis.get_session(&token).await {
-        Ok(Some(user_id)) => {
-            req.extensions_mut().insert(user_id as i64);
-            Ok(next.run(req).await)
+        Ok(Some(session)) => {
+            ensure_writable(&session, req.method())?;
+            let impersonator_id = session.impersonator_id;
+            // Student ids share the number space with teachers, so only teacher sessions
+            // expose the bare user id that teacher routes rely on.
+            if session.kind == AccountKind::Teacher {
+                req.extensions_mut().insert(session.user_id);
+            }
+            req.extensions_mut().insert(session);
+
+            let mut response = next.run(req).await;
+            if let Some(impersonator_id) = impersonator_id {
+                response
+                    .headers_mut()
+                    .insert(IMPERSONATED_BY_HEADER, HeaderValue::from(impersonator_id));
+            }
+            Ok(response)
         }
-        Ok(None) => Err(AppError::BadRequest(String::from("Invalid token"))),
+        Ok(None) => Err(AppError::Unauthorized),
         Err(_) => Err(AppError::Internal),
     }
 }
 
-pub fn require_role(
-    roles: &'static [&'static str],
-) -> impl Clone
-+ Send
-+ Sync
-+ 'static
-+ Fn(
-    State<AppState>,
-    Request<Body>,
-    Next,
-) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Response, AppError>> + Send>> {
+pub fn require_permission(
+    permission: Permission,
+) -> impl Clone + Send + Sync + 'static + Fn(State<AppState>, Request<Body>, Next) -> MiddlewareFuture
+{
+    authorize(Some(permission))
+}
+
+/// Same checks as `require_permission` but for any permission, for endpoints that check
+/// it per operation. Students are refused, such endpoints serve staff data.
+pub fn require_staff()
+-> impl Clone + Send + Sync + 'static + Fn(State<AppState>, Request<Body>, Next) -> MiddlewareFuture
+{
+    authorize(None)
+}
+
+fn authorize(
+    permission: Option<Permission>,
+) -> impl Clone + Send + Sync + 'static + Fn(State<AppState>, Request<Body>, Next) -> MiddlewareFuture
+{
     move |State(app_state): State<AppState>, mut req: Request<Body>, next: Next| {
         let app_state = app_state.clone();
 
         Box::pin(async move {
+            if let Some(key) = req.extensions().get::<ApiKey>() {
+                if permission.is_some_and(|p| !key.scopes.contains(&p)) {
+                    return Err(AppError::Forbidden);
+                }
+
+                let scope = key.group_scope();
+                req.extensions_mut().insert(scope);
+                return Ok(next.run(req).await);
+            }
+
+            let student_id = req
+                .extensions()
+                .get::<Session>()
+                .filter(|s| s.kind == AccountKind::Student)
+                .map(|s| s.user_id);
+            if let Some(student_id) = student_id {
+                if !permission.is_some_and(|p| Role::Student.has(p)) {
+                    return Err(AppError::Forbidden);
+                }
+
+                let student = app_state
+                    .db
+                    .get_student_by_id(student_id)
+                    .await
+                    .map_err(|_| AppError::Internal)?;
+                if student.must_change_password
+                    && !STUDENT_PASSWORD_CHANGE_PATHS.contains(&req.uri().path())
+                {
+                    return Err(AppError::PasswordChangeRequired);
+                }
+
+                req.extensions_mut().insert(student);
+                return Ok(next.run(req).await);
+            }
+
             let user_id = req
                 .extensions_mut()
                 .get::<i64>()
@@ -59,12 +172,78 @@ pub fn require_role(
                 .await
                 .map_err(|_| AppError::Internal)?;
 
-            if teacher.role == "admin" || roles.contains(&teacher.role.as_str()) {
-                req.extensions_mut().insert(teacher);
-                Ok(next.run(req).await)
-            } else {
-                Err(AppError::Forbidden)
+            if permission.is_some_and(|p| !teacher.role.has(p)) {
+                return Err(AppError::Forbidden);
             }
+            let path = req.uri().path();
+            if teacher.must_change_password {
+                if !PASSWORD_CHANGE_PATHS.contains(&path) {
+                    return Err(AppError::PasswordChangeRequired);
+                }
+            } else if app_state.mfa.is_required(teacher.role)
+                && !teacher.totp_enabled
+                && !MFA_SETUP_PATHS.contains(&path)
+            {
+                return Err(AppError::MfaSetupRequired);
+            }
+
+            let scope = if teacher.role.is_college_wide() {
+                GroupScope::All
+            } else {
+                let grants = app_state
+                    .db
+                    .get_group_grants(teacher.id)
+                    .await
+                    .map_err(|_| AppError::Internal)?;
+                GroupScope::Groups(grants.into_iter().map(|g| g.group_id).collect())
+            };
+
+            req.extensions_mut().insert(teacher);
+            req.extensions_mut().insert(scope);
+            Ok(next.run(req).await)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn session(read_only: bool) -> Session {
+        Session {
+            id: String::from("s"),
+            token: String::from("t"),
+            kind: AccountKind::Teacher,
+            user_id: 2,
+            device: String::new(),
+            ip: String::new(),
+            created_at: Utc::now(),
+            last_seen: Utc::now(),
+            impersonator_id: Some(1),
+            read_only,
+        }
+    }
+
+    #[test]
+    fn read_only_session_can_read() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            assert!(ensure_writable(&session(true), &method).is_ok());
+        }
+    }
+
+    #[test]
+    fn read_only_session_cant_write() {
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            assert!(matches!(
+                ensure_writable(&session(true), &method),
+                Err(AppError::ReadOnlySession)
+            ));
+        }
+    }
+
+    #[test]
+    fn writable_session_can_write() {
+        assert!(ensure_writable(&session(false), &Method::DELETE).is_ok());
+    }
+}
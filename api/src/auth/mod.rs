@@ -1,4 +1,8 @@
 pub mod handlers;
 pub mod middleware;
+pub mod roles;
+pub mod scope;
 
-pub use middleware::{auth_middleware, require_role};
+pub use middleware::{auth_middleware, require_permission, require_staff};
+pub use roles::{Permission, Role};
+pub use scope::GroupScope;
@@ -0,0 +1,193 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, Enum)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Dispatcher,
+    Curator,
+    Teacher,
+    Student,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ManageTeachers,
+    ManageRoles,
+    ViewTeachers,
+    ManageGroups,
+    ManageSubjects,
+    EditSchedule,
+    EditScheduleChanges,
+    ManageTeacherLinks,
+    SendNotifications,
+    SelfService,
+    ManageApiKeys,
+    ViewAuditLog,
+    ManageStudents,
+    StudentSelfService,
+    Impersonate,
+}
+
+impl Role {
+    /// Roles a teacher account can hold, from the most privileged one. `Student` belongs
+    /// to student accounts only and is never parsed from input.
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Dispatcher, Role::Curator, Role::Teacher];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Dispatcher => "dispatcher",
+            Role::Curator => "curator",
+            Role::Teacher => "teacher",
+            Role::Student => "student",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::ManageTeachers,
+                Permission::ManageRoles,
+                Permission::ViewTeachers,
+                Permission::ManageGroups,
+                Permission::ManageSubjects,
+                Permission::EditSchedule,
+                Permission::EditScheduleChanges,
+                Permission::ManageTeacherLinks,
+                Permission::SendNotifications,
+                Permission::SelfService,
+                Permission::ManageApiKeys,
+                Permission::ViewAuditLog,
+                Permission::ManageStudents,
+                Permission::Impersonate,
+            ],
+            Role::Dispatcher => &[
+                Permission::ViewTeachers,
+                Permission::ManageSubjects,
+                Permission::EditSchedule,
+                Permission::EditScheduleChanges,
+                Permission::ManageTeacherLinks,
+                Permission::SendNotifications,
+                Permission::SelfService,
+                Permission::ManageStudents,
+            ],
+            Role::Curator => &[
+                Permission::ViewTeachers,
+                Permission::ManageSubjects,
+                Permission::EditSchedule,
+                Permission::EditScheduleChanges,
+                Permission::ManageTeacherLinks,
+                Permission::SendNotifications,
+                Permission::SelfService,
+                Permission::ManageStudents,
+            ],
+            Role::Teacher => &[Permission::SelfService],
+            Role::Student => &[Permission::StudentSelfService],
+        }
+    }
+
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    pub fn is_college_wide(&self) -> bool {
+        matches!(self, Role::Admin | Role::Dispatcher)
+    }
+}
+
+impl Permission {
+    pub const ALL: [Permission; 15] = [
+        Permission::ManageTeachers,
+        Permission::ManageRoles,
+        Permission::ViewTeachers,
+        Permission::ManageGroups,
+        Permission::ManageSubjects,
+        Permission::EditSchedule,
+        Permission::EditScheduleChanges,
+        Permission::ManageTeacherLinks,
+        Permission::SendNotifications,
+        Permission::SelfService,
+        Permission::ManageApiKeys,
+        Permission::ViewAuditLog,
+        Permission::ManageStudents,
+        Permission::StudentSelfService,
+        Permission::Impersonate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageTeachers => "manage_teachers",
+            Permission::ManageRoles => "manage_roles",
+            Permission::ViewTeachers => "view_teachers",
+            Permission::ManageGroups => "manage_groups",
+            Permission::ManageSubjects => "manage_subjects",
+            Permission::EditSchedule => "edit_schedule",
+            Permission::EditScheduleChanges => "edit_schedule_changes",
+            Permission::ManageTeacherLinks => "manage_teacher_links",
+            Permission::SendNotifications => "send_notifications",
+            Permission::SelfService => "self_service",
+            Permission::ManageApiKeys => "manage_api_keys",
+            Permission::ViewAuditLog => "view_audit_log",
+            Permission::ManageStudents => "manage_students",
+            Permission::StudentSelfService => "student_self_service",
+            Permission::Impersonate => "impersonate",
+        }
+    }
+
+    /// Scopes an API key may hold. Managing accounts, roles and keys stays with people,
+    /// so a leaked key can't grant itself more access.
+    pub fn is_key_scope(&self) -> bool {
+        matches!(
+            self,
+            Permission::ViewTeachers
+                | Permission::ManageGroups
+                | Permission::ManageSubjects
+                | Permission::EditSchedule
+                | Permission::EditScheduleChanges
+                | Permission::ManageTeacherLinks
+                | Permission::SendNotifications
+                | Permission::ViewAuditLog
+        )
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| format!("Unknown role: {}", s))
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or_else(|| format!("Unknown permission: {}", s))
+    }
+}
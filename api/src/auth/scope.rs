@@ -0,0 +1,24 @@
+use crate::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub enum GroupScope {
+    All,
+    Groups(Vec<i64>),
+}
+
+impl GroupScope {
+    pub fn allows(&self, group_id: i64) -> bool {
+        match self {
+            GroupScope::All => true,
+            GroupScope::Groups(group_ids) => group_ids.contains(&group_id),
+        }
+    }
+
+    pub fn ensure(&self, group_id: i64) -> Result<(), AppError> {
+        if self.allows(group_id) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+}
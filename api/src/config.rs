@@ -1,7 +1,12 @@
 use {
-    crate::{db::DBState, redis::RedisState, services::notifications::FCM},
-    dotenv,
-    std::env,
+    crate::{
+        auth::Role,
+        db::DBState,
+        redis::RedisState,
+        services::{email::Mailer, live::Live, notifications::Fcm, oidc::Oidc},
+        telegram::TelegramBot,
+    },
+    std::{env, net::IpAddr},
 };
 
 #[derive(Debug)]
@@ -10,6 +15,75 @@ pub struct Config {
     pub ip: String,
     pub port: String,
     pub redis_address: String,
+    pub smtp: Option<SmtpConfig>,
+    pub digest_hour: u32,
+    pub telegram: Option<TelegramConfig>,
+    pub limits: RateLimitConfig,
+    pub password_policy: PasswordPolicy,
+    pub mfa: MfaConfig,
+    pub oidc: Option<OidcConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub tls: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub login_max_failures: u64,
+    pub ip_max_failures: u64,
+    pub lockout_seconds: u64,
+    pub max_lockout_seconds: u64,
+    pub public_requests: u64,
+    pub public_window: u64,
+    /// Load balancers whose `X-Forwarded-For` names the client
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_digit: bool,
+    pub require_mixed_case: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MfaConfig {
+    pub issuer: String,
+    pub required_roles: Vec<Role>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+    pub match_claim: String,
+    pub match_field: OidcMatchField,
+    pub auto_provision: bool,
+    pub groups_claim: String,
+    pub role_mapping: Vec<(String, Role)>,
+}
+
+/// Teacher column compared with the value of `OidcConfig::match_claim`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OidcMatchField {
+    Login,
+    Email,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelegramConfig {
+    pub token: String,
+    pub api_url: String,
 }
 
 impl Config {
@@ -19,19 +93,287 @@ impl Config {
         let ip = env::var("IP").expect("IP is not found");
         let port = env::var("PORT").expect("PORT is not found");
         let redis_address = env::var("REDIS_ADDRESS").expect("REDIS_ADDRESS is not found");
+        let smtp = SmtpConfig::from_env();
+        let digest_hour = env::var("DIGEST_HOUR")
+            .ok()
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(18);
+        let telegram = TelegramConfig::from_env();
+        let limits = RateLimitConfig::from_env();
+        let password_policy = PasswordPolicy::from_env();
+        let mfa = MfaConfig::from_env();
+        let oidc = OidcConfig::from_env();
 
         Self {
             database_url,
             ip,
             port,
             redis_address,
+            smtp,
+            digest_hour,
+            telegram,
+            limits,
+            password_policy,
+            mfa,
+            oidc,
+        }
+    }
+}
+
+impl SmtpConfig {
+    fn from_env() -> Option<Self> {
+        let host = env::var("SMTP_HOST").ok()?;
+        let port = env::var("SMTP_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(587);
+        let username = env::var("SMTP_USERNAME").ok();
+        let password = env::var("SMTP_PASSWORD").ok();
+        let from = env::var("SMTP_FROM").expect("SMTP_FROM is not found");
+        let tls = env::var("SMTP_TLS").map(|t| t != "false").unwrap_or(true);
+
+        Some(Self {
+            host,
+            port,
+            username,
+            password,
+            from,
+            tls,
+        })
+    }
+}
+
+impl RateLimitConfig {
+    fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            login_max_failures: var("LOGIN_MAX_FAILURES", 5),
+            ip_max_failures: var("LOGIN_IP_MAX_FAILURES", 20),
+            lockout_seconds: var("LOGIN_LOCKOUT_SECONDS", 60),
+            max_lockout_seconds: var("LOGIN_MAX_LOCKOUT_SECONDS", 3600),
+            public_requests: var("RATE_LIMIT_REQUESTS", 120),
+            public_window: var("RATE_LIMIT_WINDOW", 60),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    p.parse()
+                        .expect("TRUSTED_PROXIES contains an invalid address")
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    fn from_env() -> Self {
+        let min_length = env::var("PASSWORD_MIN_LENGTH")
+            .ok()
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(8);
+        let require_digit = env::var("PASSWORD_REQUIRE_DIGIT")
+            .map(|v| v != "false")
+            .unwrap_or(true);
+        let require_mixed_case = env::var("PASSWORD_REQUIRE_MIXED_CASE")
+            .map(|v| v != "false")
+            .unwrap_or(false);
+
+        Self {
+            min_length,
+            require_digit,
+            require_mixed_case,
+        }
+    }
+
+    pub fn validate(&self, password: &str, login: &str) -> Result<(), String> {
+        if password.chars().count() < self.min_length {
+            return Err(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_numeric()) {
+            return Err(String::from("Password must contain a digit"));
+        }
+        if self.require_mixed_case
+            && !(password.chars().any(char::is_uppercase)
+                && password.chars().any(char::is_lowercase))
+        {
+            return Err(String::from(
+                "Password must contain upper and lower case letters",
+            ));
+        }
+        if password.eq_ignore_ascii_case(login) {
+            return Err(String::from("Password must differ from the login"));
+        }
+
+        Ok(())
+    }
+}
+
+impl MfaConfig {
+    fn from_env() -> Self {
+        let issuer = env::var("MFA_ISSUER").unwrap_or_else(|_| String::from("StudyLine"));
+        let required_roles = env::var("MFA_REQUIRED_ROLES")
+            .unwrap_or_else(|_| String::from("admin,dispatcher"))
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                r.parse()
+                    .expect("MFA_REQUIRED_ROLES contains an unknown role")
+            })
+            .collect();
+
+        Self {
+            issuer,
+            required_roles,
         }
     }
+
+    pub fn is_required(&self, role: Role) -> bool {
+        self.required_roles.contains(&role)
+    }
+}
+
+impl OidcConfig {
+    fn from_env() -> Option<Self> {
+        let issuer_url = env::var("OIDC_ISSUER_URL").ok()?;
+        let client_id = env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID is not found");
+        let client_secret = env::var("OIDC_CLIENT_SECRET").ok();
+        let redirect_url = env::var("OIDC_REDIRECT_URL").expect("OIDC_REDIRECT_URL is not found");
+        let scopes = env::var("OIDC_SCOPES")
+            .unwrap_or_else(|_| String::from("email,profile"))
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        let match_claim = env::var("OIDC_MATCH_CLAIM").unwrap_or_else(|_| String::from("email"));
+        let match_field = match env::var("OIDC_MATCH_FIELD").as_deref() {
+            Ok("login") => OidcMatchField::Login,
+            Ok("email") | Err(_) => OidcMatchField::Email,
+            Ok(_) => panic!("OIDC_MATCH_FIELD must be either login or email"),
+        };
+        let auto_provision = env::var("OIDC_AUTO_PROVISION")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        let groups_claim = env::var("OIDC_GROUPS_CLAIM").unwrap_or_else(|_| String::from("groups"));
+        let role_mapping = env::var("OIDC_ROLE_MAPPING")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(group, role)| {
+                let role = role
+                    .trim()
+                    .parse()
+                    .expect("OIDC_ROLE_MAPPING contains an unknown role");
+                (group.trim().to_string(), role)
+            })
+            .collect();
+
+        Some(Self {
+            issuer_url,
+            client_id,
+            client_secret,
+            redirect_url,
+            scopes,
+            match_claim,
+            match_field,
+            auto_provision,
+            groups_claim,
+            role_mapping,
+        })
+    }
+
+    /// Picks the most privileged role mapped from the provider groups, or `None` when
+    /// no mapping is configured.
+    pub fn mapped_role(&self, groups: &[String]) -> Option<Role> {
+        if self.role_mapping.is_empty() {
+            return None;
+        }
+
+        let role = self
+            .role_mapping
+            .iter()
+            .filter(|(group, _)| groups.contains(group))
+            .map(|(_, role)| *role)
+            .min_by_key(|role| Role::ALL.iter().position(|r| r == role))
+            .unwrap_or(Role::Teacher);
+
+        Some(role)
+    }
+}
+
+impl TelegramConfig {
+    fn from_env() -> Option<Self> {
+        let token = env::var("TELEGRAM_BOT_TOKEN").ok()?;
+        let api_url = env::var("TELEGRAM_API_URL")
+            .unwrap_or_else(|_| String::from("https://api.telegram.org"));
+
+        Some(Self { token, api_url })
+    }
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: DBState,
     pub redis: RedisState,
-    pub fcm: FCM,
+    pub fcm: Fcm,
+    pub mailer: Option<Mailer>,
+    pub telegram: Option<TelegramBot>,
+    pub limits: RateLimitConfig,
+    pub password_policy: PasswordPolicy,
+    pub mfa: MfaConfig,
+    pub oidc: Option<Oidc>,
+    pub live: Live,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(require_mixed_case: bool) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            require_digit: true,
+            require_mixed_case,
+        }
+    }
+
+    #[test]
+    fn strong_password_passes() {
+        assert!(policy(true).validate("Schedule42", "ivanov").is_ok());
+    }
+
+    #[test]
+    fn length_counts_characters_not_bytes() {
+        assert!(policy(false).validate("пароль1", "ivanov").is_err());
+        assert!(policy(false).validate("пароль12", "ivanov").is_ok());
+    }
+
+    #[test]
+    fn digit_is_required() {
+        assert!(policy(false).validate("schedules", "ivanov").is_err());
+    }
+
+    #[test]
+    fn mixed_case_is_required_when_enabled() {
+        assert!(policy(false).validate("schedule42", "ivanov").is_ok());
+        assert!(policy(true).validate("schedule42", "ivanov").is_err());
+        assert!(policy(true).validate("SCHEDULE42", "ivanov").is_err());
+    }
+
+    #[test]
+    fn password_must_differ_from_login() {
+        assert!(policy(false).validate("Ivanov2024", "ivanov2024").is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use sqlx::{MySqlPool, mysql::MySqlPoolOptions};
+use sqlx::{MySql, MySqlPool, QueryBuilder, mysql::MySqlPoolOptions};
 
 #[derive(Clone)]
 pub struct DBState {
@@ -14,3 +14,18 @@ impl DBState {
         Ok(Self { db: pool })
     }
 }
+
+/// Appends `(?, ?, ...)` for an `IN` condition. An empty list becomes `(NULL)`, which
+/// matches nothing instead of being a syntax error.
+pub fn push_id_list(builder: &mut QueryBuilder<'_, MySql>, ids: &[i64]) {
+    if ids.is_empty() {
+        builder.push("(NULL)");
+        return;
+    }
+    builder.push("(");
+    let mut separated = builder.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    builder.push(")");
+}
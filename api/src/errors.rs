@@ -1,15 +1,48 @@
+use async_graphql::ErrorExtensions;
 use axum::{
     Json,
-    http::StatusCode,
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
     response::{IntoResponse, Response},
 };
 use serde::Serialize;
+use sqlx::mysql::MySqlDatabaseError;
 use utoipa::ToSchema;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+tokio::task_local! {
+    /// Id of the request being handled, echoed in error bodies.
+    static REQUEST_ID: String;
+}
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     //pub status: u64,
     pub error: String,
+    /// Stable machine-readable error code
+    pub code: String,
+    /// Fields the error refers to
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+    /// Value of the `x-request-id` header of the failed request
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -29,53 +62,355 @@ pub enum AppError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
     #[error("Forbidden")]
     Forbidden,
 
+    #[error("Password change required")]
+    PasswordChangeRequired,
+
+    #[error("Two-factor authentication setup required")]
+    MfaSetupRequired,
+
+    #[error("Impersonation session is read-only")]
+    ReadOnlySession,
+
     #[error("Not found")]
     NotFound,
 
     #[error("Conflict")]
     Conflict,
 
+    #[error("Record was changed by another edit")]
+    VersionConflict,
+
+    #[error("Request with this idempotency key is still in progress")]
+    RequestInProgress,
+
+    #[error("Idempotency key was used for another request")]
+    IdempotencyKeyReused,
+
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Validation error: {0:?}")]
+    FieldValidation(Vec<FieldError>),
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
     //Restrictions
-    #[error("Too many requests")]
-    RateLimit,
+    #[error("Too many requests, retry after {0}s")]
+    RateLimit(u64),
 
     #[error("Request timeout")]
     Timeout,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match &self {
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut details = Vec::new();
+        flatten_errors(&errors, "", &mut details);
+        details.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::FieldValidation(details)
+    }
+}
+
+/// Collects nested errors under paths like `pairs[0].cabinet`. Struct-level checks report
+/// under `__all__` and name the offending field in the error code.
+fn flatten_errors(errors: &ValidationErrors, prefix: &str, details: &mut Vec<FieldError>) {
+    let path = |name: &str| {
+        // Lists validated on their own report their items under a placeholder key.
+        if name == "_tmp_validator" {
+            prefix.to_string()
+        } else if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+
+    for (field, kind) in errors.errors() {
+        match kind {
+            ValidationErrorsKind::Field(list) => {
+                for error in list {
+                    let name = if field == "__all__" {
+                        error.code.as_ref()
+                    } else {
+                        field.as_ref()
+                    };
+                    let message = match &error.message {
+                        Some(message) => message.to_string(),
+                        None => default_message(error),
+                    };
+                    details.push(FieldError::new(path(name), message));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => flatten_errors(nested, &path(field), details),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_errors(nested, &format!("{}[{}]", path(field), index), details);
+                }
+            }
+        }
+    }
+}
+
+fn default_message(error: &ValidationError) -> String {
+    let param = |name: &str| error.params.get(name).map(|v| v.to_string());
+    match (error.code.as_ref(), param("min"), param("max")) {
+        ("range", Some(min), Some(max)) => format!("Must be between {} and {}", min, max),
+        ("range", Some(min), None) => format!("Must be at least {}", min),
+        ("length", Some(min), Some(max)) => {
+            format!("Must be between {} and {} characters long", min, max)
+        }
+        ("length", None, Some(max)) => format!("Must be at most {} characters long", max),
+        ("length", Some(min), None) => format!("Must be at least {} characters long", min),
+        ("email", _, _) => String::from("Must be a valid email address"),
+        _ => String::from("Invalid value"),
+    }
+}
+
+struct ErrorParts {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    details: Vec<FieldError>,
+}
+
+impl ErrorParts {
+    fn new(status: StatusCode, code: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            code,
+            message: message.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    fn field(mut self, field: Option<&str>, message: &str) -> Self {
+        if let Some(field) = field {
+            self.details.push(FieldError::new(field, message));
+        }
+        self
+    }
+}
+
+// MySQL and MariaDB error numbers the clients can act on.
+const ER_DUP_ENTRY: u16 = 1062;
+const ER_ROW_IS_REFERENCED_2: u16 = 1451;
+const ER_NO_REFERENCED_ROW_2: u16 = 1452;
+const ER_BAD_NULL_ERROR: u16 = 1048;
+const ER_DATA_TOO_LONG: u16 = 1406;
+const ER_CHECK_CONSTRAINT_VIOLATED: u16 = 3819;
+/// MariaDB reports failed CHECK constraints under its own number.
+const ER_CONSTRAINT_FAILED: u16 = 4025;
+
+/// Returns the text between `start` and the next `end` in a server message.
+fn between<'a>(message: &'a str, start: &str, end: char) -> Option<&'a str> {
+    let rest = &message[message.find(start)? + start.len()..];
+    Some(&rest[..rest.find(end)?])
+}
+
+fn database_error(error: &sqlx::Error) -> ErrorParts {
+    if let sqlx::Error::RowNotFound = error {
+        return ErrorParts::new(StatusCode::NOT_FOUND, "not_found", "Not Found");
+    }
+    let Some(db_error) = error
+        .as_database_error()
+        .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>())
+    else {
+        eprintln!("Database error: {}", error);
+        return ErrorParts::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "database_error",
+            "Database error",
+        );
+    };
+
+    let message = db_error.message();
+    match db_error.number() {
+        ER_DUP_ENTRY => {
+            // Unique indexes are named after their column: "Duplicate entry 'x' for key 'groups.name'".
+            let field =
+                between(message, "for key '", '\'').map(|k| k.rsplit('.').next().unwrap_or(k));
+            ErrorParts::new(StatusCode::CONFLICT, "duplicate", "Record already exists")
+                .field(field, "Value is already taken")
+        }
+        ER_ROW_IS_REFERENCED_2 => ErrorParts::new(
+            StatusCode::CONFLICT,
+            "still_referenced",
+            "Record is still referenced by other records",
+        ),
+        ER_NO_REFERENCED_ROW_2 => ErrorParts::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_reference",
+            "Referenced record does not exist",
+        )
+        .field(
+            between(message, "FOREIGN KEY (`", '`'),
+            "Referenced record does not exist",
+        ),
+        ER_BAD_NULL_ERROR => ErrorParts::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            "Required value is missing",
+        )
+        .field(between(message, "Column '", '\''), "Value is required"),
+        ER_DATA_TOO_LONG => ErrorParts::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            "Value is too long",
+        )
+        .field(between(message, "column '", '\''), "Value is too long"),
+        ER_CHECK_CONSTRAINT_VIOLATED | ER_CONSTRAINT_FAILED => ErrorParts::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "constraint_violation",
+            "Value is out of the allowed range",
+        ),
+        _ => {
+            eprintln!("Database error: {}", error);
+            ErrorParts::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+                "Database error",
+            )
+        }
+    }
+}
+
+impl AppError {
+    fn parts(&self) -> ErrorParts {
+        match self {
             //System
-            AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database error"),
-            AppError::Redis(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Redis error"),
-            AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
+            AppError::Database(e) => database_error(e),
+            AppError::Redis(e) => {
+                eprintln!("Redis error: {}", e);
+                ErrorParts::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "redis_error",
+                    "Redis error",
+                )
+            }
+            AppError::Internal => ErrorParts::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+                "Internal server error",
+            ),
             //Authorization
-            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unathorized"),
-            AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AppError::Unauthorized => {
+                ErrorParts::new(StatusCode::UNAUTHORIZED, "unauthorized", "Unauthorized")
+            }
+            AppError::InvalidCredentials => ErrorParts::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_credentials",
+                "Invalid login or password",
+            ),
+            AppError::Forbidden => ErrorParts::new(StatusCode::FORBIDDEN, "forbidden", "Forbidden"),
+            AppError::PasswordChangeRequired => ErrorParts::new(
+                StatusCode::FORBIDDEN,
+                "password_change_required",
+                "Password change required",
+            ),
+            AppError::MfaSetupRequired => ErrorParts::new(
+                StatusCode::FORBIDDEN,
+                "mfa_setup_required",
+                "Two-factor authentication setup required",
+            ),
+            AppError::ReadOnlySession => ErrorParts::new(
+                StatusCode::FORBIDDEN,
+                "read_only_session",
+                "Impersonation session is read-only",
+            ),
             //Client errors
-            AppError::NotFound => (StatusCode::NOT_FOUND, "Not Found"),
-            AppError::Conflict => (StatusCode::CONFLICT, "Conflict"),
-            AppError::Validation(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg.as_str()),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            AppError::NotFound => ErrorParts::new(StatusCode::NOT_FOUND, "not_found", "Not Found"),
+            AppError::Conflict => ErrorParts::new(StatusCode::CONFLICT, "conflict", "Conflict"),
+            AppError::VersionConflict => ErrorParts::new(
+                StatusCode::CONFLICT,
+                "version_conflict",
+                "Record was changed by another edit",
+            )
+            .field(Some("version"), "Reload the record and retry"),
+            AppError::RequestInProgress => ErrorParts::new(
+                StatusCode::CONFLICT,
+                "request_in_progress",
+                "Request with this idempotency key is still in progress",
+            ),
+            AppError::IdempotencyKeyReused => ErrorParts::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "idempotency_key_reused",
+                "Idempotency key was used for another request",
+            ),
+            AppError::Validation(msg) => {
+                ErrorParts::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", msg)
+            }
+            AppError::FieldValidation(details) => ErrorParts {
+                status: StatusCode::UNPROCESSABLE_ENTITY,
+                code: "validation_failed",
+                message: String::from("Validation failed"),
+                details: details.clone(),
+            },
+            AppError::BadRequest(msg) => {
+                ErrorParts::new(StatusCode::BAD_REQUEST, "bad_request", msg)
+            }
             //Restrictions
-            AppError::RateLimit => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
-            AppError::Timeout => (StatusCode::REQUEST_TIMEOUT, "Request timeout"),
-        };
+            AppError::RateLimit(_) => ErrorParts::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                "Too many requests",
+            ),
+            AppError::Timeout => {
+                ErrorParts::new(StatusCode::REQUEST_TIMEOUT, "timeout", "Request timeout")
+            }
+        }
+    }
+
+    /// Same message and code as the JSON error body, carried in the GraphQL error extensions.
+    pub fn into_graphql(self) -> async_graphql::Error {
+        let parts = self.parts();
+        async_graphql::Error::new(parts.message).extend_with(|_, extensions| {
+            extensions.set("code", parts.code);
+            if !parts.details.is_empty()
+                && let Ok(details) = async_graphql::to_value(&parts.details)
+            {
+                extensions.set("details", details);
+            }
+        })
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let parts = self.parts();
 
         let body = Json(ErrorResponse {
-            error: message.to_string(),
+            error: parts.message,
+            code: parts.code.to_string(),
+            details: parts.details,
+            request_id: REQUEST_ID.try_with(String::clone).ok(),
         });
 
-        (status, body).into_response()
+        let mut response = (parts.status, body).into_response();
+        if let AppError::RateLimit(retry_after) = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+
+        response
     }
 }
+
+/// Makes the `x-request-id` set by the request id layer available to error responses.
+pub async fn request_id_middleware(req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    REQUEST_ID.scope(request_id, next.run(req)).await
+}
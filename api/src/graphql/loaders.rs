@@ -0,0 +1,221 @@
+use async_graphql::dataloader::Loader;
+use chrono::Local;
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    db::DBState,
+    graphql::IntoGraphql,
+    models::{Group, ScheduleChange, ScheduleRow, Subject, TeacherLink, TeacherSafe},
+    traits::{Groups, ScheduleChanges, Schedules, Subjects, TeacherLinks, Teachers},
+};
+
+/// Batches the lookups of one GraphQL request into a query per key type.
+pub struct DbLoader(pub DBState);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupId(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubjectId(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TeacherId(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PairId(pub i64);
+
+/// Upcoming change of a pair, keyed by the pair id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PairChange(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupSubjects(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupLinks(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupPairs(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupChanges(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TeacherPairs(pub i64);
+
+fn ids<K>(keys: &[K], id: impl Fn(&K) -> i64) -> Vec<i64> {
+    keys.iter().map(id).collect()
+}
+
+fn by_key<K: Hash + Eq, T>(rows: Vec<T>, key: impl Fn(&T) -> K) -> HashMap<K, T> {
+    rows.into_iter().map(|row| (key(&row), row)).collect()
+}
+
+fn grouped<K: Hash + Eq, T>(rows: Vec<T>, key: impl Fn(&T) -> K) -> HashMap<K, Vec<T>> {
+    let mut grouped: HashMap<K, Vec<T>> = HashMap::new();
+    for row in rows {
+        grouped.entry(key(&row)).or_default().push(row);
+    }
+    grouped
+}
+
+impl Loader<GroupId> for DbLoader {
+    type Value = Group;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[GroupId]) -> Result<HashMap<GroupId, Group>, Self::Error> {
+        let groups = self
+            .0
+            .get_groups_by_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(by_key(groups, |g| GroupId(g.id)))
+    }
+}
+
+impl Loader<SubjectId> for DbLoader {
+    type Value = Subject;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[SubjectId]) -> Result<HashMap<SubjectId, Subject>, Self::Error> {
+        let subjects = self
+            .0
+            .get_subjects_by_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(by_key(subjects, |s| SubjectId(s.id)))
+    }
+}
+
+impl Loader<TeacherId> for DbLoader {
+    type Value = TeacherSafe;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[TeacherId],
+    ) -> Result<HashMap<TeacherId, TeacherSafe>, Self::Error> {
+        let teachers = self
+            .0
+            .get_teachers_by_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(by_key(teachers, |t| TeacherId(t.id)))
+    }
+}
+
+impl Loader<PairId> for DbLoader {
+    type Value = ScheduleRow;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[PairId]) -> Result<HashMap<PairId, ScheduleRow>, Self::Error> {
+        let pairs = self
+            .0
+            .get_pairs_by_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(by_key(pairs, |p| PairId(p.id)))
+    }
+}
+
+impl Loader<PairChange> for DbLoader {
+    type Value = ScheduleChange;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[PairChange],
+    ) -> Result<HashMap<PairChange, ScheduleChange>, Self::Error> {
+        let changes = self
+            .0
+            .get_changes_by_schedule_ids(&ids(keys, |k| k.0), Local::now().date_naive())
+            .await
+            .into_graphql()?;
+        Ok(by_key(changes, |c| PairChange(c.schedule_id)))
+    }
+}
+
+impl Loader<GroupSubjects> for DbLoader {
+    type Value = Vec<Subject>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[GroupSubjects],
+    ) -> Result<HashMap<GroupSubjects, Vec<Subject>>, Self::Error> {
+        let subjects = self
+            .0
+            .get_subjects_by_group_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(grouped(subjects, |s| GroupSubjects(s.group_id)))
+    }
+}
+
+impl Loader<GroupLinks> for DbLoader {
+    type Value = Vec<TeacherLink>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[GroupLinks],
+    ) -> Result<HashMap<GroupLinks, Vec<TeacherLink>>, Self::Error> {
+        let links = self
+            .0
+            .get_teacher_links_by_group_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(grouped(links, |l| GroupLinks(l.group_id)))
+    }
+}
+
+impl Loader<GroupPairs> for DbLoader {
+    type Value = Vec<ScheduleRow>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[GroupPairs],
+    ) -> Result<HashMap<GroupPairs, Vec<ScheduleRow>>, Self::Error> {
+        let pairs = self
+            .0
+            .get_pairs_by_group_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(grouped(pairs, |p| GroupPairs(p.group_id)))
+    }
+}
+
+impl Loader<GroupChanges> for DbLoader {
+    type Value = Vec<ScheduleChange>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[GroupChanges],
+    ) -> Result<HashMap<GroupChanges, Vec<ScheduleChange>>, Self::Error> {
+        let changes = self
+            .0
+            .get_changes_by_group_ids(&ids(keys, |k| k.0), Local::now().date_naive())
+            .await
+            .into_graphql()?;
+        Ok(grouped(changes, |c| GroupChanges(c.group_id)))
+    }
+}
+
+impl Loader<TeacherPairs> for DbLoader {
+    type Value = Vec<ScheduleRow>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[TeacherPairs],
+    ) -> Result<HashMap<TeacherPairs, Vec<ScheduleRow>>, Self::Error> {
+        let pairs = self
+            .0
+            .get_pairs_by_teacher_ids(&ids(keys, |k| k.0))
+            .await
+            .into_graphql()?;
+        Ok(grouped(pairs, |p| TeacherPairs(p.teacher_id)))
+    }
+}
@@ -0,0 +1,128 @@
+pub mod loaders;
+pub mod mutation;
+pub mod query;
+pub mod types;
+
+use async_graphql::{Context, EmptySubscription, Guard, Schema, dataloader::DataLoader};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{
+    Extension,
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+};
+use std::convert::Infallible;
+
+use crate::{
+    auth::{GroupScope, Permission},
+    config::AppState,
+    errors::AppError,
+    models::{ApiKey, Session, Teacher},
+    services::audit::AuditContext,
+};
+use loaders::DbLoader;
+use mutation::MutationRoot;
+use query::QueryRoot;
+
+/// Deepest selection accepted, the relations loop back onto each other.
+const MAX_DEPTH: usize = 12;
+
+pub type StudyLineSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+pub type Loaders = DataLoader<DbLoader>;
+
+pub fn build_schema() -> StudyLineSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// Turns errors of the trait layer and of the REST handlers into GraphQL errors.
+pub trait IntoGraphql<T> {
+    fn into_graphql(self) -> async_graphql::Result<T>;
+}
+
+impl<T, E: Into<AppError>> IntoGraphql<T> for Result<T, E> {
+    fn into_graphql(self) -> async_graphql::Result<T> {
+        self.map_err(|e| AppError::into_graphql(e.into()))
+    }
+}
+
+/// What the account behind a request may do. One endpoint serves every operation, so
+/// guarded fields and mutations check it themselves instead of relying on `require_permission`.
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    pub permissions: Vec<Permission>,
+    pub read_only: bool,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Viewer {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let permissions = if let Some(teacher) = parts.extensions.get::<Teacher>() {
+            teacher.role.permissions().to_vec()
+        } else if let Some(key) = parts.extensions.get::<ApiKey>() {
+            key.scopes.clone()
+        } else {
+            Vec::new()
+        };
+        let read_only = parts
+            .extensions
+            .get::<Session>()
+            .is_some_and(|s| s.read_only);
+
+        Ok(Self {
+            permissions,
+            read_only,
+        })
+    }
+}
+
+/// Guards a read that needs a permission on REST as well.
+pub struct ViewGuard(pub Permission);
+
+impl Guard for ViewGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if !ctx.data::<Viewer>()?.permissions.contains(&self.0) {
+            return Err(AppError::Forbidden.into_graphql());
+        }
+        Ok(())
+    }
+}
+
+/// Guards a mutation, which read-only sessions can't run either.
+pub struct PermissionGuard(pub Permission);
+
+impl Guard for PermissionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        ViewGuard(self.0).check(ctx).await?;
+        let viewer = ctx.data::<Viewer>()?;
+        // Queries of impersonation sessions come in over GET, this stops mutations there.
+        if viewer.read_only {
+            return Err(AppError::ReadOnlySession.into_graphql());
+        }
+        Ok(())
+    }
+}
+
+/// Serves the schema over GET and POST. Loaders are created per request, so batched
+/// lookups are cached for one request only.
+pub async fn graphql(
+    State(app_state): State<AppState>,
+    Extension(schema): Extension<StudyLineSchema>,
+    Extension(scope): Extension<GroupScope>,
+    viewer: Viewer,
+    audit: AuditContext,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let loaders = DataLoader::new(DbLoader(app_state.db.clone()), tokio::spawn);
+    let request = request
+        .into_inner()
+        .data(loaders)
+        .data(app_state)
+        .data(scope)
+        .data(viewer)
+        .data(audit);
+
+    schema.execute(request).await.into()
+}
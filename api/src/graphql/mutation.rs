@@ -0,0 +1,224 @@
+//! Mutations run the REST handlers of the same operations, so validation, cache
+//! invalidation, live events, notices and the audit trail stay identical.
+
+use async_graphql::{Context, Object, Result};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+
+use crate::{
+    auth::{GroupScope, Permission},
+    config::AppState,
+    graphql::{IntoGraphql, PermissionGuard},
+    models::{
+        AddGroupRequest, AddScheduleRequest, AddSubjectRequest, EditGroupRequest,
+        EditSubjectRequest, Group, Schedule, ScheduleChange, ScheduleRow, Subject, TeacherLink,
+    },
+    routes,
+    services::audit::AuditContext,
+    traits::Schedules,
+};
+
+fn state(ctx: &Context<'_>) -> Result<State<AppState>> {
+    Ok(State(ctx.data::<AppState>()?.clone()))
+}
+
+fn scope(ctx: &Context<'_>) -> Result<Extension<GroupScope>> {
+    Ok(Extension(ctx.data::<GroupScope>()?.clone()))
+}
+
+fn audit(ctx: &Context<'_>) -> Result<AuditContext> {
+    Ok(ctx.data::<AuditContext>()?.clone())
+}
+
+/// Pairs of the group as stored, the handlers return days without row versions.
+async fn group_pairs(ctx: &Context<'_>, group_id: i64) -> Result<Vec<ScheduleRow>> {
+    ctx.data::<AppState>()?
+        .db
+        .get_pairs_by_group_ids(&[group_id])
+        .await
+        .into_graphql()
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    #[graphql(guard = "PermissionGuard(Permission::ManageGroups)")]
+    async fn add_group(&self, ctx: &Context<'_>, input: AddGroupRequest) -> Result<Group> {
+        let Json(group) = routes::groups::add_group(state(ctx)?, audit(ctx)?, Json(input))
+            .await
+            .into_graphql()?;
+        Ok(group)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageGroups)")]
+    async fn edit_group(&self, ctx: &Context<'_>, input: EditGroupRequest) -> Result<Group> {
+        let Json(group) = routes::groups::edit_group(state(ctx)?, audit(ctx)?, Json(input))
+            .await
+            .into_graphql()?;
+        Ok(group)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageGroups)")]
+    async fn delete_group(&self, ctx: &Context<'_>, id: i64) -> Result<bool> {
+        let Json(_) = routes::groups::delete_group(state(ctx)?, audit(ctx)?, Path(id))
+            .await
+            .into_graphql()?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageSubjects)")]
+    async fn add_subject(&self, ctx: &Context<'_>, input: AddSubjectRequest) -> Result<Subject> {
+        let Json(subject) =
+            routes::subjects::add_subject(state(ctx)?, scope(ctx)?, audit(ctx)?, Json(input))
+                .await
+                .into_graphql()?;
+        Ok(subject)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageSubjects)")]
+    async fn edit_subject(&self, ctx: &Context<'_>, input: EditSubjectRequest) -> Result<Subject> {
+        let Json(subject) =
+            routes::subjects::edit_subject(state(ctx)?, scope(ctx)?, audit(ctx)?, Json(input))
+                .await
+                .into_graphql()?;
+        Ok(subject)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageSubjects)")]
+    async fn delete_subject(&self, ctx: &Context<'_>, id: i64) -> Result<bool> {
+        let Json(_) =
+            routes::subjects::delete_subject(state(ctx)?, scope(ctx)?, audit(ctx)?, Path(id))
+                .await
+                .into_graphql()?;
+        Ok(true)
+    }
+
+    /// Returns every pair of the group after the insert
+    #[graphql(guard = "PermissionGuard(Permission::EditSchedule)")]
+    async fn add_pairs(
+        &self,
+        ctx: &Context<'_>,
+        input: AddScheduleRequest,
+    ) -> Result<Vec<ScheduleRow>> {
+        let group_id = input.group_id;
+        let Json(_) =
+            routes::schedule::add_pairs(state(ctx)?, scope(ctx)?, audit(ctx)?, Json(input))
+                .await
+                .into_graphql()?;
+        group_pairs(ctx, group_id).await
+    }
+
+    /// Returns every pair of the group after the update
+    #[graphql(guard = "PermissionGuard(Permission::EditSchedule)")]
+    async fn edit_pairs(&self, ctx: &Context<'_>, input: Schedule) -> Result<Vec<ScheduleRow>> {
+        let group_id = input.group_id;
+        let Json(_) =
+            routes::schedule::edit_pairs(state(ctx)?, scope(ctx)?, audit(ctx)?, Json(input))
+                .await
+                .into_graphql()?;
+        group_pairs(ctx, group_id).await
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::EditSchedule)")]
+    async fn delete_pair(&self, ctx: &Context<'_>, id: i64) -> Result<bool> {
+        let Json(_) =
+            routes::schedule::delete_pair(state(ctx)?, scope(ctx)?, audit(ctx)?, Path(id))
+                .await
+                .into_graphql()?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::EditSchedule)")]
+    async fn delete_day(&self, ctx: &Context<'_>, group_id: i64, weekday: i8) -> Result<bool> {
+        let Json(_) = routes::schedule::delete_day(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Path((group_id, weekday)),
+        )
+        .await
+        .into_graphql()?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::EditScheduleChanges)")]
+    async fn add_schedule_changes(
+        &self,
+        ctx: &Context<'_>,
+        input: Vec<ScheduleChange>,
+    ) -> Result<Vec<ScheduleChange>> {
+        let Json(changes) = routes::schedule_changes::add_schedule_changes(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Json(input),
+        )
+        .await
+        .into_graphql()?;
+        Ok(changes)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::EditScheduleChanges)")]
+    async fn edit_schedule_change(
+        &self,
+        ctx: &Context<'_>,
+        input: ScheduleChange,
+    ) -> Result<ScheduleChange> {
+        let Json(change) = routes::schedule_changes::edit_schedule_changes(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Json(input),
+        )
+        .await
+        .into_graphql()?;
+        Ok(change)
+    }
+
+    /// Removes the changes of the given pairs
+    #[graphql(guard = "PermissionGuard(Permission::EditScheduleChanges)")]
+    async fn delete_schedule_changes(
+        &self,
+        ctx: &Context<'_>,
+        schedule_ids: Vec<i64>,
+    ) -> Result<bool> {
+        let Json(_) = routes::schedule_changes::delete_schedule_changes(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Json(schedule_ids),
+        )
+        .await
+        .into_graphql()?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageTeacherLinks)")]
+    async fn add_teacher_link(&self, ctx: &Context<'_>, input: TeacherLink) -> Result<TeacherLink> {
+        let Json(link) = routes::teacher_links::add_teacher_link(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Json(input),
+        )
+        .await
+        .into_graphql()?;
+        Ok(link)
+    }
+
+    #[graphql(guard = "PermissionGuard(Permission::ManageTeacherLinks)")]
+    async fn delete_teacher_link(&self, ctx: &Context<'_>, input: TeacherLink) -> Result<bool> {
+        let Json(_) = routes::teacher_links::delete_teacher_link(
+            state(ctx)?,
+            scope(ctx)?,
+            audit(ctx)?,
+            Json(input),
+        )
+        .await
+        .into_graphql()?;
+        Ok(true)
+    }
+}
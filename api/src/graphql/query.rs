@@ -0,0 +1,47 @@
+use async_graphql::{Context, Object, Result};
+
+use crate::{
+    auth::Permission,
+    config::AppState,
+    graphql::{
+        IntoGraphql, Loaders, ViewGuard,
+        loaders::{GroupId, PairId, SubjectId, TeacherId},
+    },
+    models::{Group, ScheduleRow, Subject, TeacherSafe},
+    traits::{Groups, Teachers},
+};
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn groups(&self, ctx: &Context<'_>) -> Result<Vec<Group>> {
+        ctx.data::<AppState>()?.db.get_groups().await.into_graphql()
+    }
+
+    async fn group(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Group>> {
+        ctx.data::<Loaders>()?.load_one(GroupId(id)).await
+    }
+
+    #[graphql(guard = "ViewGuard(Permission::ViewTeachers)")]
+    async fn teachers(&self, ctx: &Context<'_>) -> Result<Vec<TeacherSafe>> {
+        ctx.data::<AppState>()?
+            .db
+            .get_teachers()
+            .await
+            .into_graphql()
+    }
+
+    #[graphql(guard = "ViewGuard(Permission::ViewTeachers)")]
+    async fn teacher(&self, ctx: &Context<'_>, id: i64) -> Result<Option<TeacherSafe>> {
+        ctx.data::<Loaders>()?.load_one(TeacherId(id)).await
+    }
+
+    async fn subject(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Subject>> {
+        ctx.data::<Loaders>()?.load_one(SubjectId(id)).await
+    }
+
+    async fn pair(&self, ctx: &Context<'_>, id: i64) -> Result<Option<ScheduleRow>> {
+        ctx.data::<Loaders>()?.load_one(PairId(id)).await
+    }
+}
@@ -0,0 +1,282 @@
+use async_graphql::{Context, Object, Result};
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::{
+    auth::{Permission, Role},
+    graphql::{
+        Loaders, ViewGuard,
+        loaders::{
+            GroupChanges, GroupId, GroupLinks, GroupPairs, GroupSubjects, PairChange, PairId,
+            SubjectId, TeacherId, TeacherPairs,
+        },
+    },
+    models::{Group, ScheduleChange, ScheduleRow, Subject, TeacherLink, TeacherSafe},
+};
+
+async fn group(ctx: &Context<'_>, id: i64) -> Result<Option<Group>> {
+    ctx.data::<Loaders>()?.load_one(GroupId(id)).await
+}
+
+async fn subject(ctx: &Context<'_>, id: i64) -> Result<Option<Subject>> {
+    ctx.data::<Loaders>()?.load_one(SubjectId(id)).await
+}
+
+async fn teacher(ctx: &Context<'_>, id: i64) -> Result<Option<TeacherSafe>> {
+    ctx.data::<Loaders>()?.load_one(TeacherId(id)).await
+}
+
+#[Object]
+impl Group {
+    async fn id(&self) -> i64 {
+        self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn shift(&self) -> i8 {
+        self.shift
+    }
+
+    async fn version(&self) -> i32 {
+        self.version
+    }
+
+    async fn subjects(&self, ctx: &Context<'_>) -> Result<Vec<Subject>> {
+        let subjects = ctx
+            .data::<Loaders>()?
+            .load_one(GroupSubjects(self.id))
+            .await?;
+        Ok(subjects.unwrap_or_default())
+    }
+
+    async fn teacher_links(&self, ctx: &Context<'_>) -> Result<Vec<TeacherLink>> {
+        let links = ctx.data::<Loaders>()?.load_one(GroupLinks(self.id)).await?;
+        Ok(links.unwrap_or_default())
+    }
+
+    /// Pairs of the week, or of one weekday (1 to 7) when it is given
+    async fn pairs(&self, ctx: &Context<'_>, weekday: Option<i8>) -> Result<Vec<ScheduleRow>> {
+        let mut pairs = ctx
+            .data::<Loaders>()?
+            .load_one(GroupPairs(self.id))
+            .await?
+            .unwrap_or_default();
+        if let Some(weekday) = weekday {
+            pairs.retain(|p| p.weekday == weekday);
+        }
+        Ok(pairs)
+    }
+
+    /// Changes dated today or later
+    async fn changes(&self, ctx: &Context<'_>) -> Result<Vec<ScheduleChange>> {
+        let changes = ctx
+            .data::<Loaders>()?
+            .load_one(GroupChanges(self.id))
+            .await?;
+        Ok(changes.unwrap_or_default())
+    }
+}
+
+#[Object]
+impl Subject {
+    async fn id(&self) -> i64 {
+        self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn group_id(&self) -> i64 {
+        self.group_id
+    }
+
+    async fn version(&self) -> i32 {
+        self.version
+    }
+
+    async fn group(&self, ctx: &Context<'_>) -> Result<Option<Group>> {
+        group(ctx, self.group_id).await
+    }
+}
+
+#[Object(name = "Teacher")]
+impl TeacherSafe {
+    async fn id(&self) -> i64 {
+        self.id
+    }
+
+    async fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    #[graphql(guard = "ViewGuard(Permission::ViewTeachers)")]
+    async fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Pairs the teacher holds over the week, across all groups
+    async fn week(&self, ctx: &Context<'_>) -> Result<Vec<ScheduleRow>> {
+        let pairs = ctx
+            .data::<Loaders>()?
+            .load_one(TeacherPairs(self.id))
+            .await?;
+        Ok(pairs.unwrap_or_default())
+    }
+}
+
+#[Object(name = "Pair")]
+impl ScheduleRow {
+    async fn id(&self) -> i64 {
+        self.id
+    }
+
+    async fn pair_number(&self) -> i8 {
+        self.pair_number
+    }
+
+    async fn weekday(&self) -> i8 {
+        self.weekday
+    }
+
+    /// Empty when the whole group attends the pair
+    async fn subgroup(&self) -> Option<i8> {
+        self.subgroup
+    }
+
+    async fn start_time(&self) -> NaiveTime {
+        self.start_time
+    }
+
+    async fn end_time(&self) -> NaiveTime {
+        self.end_time
+    }
+
+    async fn cabinet(&self) -> &str {
+        &self.cabinet
+    }
+
+    async fn group_id(&self) -> i64 {
+        self.group_id
+    }
+
+    async fn subject_id(&self) -> i64 {
+        self.subject_id
+    }
+
+    async fn teacher_id(&self) -> i64 {
+        self.teacher_id
+    }
+
+    async fn version(&self) -> i32 {
+        self.version
+    }
+
+    async fn group(&self, ctx: &Context<'_>) -> Result<Option<Group>> {
+        group(ctx, self.group_id).await
+    }
+
+    async fn subject(&self, ctx: &Context<'_>) -> Result<Option<Subject>> {
+        subject(ctx, self.subject_id).await
+    }
+
+    async fn teacher(&self, ctx: &Context<'_>) -> Result<Option<TeacherSafe>> {
+        teacher(ctx, self.teacher_id).await
+    }
+
+    /// Upcoming change replacing the pair
+    async fn change(&self, ctx: &Context<'_>) -> Result<Option<ScheduleChange>> {
+        ctx.data::<Loaders>()?.load_one(PairChange(self.id)).await
+    }
+}
+
+#[Object]
+impl ScheduleChange {
+    async fn schedule_id(&self) -> i64 {
+        self.schedule_id
+    }
+
+    async fn group_id(&self) -> i64 {
+        self.group_id
+    }
+
+    async fn new_subject_id(&self) -> i64 {
+        self.new_subject_id
+    }
+
+    async fn new_teacher_id(&self) -> i64 {
+        self.new_teacher_id
+    }
+
+    async fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    async fn new_start_time(&self) -> NaiveTime {
+        self.new_start_time
+    }
+
+    async fn new_end_time(&self) -> NaiveTime {
+        self.new_end_time
+    }
+
+    async fn cabinet(&self) -> &str {
+        &self.cabinet
+    }
+
+    async fn is_canceled(&self) -> bool {
+        self.is_canceled
+    }
+
+    async fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    /// The pair as it stands in the regular schedule
+    async fn pair(&self, ctx: &Context<'_>) -> Result<Option<ScheduleRow>> {
+        ctx.data::<Loaders>()?
+            .load_one(PairId(self.schedule_id))
+            .await
+    }
+
+    async fn group(&self, ctx: &Context<'_>) -> Result<Option<Group>> {
+        group(ctx, self.group_id).await
+    }
+
+    async fn new_subject(&self, ctx: &Context<'_>) -> Result<Option<Subject>> {
+        subject(ctx, self.new_subject_id).await
+    }
+
+    async fn new_teacher(&self, ctx: &Context<'_>) -> Result<Option<TeacherSafe>> {
+        teacher(ctx, self.new_teacher_id).await
+    }
+}
+
+#[Object]
+impl TeacherLink {
+    async fn group_id(&self) -> i64 {
+        self.group_id
+    }
+
+    async fn subject_id(&self) -> i64 {
+        self.subject_id
+    }
+
+    async fn teacher_id(&self) -> i64 {
+        self.teacher_id
+    }
+
+    async fn group(&self, ctx: &Context<'_>) -> Result<Option<Group>> {
+        group(ctx, self.group_id).await
+    }
+
+    async fn subject(&self, ctx: &Context<'_>) -> Result<Option<Subject>> {
+        subject(ctx, self.subject_id).await
+    }
+
+    async fn teacher(&self, ctx: &Context<'_>) -> Result<Option<TeacherSafe>> {
+        teacher(ctx, self.teacher_id).await
+    }
+}
@@ -2,27 +2,47 @@ mod auth;
 mod config;
 mod db;
 mod errors;
+mod graphql;
 mod models;
 mod redis;
 mod routes;
 mod services;
 mod swagger;
+mod telegram;
 mod traits;
 mod utils;
 
-use auth::{auth_middleware, require_role};
+use auth::{
+    Permission, auth_middleware, middleware::IMPERSONATED_BY_HEADER, require_permission,
+    require_staff,
+};
 use axum::http::{
-    HeaderValue,
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    HeaderName, HeaderValue,
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH},
 };
 use axum::routing::{delete, get, patch, post};
-use axum::{Router, middleware};
+use axum::{Extension, Router, middleware};
 use sqlx::migrate;
-use tokio;
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
-use tower_http::cors::CorsLayer;
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+};
 
-use services::notifications::FCM;
+use services::{
+    digest::spawn_daily_digest,
+    email::Mailer,
+    encoding::negotiate_encoding,
+    idempotency::{IDEMPOTENCY_KEY_HEADER, REPLAYED_HEADER, idempotency},
+    listing::TOTAL_COUNT_HEADER,
+    live::{Live, spawn_live_relay},
+    notifications::Fcm,
+    oidc::Oidc,
+    rate_limit::{client_address, rate_limit},
+    sync::spawn_sync_purge,
+};
+use telegram::{TelegramBot, spawn_polling};
 use {config::AppState, config::Config, db::DBState, redis::RedisState};
 
 #[tokio::main]
@@ -31,182 +51,529 @@ async fn main() {
     let app_state = AppState {
         db: DBState::init_pool(&config.database_url).await.unwrap(),
         redis: RedisState::init(&config.redis_address).await.unwrap(),
-        fcm: FCM::init(),
+        fcm: Fcm::init(),
+        mailer: config.smtp.as_ref().map(|smtp| Mailer::init(smtp).unwrap()),
+        telegram: config.telegram.as_ref().map(TelegramBot::init),
+        limits: config.limits.clone(),
+        password_policy: config.password_policy.clone(),
+        mfa: config.mfa.clone(),
+        oidc: match &config.oidc {
+            Some(oidc) => Some(Oidc::init(oidc).await.unwrap()),
+            None => None,
+        },
+        live: Live::init(),
     };
 
-    migrate!("src/migrations").run(&app_state.db.db).await;
+    if let Err(e) = migrate!("src/migrations").run(&app_state.db.db).await {
+        eprintln!("Failed to run migrations: {}", e);
+        std::process::exit(1);
+    }
+
+    spawn_daily_digest(app_state.clone(), config.digest_hour);
+    spawn_polling(app_state.clone());
+    spawn_live_relay(app_state.clone());
+    spawn_sync_purge(app_state.clone());
 
     let cors = CorsLayer::new()
         .allow_origin("http://127.0.0.1:3000".parse::<HeaderValue>().unwrap())
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE, ACCEPT])
+        .allow_headers([
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            ACCEPT,
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
+            HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+        ])
         .allow_methods([
             axum::http::Method::GET,
             axum::http::Method::POST,
+            axum::http::Method::PUT,
             axum::http::Method::PATCH,
             axum::http::Method::DELETE,
         ])
+        .expose_headers([
+            HeaderName::from_static(IMPERSONATED_BY_HEADER),
+            HeaderName::from_static(TOTAL_COUNT_HEADER),
+            HeaderName::from_static(REPLAYED_HEADER),
+            ETAG,
+        ])
         .allow_credentials(true);
 
-    let protected_routes: Router =
-        Router::new()
-            //FCM
-            .route(
-                "/send_notifications_to_group",
-                post(routes::fcm::send_notifications_to_group).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/send_notifications_to_teachers",
-                post(routes::fcm::send_notifications_to_teachers).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            //TEACHER LINKS ROUTES
-            .route(
-                "/add_teacher_link",
-                post(routes::teacher_links::add_teacher_link).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/delete_teacher_link",
-                delete(routes::teacher_links::delete_teacher_link).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            //SCHEDULE CHANGES ROUTES
-            .route(
-                "/add_schedule_changes",
-                post(routes::schedule_changes::add_schedule_changes).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/delete_schedule_changes",
-                delete(routes::schedule_changes::delete_schedule_changes).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/edit_schedule_changes",
-                patch(routes::schedule_changes::edit_schedule_changes).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            //SCHEDULE ROUTES
-            .route(
-                "/delete_day/{group_id}/{weekday}",
-                delete(routes::schedule::delete_day).route_layer(middleware::from_fn_with_state(
-                    app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/delete_pair/{schedule_id}",
-                delete(routes::schedule::delete_pair).route_layer(middleware::from_fn_with_state(
+    let protected_routes: Router = Router::new()
+        //FCM
+        .route(
+            "/send_notifications_to_group",
+            post(routes::fcm::send_notifications_to_group).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/add_pairs",
-                post(routes::schedule::add_pairs).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::SendNotifications),
+                ),
+            ),
+        )
+        .route(
+            "/send_notifications_to_teachers",
+            post(routes::fcm::send_notifications_to_teachers).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/edit_pairs",
-                patch(routes::schedule::edit_pairs).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::SendNotifications),
+                ),
+            ),
+        )
+        //ME ROUTES
+        .route(
+            "/me",
+            get(routes::me::get_me).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/password",
+            patch(routes::me::change_my_password).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/schedule",
+            get(routes::me::get_my_schedule).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/changes",
+            get(routes::me::get_my_changes).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/notifications",
+            get(routes::me::get_my_notifications)
+                .patch(routes::me::update_my_notifications)
+                .route_layer(middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
+                    require_permission(Permission::SelfService),
                 )),
-            )
-            //TEACHER ROUTES
-            .route(
-                "/add_teacher",
-                post(routes::teachers::add_teacher).route_layer(middleware::from_fn_with_state(
+        )
+        .route(
+            "/me/2fa/setup",
+            post(routes::me::setup_totp).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/2fa/enable",
+            post(routes::me::enable_totp).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/2fa/disable",
+            post(routes::me::disable_totp).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/2fa/recovery_codes",
+            post(routes::me::regenerate_recovery_codes).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/delete_teacher/{teacher_id}",
-                delete(routes::teachers::delete_teacher).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/update_teacher_password",
-                patch(routes::teachers::update_teacher_password).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/update_teacher_login",
-                patch(routes::teachers::update_teacher_login).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/update_teacher_fullname",
-                patch(routes::teachers::update_teacher_fullname).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .route(
-                "/get_teacher_by_id/{teacher_id}",
-                get(routes::teachers::get_teacher_by_id).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            //GROUP ROUTES
-            .route(
-                "/add_group",
-                post(routes::groups::add_group).route_layer(middleware::from_fn_with_state(
-                    app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/edit_group",
-                patch(routes::groups::edit_group).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::SelfService),
+                ),
+            ),
+        )
+        .route(
+            "/me/sessions",
+            get(routes::me::get_my_sessions).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        .route(
+            "/me/sessions/{session_id}",
+            delete(routes::me::revoke_my_session).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::SelfService),
+            )),
+        )
+        //STUDENT ROUTES
+        .route(
+            "/student/me",
+            get(routes::student_me::get_student_profile).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/delete_group/{group_id}",
-                delete(routes::groups::delete_group).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::StudentSelfService),
+                ),
+            ),
+        )
+        .route(
+            "/student/me/schedule",
+            get(routes::student_me::get_student_schedule).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            //SUBJECT ROUTES
-            .route(
-                "/add_subject",
-                post(routes::subjects::add_subject).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::StudentSelfService),
+                ),
+            ),
+        )
+        .route(
+            "/student/me/changes",
+            get(routes::student_me::get_student_changes).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/edit_subject",
-                patch(routes::subjects::edit_subject).route_layer(middleware::from_fn_with_state(
+                    require_permission(Permission::StudentSelfService),
+                ),
+            ),
+        )
+        .route(
+            "/student/me/password",
+            patch(routes::student_me::change_student_password).route_layer(
+                middleware::from_fn_with_state(
                     app_state.clone(),
-                    require_role(&[]),
-                )),
-            )
-            .route(
-                "/delete_subject/{subject_id}",
-                delete(routes::subjects::delete_subject).route_layer(
-                    middleware::from_fn_with_state(app_state.clone(), require_role(&[])),
-                ),
-            )
-            .with_state(app_state.clone());
+                    require_permission(Permission::StudentSelfService),
+                ),
+            ),
+        )
+        .route(
+            "/get_students/{group_id}",
+            get(routes::students::get_students).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageStudents),
+            )),
+        )
+        .route(
+            "/add_students",
+            post(routes::students::add_students).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageStudents),
+            )),
+        )
+        .route(
+            "/edit_student",
+            patch(routes::students::edit_student).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageStudents),
+            )),
+        )
+        .route(
+            "/delete_student/{student_id}",
+            delete(routes::students::delete_student).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageStudents),
+            )),
+        )
+        .route(
+            "/issue_student_password",
+            post(routes::students::issue_student_password).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageStudents),
+                ),
+            ),
+        )
+        .route(
+            "/impersonate",
+            post(routes::impersonation::impersonate).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::Impersonate),
+            )),
+        )
+        //TEACHER LINKS ROUTES
+        .route(
+            "/add_teacher_link",
+            post(routes::teacher_links::add_teacher_link).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeacherLinks),
+                ),
+            ),
+        )
+        .route(
+            "/delete_teacher_link",
+            delete(routes::teacher_links::delete_teacher_link).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeacherLinks),
+                ),
+            ),
+        )
+        //SCHEDULE CHANGES ROUTES
+        .route(
+            "/add_schedule_changes",
+            post(routes::schedule_changes::add_schedule_changes).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::EditScheduleChanges),
+                ),
+            ),
+        )
+        .route(
+            "/delete_schedule_changes",
+            delete(routes::schedule_changes::delete_schedule_changes).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::EditScheduleChanges),
+                ),
+            ),
+        )
+        .route(
+            "/edit_schedule_changes",
+            patch(routes::schedule_changes::edit_schedule_changes).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::EditScheduleChanges),
+                ),
+            ),
+        )
+        //SCHEDULE ROUTES
+        .route(
+            "/delete_day/{group_id}/{weekday}",
+            delete(routes::schedule::delete_day).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::EditSchedule),
+            )),
+        )
+        .route(
+            "/delete_pair/{schedule_id}",
+            delete(routes::schedule::delete_pair).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::EditSchedule),
+            )),
+        )
+        .route(
+            "/add_pairs",
+            post(routes::schedule::add_pairs).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::EditSchedule),
+            )),
+        )
+        .route(
+            "/edit_pairs",
+            patch(routes::schedule::edit_pairs).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::EditSchedule),
+            )),
+        )
+        //TEACHER ROUTES
+        .route(
+            "/add_teacher",
+            post(routes::teachers::add_teacher).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageTeachers),
+            )),
+        )
+        .route(
+            "/delete_teacher/{teacher_id}",
+            delete(routes::teachers::delete_teacher).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageTeachers),
+            )),
+        )
+        .route(
+            "/update_teacher_password",
+            patch(routes::teachers::update_teacher_password).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/update_teacher_login",
+            patch(routes::teachers::update_teacher_login).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/update_teacher_fullname",
+            patch(routes::teachers::update_teacher_fullname).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/update_teacher_email",
+            patch(routes::teachers::update_teacher_email).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/update_teacher_role",
+            patch(routes::teachers::update_teacher_role).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageRoles),
+                ),
+            ),
+        )
+        .route(
+            "/get_roles",
+            get(routes::teachers::get_roles).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageRoles),
+            )),
+        )
+        .route(
+            "/issue_reset_code",
+            post(routes::teachers::issue_reset_code).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageTeachers),
+            )),
+        )
+        .route(
+            "/get_teacher_sessions/{teacher_id}",
+            get(routes::teachers::get_teacher_sessions).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/revoke_teacher_sessions/{teacher_id}",
+            delete(routes::teachers::revoke_teacher_sessions).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageTeachers),
+                ),
+            ),
+        )
+        .route(
+            "/get_audit_log",
+            get(routes::audit::get_audit_log).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ViewAuditLog),
+            )),
+        )
+        .route(
+            "/get_api_keys",
+            get(routes::api_keys::get_api_keys).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageApiKeys),
+            )),
+        )
+        .route(
+            "/add_api_key",
+            post(routes::api_keys::add_api_key).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageApiKeys),
+            )),
+        )
+        .route(
+            "/delete_api_key/{api_key_id}",
+            delete(routes::api_keys::delete_api_key).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageApiKeys),
+            )),
+        )
+        .route(
+            "/get_group_grants/{teacher_id}",
+            get(routes::group_grants::get_group_grants).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageRoles),
+                ),
+            ),
+        )
+        .route(
+            "/add_group_grants",
+            post(routes::group_grants::add_group_grants).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageRoles),
+                ),
+            ),
+        )
+        .route(
+            "/delete_group_grant/{teacher_id}/{group_id}",
+            delete(routes::group_grants::delete_group_grant).route_layer(
+                middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_permission(Permission::ManageRoles),
+                ),
+            ),
+        )
+        .route(
+            "/get_teacher_by_id/{teacher_id}",
+            get(routes::teachers::get_teacher_by_id).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ViewTeachers),
+            )),
+        )
+        //GROUP ROUTES
+        .route(
+            "/add_group",
+            post(routes::groups::add_group).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageGroups),
+            )),
+        )
+        .route(
+            "/edit_group",
+            patch(routes::groups::edit_group).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageGroups),
+            )),
+        )
+        .route(
+            "/delete_group/{group_id}",
+            delete(routes::groups::delete_group).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageGroups),
+            )),
+        )
+        //SUBJECT ROUTES
+        .route(
+            "/add_subject",
+            post(routes::subjects::add_subject).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageSubjects),
+            )),
+        )
+        .route(
+            "/edit_subject",
+            patch(routes::subjects::edit_subject).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageSubjects),
+            )),
+        )
+        .route(
+            "/delete_subject/{subject_id}",
+            delete(routes::subjects::delete_subject).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_permission(Permission::ManageSubjects),
+            )),
+        )
+        //GRAPHQL, permissions are checked per field
+        .route(
+            "/graphql",
+            get(graphql::graphql)
+                .post(graphql::graphql)
+                .route_layer(middleware::from_fn_with_state(
+                    app_state.clone(),
+                    require_staff(),
+                ))
+                .layer(Extension(graphql::build_schema())),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            idempotency,
+        ))
+        .with_state(app_state.clone());
 
-    let app: Router = Router::new()
+    let public_routes = Router::new()
         .route(
             "/get_teacher_links/{group_id}",
             get(routes::teacher_links::get_teacher_links),
@@ -225,23 +592,60 @@ async fn main() {
             "/get_group_by_id/{group_id}",
             get(routes::groups::get_group_by_id),
         )
+        .route(
+            "/get_group_bundle/{group_id}",
+            get(routes::groups::get_group_bundle),
+        )
         .route(
             "/get_subjects_by_group_id/{group_id}",
             get(routes::subjects::get_subjects_by_group_id),
         )
+        .route("/live_events", get(routes::live::live_events))
+        .route("/sync", get(routes::sync::sync))
+        .route_layer(middleware::from_fn(negotiate_encoding))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            rate_limit,
+        ))
+        .with_state(app_state.clone());
+
+    let legacy_routes: Router = Router::new()
         .route("/login", post(auth::handlers::login))
+        .route("/login/2fa", post(auth::handlers::login_mfa))
+        .route("/student/login", post(auth::handlers::student_login))
         .route("/logout", post(auth::handlers::logout))
-        .merge(swagger::swagger_ui())
+        .route("/reset_password", post(auth::handlers::reset_password))
+        .route("/oidc/login", get(auth::handlers::oidc_login))
+        .route("/oidc/callback", get(auth::handlers::oidc_callback))
         .with_state(app_state.clone())
+        .merge(public_routes)
         .merge(protected_routes.route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth_middleware,
-        )))
-        .layer(cors);
+        )));
+
+    let app: Router = legacy_routes
+        .clone()
+        .route_layer(middleware::from_fn(routes::v1::deprecate_legacy))
+        .nest(routes::v1::PREFIX, routes::v1::router(legacy_routes))
+        .merge(swagger::swagger_ui())
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            client_address,
+        ))
+        .layer(middleware::from_fn(errors::request_id_middleware))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(cors)
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-    let listener: TcpListener = TcpListener::bind(format!("0.0.0.0:{}", config.port))
+    let listener: TcpListener = TcpListener::bind(format!("{}:{}", config.ip, config.port))
         .await
         .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
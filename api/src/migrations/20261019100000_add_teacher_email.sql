@@ -0,0 +1 @@
+ALTER TABLE teachers ADD COLUMN email VARCHAR(255) NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE telegram_subscriptions (
+    chat_id BIGINT NOT NULL,
+    group_id BIGINT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (chat_id, group_id),
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+UPDATE teachers SET role='teacher' WHERE role NOT IN ('admin', 'dispatcher', 'curator', 'teacher');
+
+ALTER TABLE teachers MODIFY role ENUM('admin', 'dispatcher', 'curator', 'teacher') NOT NULL DEFAULT 'teacher';
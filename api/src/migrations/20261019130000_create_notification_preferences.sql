@@ -0,0 +1,8 @@
+CREATE TABLE notification_preferences (
+    teacher_id BIGINT PRIMARY KEY,
+    push_changes BOOLEAN NOT NULL DEFAULT TRUE,
+    email_changes BOOLEAN NOT NULL DEFAULT TRUE,
+    email_digest BOOLEAN NOT NULL DEFAULT TRUE,
+
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+ALTER TABLE teachers ADD COLUMN must_change_password BOOL NOT NULL DEFAULT FALSE;
+
+UPDATE teachers SET must_change_password=TRUE
+WHERE login='admin'
+  AND password_hash='$argon2id$v=19$m=19456,t=2,p=1$Ebj+LAv04o5Z5CYGh0CGbQ$uWZGAvXeb5m3xzqKaC6pBKQCyT/fX0rrSkgrtsUtrxw';
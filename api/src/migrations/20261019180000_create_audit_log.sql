@@ -0,0 +1,17 @@
+CREATE TABLE audit_log (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    actor_type ENUM('teacher', 'api_key', 'anonymous') NOT NULL,
+    actor_id BIGINT NULL,
+    actor_name VARCHAR(150) NULL,
+    action VARCHAR(64) NOT NULL,
+    entity VARCHAR(64) NOT NULL,
+    entity_id VARCHAR(64) NULL,
+    before_data LONGTEXT NULL,
+    after_data LONGTEXT NULL,
+    request_id VARCHAR(64) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    INDEX idx_audit_log_entity (entity, entity_id),
+    INDEX idx_audit_log_actor (actor_type, actor_id),
+    INDEX idx_audit_log_created_at (created_at)
+);
@@ -0,0 +1,17 @@
+ALTER TABLE schedule ADD COLUMN subgroup TINYINT NULL CHECK (subgroup BETWEEN 1 AND 9);
+
+CREATE TABLE students (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    group_id BIGINT NOT NULL,
+    subgroup TINYINT NULL CHECK (subgroup BETWEEN 1 AND 9),
+    full_name VARCHAR(150) NOT NULL,
+    login VARCHAR(50) NULL UNIQUE,
+    password_hash VARCHAR(255) NULL,
+    must_change_password BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+    INDEX idx_students_group (group_id, subgroup)
+);
+
+ALTER TABLE audit_log MODIFY actor_type ENUM('teacher', 'api_key', 'student', 'anonymous') NOT NULL;
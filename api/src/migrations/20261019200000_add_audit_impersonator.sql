@@ -0,0 +1,3 @@
+ALTER TABLE audit_log ADD COLUMN impersonator_id BIGINT NULL AFTER actor_name;
+
+CREATE INDEX idx_audit_log_impersonator ON audit_log (impersonator_id);
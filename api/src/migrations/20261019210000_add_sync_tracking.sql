@@ -0,0 +1,30 @@
+ALTER TABLE groups ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_groups_updated (updated_at);
+ALTER TABLE subjects ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_subjects_updated (updated_at);
+ALTER TABLE teachers ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_teachers_updated (updated_at);
+ALTER TABLE schedule ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_schedule_updated (updated_at);
+ALTER TABLE teacher_links ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_teacher_links_updated (updated_at);
+ALTER TABLE schedule_changes ADD COLUMN updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6), ADD INDEX idx_schedule_changes_updated (updated_at);
+
+-- Rows removed through ON DELETE CASCADE don't fire triggers, clients drop those together with the deleted parent.
+CREATE TABLE sync_deletions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    entity ENUM('group', 'subject', 'teacher', 'pair', 'teacher_link', 'change') NOT NULL,
+    entity_key VARCHAR(64) NOT NULL,
+    group_id BIGINT NULL,
+    deleted_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    INDEX idx_sync_deletions_deleted (deleted_at)
+);
+
+CREATE TRIGGER groups_sync_delete AFTER DELETE ON groups FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key, group_id) VALUES ('group', OLD.id, OLD.id);
+CREATE TRIGGER subjects_sync_delete AFTER DELETE ON subjects FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key, group_id) VALUES ('subject', OLD.id, OLD.group_id);
+CREATE TRIGGER teachers_sync_delete AFTER DELETE ON teachers FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key) VALUES ('teacher', OLD.id);
+CREATE TRIGGER schedule_sync_delete AFTER DELETE ON schedule FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key, group_id) VALUES ('pair', OLD.id, OLD.group_id);
+CREATE TRIGGER teacher_links_sync_delete AFTER DELETE ON teacher_links FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key, group_id) VALUES ('teacher_link', CONCAT(OLD.group_id, ':', OLD.subject_id), OLD.group_id);
+CREATE TRIGGER schedule_changes_sync_delete AFTER DELETE ON schedule_changes FOR EACH ROW
+    INSERT INTO sync_deletions (entity, entity_key, group_id) VALUES ('change', OLD.schedule_id, OLD.group_id);
@@ -0,0 +1,4 @@
+ALTER TABLE groups ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE subjects ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE schedule ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE schedule_changes ADD COLUMN version INT NOT NULL DEFAULT 1;
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::{GroupScope, Permission};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<Permission>,
+    /// Empty when the key is not restricted to particular groups
+    pub group_ids: Vec<i64>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|e| e <= Utc::now())
+    }
+
+    pub fn group_scope(&self) -> GroupScope {
+        if self.group_ids.is_empty() {
+            GroupScope::All
+        } else {
+            GroupScope::Groups(self.group_ids.clone())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    #[serde(default)]
+    pub group_ids: Vec<i64>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyCreated {
+    pub key: ApiKey,
+    /// Shown once, only the hash is stored
+    pub token: String,
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorType {
+    Teacher,
+    ApiKey,
+    Student,
+    Anonymous,
+}
+
+impl ActorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActorType::Teacher => "teacher",
+            ActorType::ApiKey => "api_key",
+            ActorType::Student => "student",
+            ActorType::Anonymous => "anonymous",
+        }
+    }
+}
+
+impl TryFrom<String> for ActorType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "teacher" => Ok(ActorType::Teacher),
+            "api_key" => Ok(ActorType::ApiKey),
+            "student" => Ok(ActorType::Student),
+            "anonymous" => Ok(ActorType::Anonymous),
+            _ => Err(format!("Unknown actor type: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_type: ActorType,
+    pub actor_id: Option<i64>,
+    pub actor_name: Option<String>,
+    /// Admin who acted through an impersonation session
+    pub impersonator_id: Option<i64>,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub actor_type: Option<ActorType>,
+    pub actor_id: Option<i64>,
+    pub impersonator_id: Option<i64>,
+    pub action: Option<String>,
+    /// First day to include
+    pub from: Option<NaiveDate>,
+    /// Last day to include
+    pub to: Option<NaiveDate>,
+    pub limit: Option<u32>,
+}
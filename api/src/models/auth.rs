@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Role;
 
 #[derive(Deserialize, ToSchema, Debug)]
 pub struct LoginRequest {
@@ -10,11 +12,27 @@ pub struct LoginRequest {
 #[derive(Serialize, ToSchema)]
 pub struct LoginResponse {
     pub token: String,
-    pub role: String,
+    pub role: Role,
     pub id: i64,
+    pub must_change_password: bool,
+    pub mfa_setup_required: bool,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct LogoutRequest {
     pub token: String,
 }
+
+#[derive(Deserialize, ToSchema)]
+pub struct ResetPasswordRequest {
+    pub login: String,
+    pub code: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct OidcCallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+}
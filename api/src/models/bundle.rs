@@ -0,0 +1,67 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{Group, Subject};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GroupBundleQuery {
+    /// First day to include, today by default
+    pub from: Option<NaiveDate>,
+    /// Last day to include, pairs of every weekday and all upcoming changes by default
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct BundlePair {
+    pub id: i64,
+    pub weekday: i8,
+    pub pair_number: i8,
+    pub subgroup: Option<i8>,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub cabinet: String,
+    pub subject_id: i64,
+    pub subject_name: String,
+    pub teacher_id: i64,
+    pub teacher_name: String,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct BundleChange {
+    pub schedule_id: i64,
+    pub date: NaiveDate,
+    /// Number of the replaced pair
+    pub pair_number: i8,
+    pub is_canceled: bool,
+    /// Empty on canceled changes stored without a replacement
+    pub new_subject_id: Option<i64>,
+    pub new_subject_name: Option<String>,
+    pub new_teacher_id: Option<i64>,
+    pub new_teacher_name: Option<String>,
+    pub new_start_time: Option<NaiveTime>,
+    pub new_end_time: Option<NaiveTime>,
+    pub cabinet: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct BundleTeacherLink {
+    pub subject_id: i64,
+    pub subject_name: String,
+    pub teacher_id: i64,
+    pub teacher_name: String,
+}
+
+/// Everything needed to show a group's timetable, with names resolved.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GroupBundle {
+    pub group: Group,
+    pub subjects: Vec<Subject>,
+    pub teacher_links: Vec<BundleTeacherLink>,
+    /// Pairs of the weekdays in the range, ordered by weekday and number
+    pub pairs: Vec<BundlePair>,
+    /// Changes dated in the range
+    pub changes: Vec<BundleChange>,
+}
@@ -1,16 +1,33 @@
+use async_graphql::InputObject;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, ToSchema)]
 pub struct Group {
     pub id: i64,
     pub name: String,
     pub shift: i8,
+    /// Grows by one on every edit
+    pub version: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
 pub struct AddGroupRequest {
+    #[validate(length(min = 1, max = 100))]
     pub name: String,
+    #[validate(range(min = 1, max = 2))]
     pub shift: i8,
 }
+
+#[derive(Debug, Deserialize, Serialize, ToSchema, Validate, InputObject)]
+pub struct EditGroupRequest {
+    pub id: i64,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(range(min = 1, max = 2))]
+    pub shift: i8,
+    /// Version the edit was made against, a stale one is rejected with 409
+    pub version: Option<i32>,
+}
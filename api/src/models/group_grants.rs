@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+pub struct GroupGrant {
+    pub teacher_id: i64,
+    pub group_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct AddGroupGrantsRequest {
+    pub teacher_id: i64,
+    pub group_ids: Vec<i64>,
+}
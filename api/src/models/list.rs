@@ -0,0 +1,46 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// Paging and ordering accepted by every list endpoint.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Rows to return, everything when omitted
+    pub limit: Option<u32>,
+    /// Rows to skip
+    pub offset: Option<u32>,
+    /// Comma separated fields, a leading `-` sorts descending, e.g. `-shift,name`
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GroupFilter {
+    /// Start of the group name
+    pub name: Option<String>,
+    pub shift: Option<i8>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TeacherFilter {
+    /// Part of the full name
+    pub search: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubjectFilter {
+    /// Part of the subject name
+    pub search: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChangeFilter {
+    /// First day to include
+    pub from: Option<NaiveDate>,
+    /// Last day to include
+    pub to: Option<NaiveDate>,
+}
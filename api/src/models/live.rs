@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::ScheduleChange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveEventKind {
+    ChangeAdded,
+    ChangeEdited,
+    ChangeRemoved,
+    ScheduleUpdated,
+}
+
+impl LiveEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveEventKind::ChangeAdded => "change_added",
+            LiveEventKind::ChangeEdited => "change_edited",
+            LiveEventKind::ChangeRemoved => "change_removed",
+            LiveEventKind::ScheduleUpdated => "schedule_updated",
+        }
+    }
+}
+
+/// Sent as the data of a stream event named after its kind.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LiveEvent {
+    pub kind: LiveEventKind,
+    pub group_id: i64,
+    /// Teachers whose timetable is affected
+    pub teacher_ids: Vec<i64>,
+    /// The change for change events
+    pub change: Option<ScheduleChange>,
+    /// Day of a schedule update
+    pub weekday: Option<i8>,
+}
+
+/// Without parameters every event of the college is streamed.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LiveQuery {
+    pub group_id: Option<i64>,
+    pub teacher_id: Option<i64>,
+}
+
+impl LiveQuery {
+    pub fn matches(&self, event: &LiveEvent) -> bool {
+        self.group_id
+            .is_none_or(|group_id| group_id == event.group_id)
+            && self
+                .teacher_id
+                .is_none_or(|teacher_id| event.teacher_ids.contains(&teacher_id))
+    }
+}
@@ -1,20 +1,54 @@
+pub mod api_keys;
+pub mod audit;
 pub mod auth;
+pub mod bundle;
 pub mod fcm;
 pub mod group;
+pub mod group_grants;
+pub mod list;
+pub mod live;
+pub mod mfa;
+pub mod notifications;
 pub mod schedule;
 pub mod schedule_changes;
+pub mod session;
+pub mod student;
 pub mod subject;
+pub mod sync;
 pub mod teacher;
 pub mod teacher_links;
+pub mod telegram;
 
-pub use auth::{LoginRequest, LoginResponse, LogoutRequest};
+pub use api_keys::{AddApiKeyRequest, ApiKey, ApiKeyCreated};
+pub use audit::{ActorType, AuditEntry, AuditLogQuery};
+pub use auth::{
+    LoginRequest, LoginResponse, LogoutRequest, OidcCallbackQuery, ResetPasswordRequest,
+};
+pub use bundle::{BundleChange, BundlePair, BundleTeacherLink, GroupBundle, GroupBundleQuery};
 pub use fcm::{FcmGroupRequest, FcmTeachersRequest};
-pub use group::{AddGroupRequest, Group};
-pub use schedule::{AddPair, AddScheduleRequest, Pair, Schedule, ScheduleRow};
+pub use group::{AddGroupRequest, EditGroupRequest, Group};
+pub use group_grants::{AddGroupGrantsRequest, GroupGrant};
+pub use list::{ChangeFilter, GroupFilter, PageQuery, SubjectFilter, TeacherFilter};
+pub use live::{LiveEvent, LiveEventKind, LiveQuery};
+pub use mfa::{
+    DisableTotpRequest, LoginMfaRequest, MfaChallenge, RecoveryCodesResponse, TotpCodeRequest,
+    TotpSetupResponse,
+};
+pub use notifications::{EditNotificationPreferenceRequest, NotificationPreference};
+pub use schedule::{AddScheduleRequest, Pair, Schedule, ScheduleRow};
 pub use schedule_changes::ScheduleChange;
+pub use session::{AccountKind, ImpersonateRequest, ImpersonationResponse, Session, SessionInfo};
+pub use student::{
+    AddStudentsRequest, EditStudentRequest, IssueStudentPasswordRequest, NewStudent, Student,
+    StudentCreated, StudentPasswordResponse,
+};
 pub use subject::{AddSubjectRequest, EditSubjectRequest, Subject};
+pub use sync::{SyncDeletion, SyncEntity, SyncQuery, SyncResponse};
 pub use teacher::{
-    AddTeacherRequest, EditTeacherFullnameRequest, EditTeacherLoginRequest,
-    EditTeacherPasswordRequest, Teacher, TeacherSafe,
+    AddTeacherRequest, ChangeOwnPasswordRequest, EditTeacherEmailRequest,
+    EditTeacherFullnameRequest, EditTeacherLoginRequest, EditTeacherPasswordRequest,
+    EditTeacherRoleRequest, IssueResetCodeRequest, ResetCodeResponse, RoleInfo, Teacher,
+    TeacherProfile, TeacherSafe,
 };
 pub use teacher_links::TeacherLink;
+pub use telegram::TelegramSubscription;
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+pub struct NotificationPreference {
+    pub teacher_id: i64,
+    pub push_changes: bool,
+    pub email_changes: bool,
+    pub email_digest: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct EditNotificationPreferenceRequest {
+    pub push_changes: bool,
+    pub email_changes: bool,
+    pub email_digest: bool,
+}
+
+impl NotificationPreference {
+    pub fn default_for(teacher_id: i64) -> Self {
+        Self {
+            teacher_id,
+            push_changes: true,
+            email_changes: true,
+            email_digest: true,
+        }
+    }
+}
@@ -1,9 +1,28 @@
+use async_graphql::InputObject;
 use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Deserialize, Serialize, FromRow)]
+/// Highest pair number a day can have.
+pub const MAX_PAIR_NUMBER: i8 = 10;
+
+/// Checks that a time span ends after it starts, reporting against `end_field`.
+pub fn validate_time_span(
+    start: NaiveTime,
+    end: NaiveTime,
+    end_field: &'static str,
+) -> Result<(), ValidationError> {
+    if start < end {
+        Ok(())
+    } else {
+        Err(ValidationError::new(end_field)
+            .with_message("Must be later than the start time".into()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, ToSchema)]
 pub struct ScheduleRow {
     pub id: i64,
     pub pair_number: i8,
@@ -14,18 +33,28 @@ pub struct ScheduleRow {
     pub start_time: NaiveTime,
     pub end_time: NaiveTime,
     pub cabinet: String,
+    pub subgroup: Option<i8>,
+    /// Grows by one on every edit
+    pub version: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
+#[graphql(name = "ScheduleInput")]
+#[validate(schema(function = "validate_schedule"))]
 pub struct Schedule {
     pub group_id: i64,
+    #[validate(range(min = 1, max = 7))]
     pub weekday: i8,
+    #[validate(nested)]
     pub pairs: Vec<Pair>,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
+#[graphql(name = "PairInput")]
+#[validate(schema(function = "validate_pair"))]
 pub struct Pair {
     pub id: i64,
+    #[validate(range(min = 1, max = MAX_PAIR_NUMBER))]
     pub pair_number: i8,
     pub teacher_id: i64,
     pub subject_id: i64,
@@ -33,18 +62,29 @@ pub struct Pair {
     pub start_time: NaiveTime,
     pub end_time: NaiveTime,
 
+    #[validate(length(min = 1, max = 100))]
     pub cabinet: String,
+    /// Empty when the whole group attends the pair
+    #[validate(range(min = 1, max = 9))]
+    pub subgroup: Option<i8>,
+    /// Row version. Sent back on edit, a stale one is rejected with 409
+    pub version: Option<i32>,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
+#[validate(schema(function = "validate_add_schedule"))]
 pub struct AddScheduleRequest {
     pub group_id: i64,
+    #[validate(range(min = 1, max = 7))]
     pub weekday: i8,
+    #[validate(nested)]
     pub pairs: Vec<AddPair>,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
+#[validate(schema(function = "validate_add_pair"))]
 pub struct AddPair {
+    #[validate(range(min = 1, max = MAX_PAIR_NUMBER))]
     pub pair_number: i8,
     pub teacher_id: i64,
     pub subject_id: i64,
@@ -52,5 +92,78 @@ pub struct AddPair {
     pub start_time: NaiveTime,
     pub end_time: NaiveTime,
 
+    #[validate(length(min = 1, max = 100))]
     pub cabinet: String,
+    /// Empty when the whole group attends the pair
+    #[validate(range(min = 1, max = 9))]
+    pub subgroup: Option<i8>,
+}
+
+fn validate_pair(pair: &Pair) -> Result<(), ValidationError> {
+    validate_time_span(pair.start_time, pair.end_time, "end_time")
+}
+
+fn validate_add_pair(pair: &AddPair) -> Result<(), ValidationError> {
+    validate_time_span(pair.start_time, pair.end_time, "end_time")
+}
+
+/// Two pairs clash when they share a number and a subgroup, or one of them is for the
+/// whole group.
+fn validate_slots(slots: &[(i8, Option<i8>)]) -> Result<(), ValidationError> {
+    for (i, (number, subgroup)) in slots.iter().enumerate() {
+        let clash = slots[..i].iter().any(|(other_number, other_subgroup)| {
+            other_number == number
+                && (subgroup.is_none() || other_subgroup.is_none() || subgroup == other_subgroup)
+        });
+        if clash {
+            return Err(ValidationError::new("pairs")
+                .with_message(format!("Pair {} is repeated", number).into()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_schedule(schedule: &Schedule) -> Result<(), ValidationError> {
+    let slots: Vec<_> = schedule
+        .pairs
+        .iter()
+        .map(|p| (p.pair_number, p.subgroup))
+        .collect();
+    validate_slots(&slots)
+}
+
+fn validate_add_schedule(schedule: &AddScheduleRequest) -> Result<(), ValidationError> {
+    let slots: Vec<_> = schedule
+        .pairs
+        .iter()
+        .map(|p| (p.pair_number, p.subgroup))
+        .collect();
+    validate_slots(&slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distinct_pairs_pass() {
+        assert!(validate_slots(&[(1, None), (2, None), (3, Some(1))]).is_ok());
+    }
+
+    #[test]
+    fn subgroups_can_share_a_pair() {
+        assert!(validate_slots(&[(1, Some(1)), (1, Some(2))]).is_ok());
+    }
+
+    #[test]
+    fn repeated_pair_clashes() {
+        assert!(validate_slots(&[(1, None), (2, None), (1, None)]).is_err());
+        assert!(validate_slots(&[(1, Some(2)), (1, Some(2))]).is_err());
+    }
+
+    #[test]
+    fn whole_group_pair_clashes_with_subgroups() {
+        assert!(validate_slots(&[(1, Some(1)), (1, None)]).is_err());
+        assert!(validate_slots(&[(1, None), (1, Some(2))]).is_err());
+    }
 }
@@ -1,9 +1,15 @@
+use async_graphql::InputObject;
 use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Clone)]
+use super::schedule::validate_time_span;
+
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Clone, Validate, InputObject)]
+#[graphql(name = "ScheduleChangeInput")]
+#[validate(schema(function = "validate_change"))]
 pub struct ScheduleChange {
     pub schedule_id: i64,
     pub group_id: i64,
@@ -12,6 +18,20 @@ pub struct ScheduleChange {
     pub date: NaiveDate,
     pub new_start_time: NaiveTime,
     pub new_end_time: NaiveTime,
+    #[validate(length(max = 100))]
     pub cabinet: String,
     pub is_canceled: bool,
+    /// Row version. Sent back on edit, a stale one is rejected with 409
+    pub version: Option<i32>,
+}
+
+/// A canceled pair keeps whatever time and cabinet were sent, they are never shown.
+fn validate_change(change: &ScheduleChange) -> Result<(), ValidationError> {
+    if change.is_canceled {
+        return Ok(());
+    }
+    if change.cabinet.is_empty() {
+        return Err(ValidationError::new("cabinet").with_message("Must not be empty".into()));
+    }
+    validate_time_span(change.new_start_time, change.new_end_time, "new_end_time")
 }
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::Role;
+
+/// Table the `user_id` of a session points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    Teacher,
+    Student,
+}
+
+impl AccountKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountKind::Teacher => "teacher",
+            AccountKind::Student => "student",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Session {
+    pub id: String,
+    #[serde(skip)]
+    pub token: String,
+    pub kind: AccountKind,
+    pub user_id: i64,
+    pub device: String,
+    pub ip: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Admin acting as the user, set for impersonation sessions only
+    pub impersonator_id: Option<i64>,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionInfo {
+    #[serde(flatten)]
+    pub session: Session,
+    pub current: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImpersonateRequest {
+    pub kind: AccountKind,
+    pub id: i64,
+    /// Lets the session change data, otherwise only reads are allowed
+    #[serde(default)]
+    pub allow_writes: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImpersonationResponse {
+    pub token: String,
+    pub kind: AccountKind,
+    pub id: i64,
+    pub role: Role,
+    pub read_only: bool,
+    /// Seconds until the session expires
+    pub expires_in: i64,
+}
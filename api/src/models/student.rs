@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Student {
+    pub id: i64,
+    pub group_id: i64,
+    /// Empty when the student attends every pair of the group
+    pub subgroup: Option<i8>,
+    pub full_name: String,
+    /// Empty when the student has no login
+    pub login: Option<String>,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    pub must_change_password: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddStudentsRequest {
+    pub group_id: i64,
+    pub students: Vec<NewStudent>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewStudent {
+    pub full_name: String,
+    pub subgroup: Option<i8>,
+    /// Students without a login can't sign in
+    pub login: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StudentCreated {
+    #[serde(flatten)]
+    pub student: Student,
+    /// One-time password, only returned for students with a login
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct EditStudentRequest {
+    pub id: i64,
+    pub group_id: i64,
+    pub subgroup: Option<i8>,
+    pub full_name: String,
+    pub login: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IssueStudentPasswordRequest {
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StudentPasswordResponse {
+    pub password: String,
+}
@@ -1,22 +1,30 @@
+use async_graphql::InputObject;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, ToSchema)]
 pub struct Subject {
     pub id: i64,
     pub name: String,
     pub group_id: i64,
+    /// Grows by one on every edit
+    pub version: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
 pub struct AddSubjectRequest {
+    #[validate(length(min = 1, max = 500))]
     pub name: String,
     pub group_id: i64,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate, InputObject)]
 pub struct EditSubjectRequest {
     pub id: i64,
+    #[validate(length(min = 1, max = 500))]
     pub new_name: String,
+    /// Version the edit was made against, a stale one is rejected with 409
+    pub version: Option<i32>,
 }
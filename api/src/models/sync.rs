@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{Group, ScheduleChange, ScheduleRow, Subject, TeacherLink, TeacherSafe};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// Cursor of the previous sync, a full snapshot is sent without it
+    pub since: Option<String>,
+    /// Limits subjects, pairs, teacher links and changes to one group
+    pub group_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncEntity {
+    Group,
+    Subject,
+    Teacher,
+    Pair,
+    TeacherLink,
+    Change,
+}
+
+impl TryFrom<String> for SyncEntity {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "group" => Ok(SyncEntity::Group),
+            "subject" => Ok(SyncEntity::Subject),
+            "teacher" => Ok(SyncEntity::Teacher),
+            "pair" => Ok(SyncEntity::Pair),
+            "teacher_link" => Ok(SyncEntity::TeacherLink),
+            "change" => Ok(SyncEntity::Change),
+            _ => Err(format!("Unknown sync entity: {}", value)),
+        }
+    }
+}
+
+/// A deleted row. Rows referencing a deleted group, subject, teacher or pair are gone
+/// as well without being listed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncDeletion {
+    pub entity: SyncEntity,
+    /// Id of the row, `group_id:subject_id` for teacher links
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncResponse {
+    /// Sent as `since` on the next sync
+    pub cursor: String,
+    /// Everything was sent, local rows missing from the response are stale
+    pub full: bool,
+    pub groups: Vec<Group>,
+    pub subjects: Vec<Subject>,
+    pub teachers: Vec<TeacherSafe>,
+    pub pairs: Vec<ScheduleRow>,
+    pub teacher_links: Vec<TeacherLink>,
+    pub changes: Vec<ScheduleChange>,
+    pub deleted: Vec<SyncDeletion>,
+}
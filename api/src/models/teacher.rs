@@ -1,33 +1,73 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Clone, FromRow, Deserialize, Serialize, ToSchema)]
+use crate::auth::{Permission, Role};
+
+/// Stored teacher with its secrets, never sent to clients. Responses use `TeacherProfile`.
+#[derive(Debug, Clone, FromRow)]
 pub struct Teacher {
     pub id: i64,
     pub login: String,
     pub password_hash: String,
     pub full_name: String,
-    pub role: String,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    pub email: Option<String>,
+    pub must_change_password: bool,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
 }
 
-#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, ToSchema)]
 pub struct TeacherSafe {
     pub id: i64,
     pub full_name: String,
-    pub role: String,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
 }
 
-#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct TeacherProfile {
+    pub id: i64,
+    pub login: String,
+    pub full_name: String,
+    pub role: Role,
+    pub email: Option<String>,
+    pub must_change_password: bool,
+    pub totp_enabled: bool,
+}
+
+impl From<Teacher> for TeacherProfile {
+    fn from(teacher: Teacher) -> Self {
+        Self {
+            id: teacher.id,
+            login: teacher.login,
+            full_name: teacher.full_name,
+            role: teacher.role,
+            email: teacher.email,
+            must_change_password: teacher.must_change_password,
+            totp_enabled: teacher.totp_enabled,
+        }
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema, Validate)]
 pub struct AddTeacherRequest {
+    #[validate(length(min = 1, max = 50))]
     pub login: String,
     pub password: String,
+    #[validate(length(min = 1, max = 150))]
     pub full_name: String,
+    #[validate(email, length(max = 255))]
+    pub email: Option<String>,
 }
 
-#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema, Validate)]
 pub struct EditTeacherLoginRequest {
     pub id: i64,
+    #[validate(length(min = 1, max = 50))]
     pub login: String,
 }
 
@@ -37,8 +77,45 @@ pub struct EditTeacherPasswordRequest {
     pub password: String,
 }
 
-#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema, Validate)]
 pub struct EditTeacherFullnameRequest {
     pub id: i64,
+    #[validate(length(min = 1, max = 150))]
     pub full_name: String,
 }
+
+#[derive(Debug, FromRow, Deserialize, Serialize, ToSchema, Validate)]
+pub struct EditTeacherEmailRequest {
+    pub id: i64,
+    #[validate(email, length(max = 255))]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ChangeOwnPasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct EditTeacherRoleRequest {
+    pub id: i64,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoleInfo {
+    pub role: Role,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct IssueResetCodeRequest {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ResetCodeResponse {
+    pub code: String,
+    pub expires_in: u64,
+}
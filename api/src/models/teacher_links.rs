@@ -1,8 +1,10 @@
+use async_graphql::InputObject;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, ToSchema, InputObject)]
+#[graphql(name = "TeacherLinkInput")]
 pub struct TeacherLink {
     pub teacher_id: i64,
     pub group_id: i64,
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+pub struct TelegramSubscription {
+    pub chat_id: i64,
+    pub group_id: i64,
+}
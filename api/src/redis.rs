@@ -1,6 +1,43 @@
-use redis::{AsyncCommands, Client};
+use chrono::{DateTime, Utc};
+use redis::{AsyncCommands, Client, aio::PubSub};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::models::{AccountKind, Session};
+
+const SESSION_TTL: i64 = 86400;
+
+fn session_key(token: &str) -> String {
+    format!("session:{}", token)
+}
+
+fn index_key(kind: AccountKind, user_id: i64) -> String {
+    match kind {
+        AccountKind::Teacher => format!("sessions:{}", user_id),
+        AccountKind::Student => format!("student_sessions:{}", user_id),
+    }
+}
+
+fn mfa_challenge_key(token: &str) -> String {
+    format!("mfa_challenge:{}", token)
+}
+
+fn reset_code_key(user_id: i64) -> String {
+    format!("reset_code:{}", user_id)
+}
+
+fn oidc_state_key(state: &str) -> String {
+    format!("oidc_state:{}", state)
+}
+
+fn group_generation_key(group_id: i64) -> String {
+    format!("group_cache_generation:{}", group_id)
+}
+
+fn group_cache_key(group_id: i64, generation: u64) -> String {
+    format!("group_cache:{}:{}", group_id, generation)
+}
+
 #[derive(Clone)]
 pub struct RedisState {
     pub client: Client,
@@ -12,23 +49,462 @@ impl RedisState {
         Ok(Self { client })
     }
 
-    pub async fn create_session(&self, user_id: i64) -> redis::RedisResult<String> {
+    pub async fn create_session(
+        &self,
+        kind: AccountKind,
+        user_id: i64,
+        device: &str,
+        ip: &str,
+    ) -> redis::RedisResult<Session> {
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            token: Uuid::new_v4().to_string(),
+            kind,
+            user_id,
+            device: device.to_string(),
+            ip: ip.to_string(),
+            created_at: Utc::now(),
+            last_seen: Utc::now(),
+            impersonator_id: None,
+            read_only: false,
+        };
+        self.store_session(&session, SESSION_TTL).await?;
+
+        Ok(session)
+    }
+
+    /// Opens a session as another user on behalf of an admin. It is listed and revoked
+    /// together with the user's own sessions but expires after `ttl` without sliding.
+    pub async fn create_impersonation_session(
+        &self,
+        kind: AccountKind,
+        user_id: i64,
+        impersonator_id: i64,
+        read_only: bool,
+        ip: &str,
+        ttl: i64,
+    ) -> redis::RedisResult<Session> {
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            token: Uuid::new_v4().to_string(),
+            kind,
+            user_id,
+            device: String::from("impersonation"),
+            ip: ip.to_string(),
+            created_at: Utc::now(),
+            last_seen: Utc::now(),
+            impersonator_id: Some(impersonator_id),
+            read_only,
+        };
+        self.store_session(&session, ttl).await?;
+
+        Ok(session)
+    }
+
+    async fn store_session(&self, session: &Session, ttl: i64) -> redis::RedisResult<()> {
         let mut conn = self.client.get_multiplexed_tokio_connection().await?;
-        let token: String = Uuid::new_v4().to_string();
-        conn.set_ex::<&str, i64, ()>(&token, user_id, 86400).await?;
+        let session_key = session_key(&session.token);
+        let index_key = index_key(session.kind, session.user_id);
 
-        Ok(token)
+        let mut fields = vec![
+            ("id", session.id.clone()),
+            ("kind", session.kind.as_str().to_string()),
+            ("user_id", session.user_id.to_string()),
+            ("device", session.device.clone()),
+            ("ip", session.ip.clone()),
+            ("created_at", session.created_at.timestamp().to_string()),
+            ("last_seen", session.last_seen.timestamp().to_string()),
+        ];
+        if let Some(impersonator_id) = session.impersonator_id {
+            fields.push(("impersonator_id", impersonator_id.to_string()));
+            fields.push(("read_only", session.read_only.to_string()));
+        }
+
+        let _: () = redis::pipe()
+            .hset_multiple(&session_key, &fields)
+            .expire(&session_key, ttl)
+            .hset(&index_key, &session.id, &session.token)
+            .expire(&index_key, SESSION_TTL)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Looks the session up and slides its expiry forward.
+    pub async fn get_session(&self, token: &str) -> redis::RedisResult<Option<Session>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let session_key = session_key(token);
+        let fields: HashMap<String, String> = conn.hgetall(&session_key).await?;
+        let Some(mut session) = Session::from_fields(token, fields) else {
+            return Ok(None);
+        };
+        if session.impersonator_id.is_some() {
+            return Ok(Some(session));
+        }
+
+        session.last_seen = Utc::now();
+        let index_key = index_key(session.kind, session.user_id);
+        let _: () = redis::pipe()
+            .hset(&session_key, "last_seen", session.last_seen.timestamp())
+            .expire(&session_key, SESSION_TTL)
+            .expire(&index_key, SESSION_TTL)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(Some(session))
     }
 
-    pub async fn get_session(&self, token: &str) -> redis::RedisResult<Option<i32>> {
+    pub async fn list_sessions(&self, user_id: i64) -> redis::RedisResult<Vec<Session>> {
         let mut conn = self.client.get_multiplexed_tokio_connection().await?;
-        conn.get(token).await
+        let index_key = index_key(AccountKind::Teacher, user_id);
+        let tokens: HashMap<String, String> = conn.hgetall(&index_key).await?;
+
+        let mut sessions = Vec::new();
+        for (id, token) in tokens {
+            let fields: HashMap<String, String> = conn.hgetall(session_key(&token)).await?;
+            match Session::from_fields(&token, fields) {
+                Some(session) => sessions.push(session),
+                None => {
+                    let _: () = conn.hdel(&index_key, &id).await?;
+                }
+            }
+        }
+        sessions.sort_by_key(|s| s.created_at);
+
+        Ok(sessions)
     }
 
     pub async fn delete_session(&self, token: &str) -> redis::RedisResult<()> {
         let mut conn = self.client.get_multiplexed_tokio_connection().await?;
-        let _: () = conn.del(token).await?;
+        let session_key = session_key(token);
+        let fields: HashMap<String, String> = conn.hgetall(&session_key).await?;
+        let _: () = conn.del(&session_key).await?;
+
+        if let Some(session) = Session::from_fields(token, fields) {
+            let _: () = conn
+                .hdel(index_key(session.kind, session.user_id), &session.id)
+                .await?;
+        }
 
         Ok(())
     }
+
+    pub async fn revoke_session(&self, user_id: i64, id: &str) -> redis::RedisResult<bool> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let index_key = index_key(AccountKind::Teacher, user_id);
+        let token: Option<String> = conn.hget(&index_key, id).await?;
+        let Some(token) = token else {
+            return Ok(false);
+        };
+
+        let _: () = redis::pipe()
+            .del(session_key(&token))
+            .hdel(&index_key, id)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Revokes every session of the teacher, optionally keeping the one with `keep_id`.
+    pub async fn revoke_sessions(
+        &self,
+        user_id: i64,
+        keep_id: Option<&str>,
+    ) -> redis::RedisResult<usize> {
+        self.revoke_account_sessions(AccountKind::Teacher, user_id, keep_id)
+            .await
+    }
+
+    pub async fn revoke_student_sessions(
+        &self,
+        student_id: i64,
+        keep_id: Option<&str>,
+    ) -> redis::RedisResult<usize> {
+        self.revoke_account_sessions(AccountKind::Student, student_id, keep_id)
+            .await
+    }
+
+    async fn revoke_account_sessions(
+        &self,
+        kind: AccountKind,
+        user_id: i64,
+        keep_id: Option<&str>,
+    ) -> redis::RedisResult<usize> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let index_key = index_key(kind, user_id);
+        let tokens: HashMap<String, String> = conn.hgetall(&index_key).await?;
+
+        let mut pipe = redis::pipe();
+        let mut revoked = 0;
+        for (id, token) in &tokens {
+            if keep_id == Some(id.as_str()) {
+                continue;
+            }
+            pipe.del(session_key(token)).hdel(&index_key, id);
+            revoked += 1;
+        }
+        if revoked > 0 {
+            let _: () = pipe.query_async(&mut conn).await?;
+        }
+
+        Ok(revoked)
+    }
+
+    pub async fn create_mfa_challenge(&self, user_id: i64, ttl: u64) -> redis::RedisResult<String> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let token = Uuid::new_v4().to_string();
+        let _: () = conn.set_ex(mfa_challenge_key(&token), user_id, ttl).await?;
+
+        Ok(token)
+    }
+
+    pub async fn get_mfa_challenge(&self, token: &str) -> redis::RedisResult<Option<i64>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        conn.get(mfa_challenge_key(token)).await
+    }
+
+    pub async fn delete_mfa_challenge(&self, token: &str) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.del(mfa_challenge_key(token)).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_reset_code(
+        &self,
+        user_id: i64,
+        code_hash: &str,
+        ttl: u64,
+    ) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.set_ex(reset_code_key(user_id), code_hash, ttl).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_reset_code(&self, user_id: i64) -> redis::RedisResult<Option<String>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        conn.get(reset_code_key(user_id)).await
+    }
+
+    pub async fn delete_reset_code(&self, user_id: i64) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.del(reset_code_key(user_id)).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_oidc_state(
+        &self,
+        state: &str,
+        nonce: &str,
+        pkce_verifier: &str,
+        ttl: u64,
+    ) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let key = oidc_state_key(state);
+        let _: () = redis::pipe()
+            .hset_multiple(&key, &[("nonce", nonce), ("pkce_verifier", pkce_verifier)])
+            .expire(&key, ttl as i64)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the nonce and PKCE verifier of a pending login and removes it, so a state is usable once.
+    pub async fn take_oidc_state(
+        &self,
+        state: &str,
+    ) -> redis::RedisResult<Option<(String, String)>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let key = oidc_state_key(state);
+        let (mut fields, _): (HashMap<String, String>, i64) = redis::pipe()
+            .atomic()
+            .hgetall(&key)
+            .del(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(fields.remove("nonce").zip(fields.remove("pkce_verifier")))
+    }
+
+    /// Increments a fixed-window counter, returning the new count and the seconds left in the window.
+    pub async fn increment(&self, key: &str, window: u64) -> redis::RedisResult<(u64, u64)> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let (count, _, ttl): (u64, i64, i64) = redis::pipe()
+            .incr(key, 1)
+            .cmd("EXPIRE")
+            .arg(key)
+            .arg(window)
+            .arg("NX")
+            .ttl(key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok((count, ttl.max(1) as u64))
+    }
+
+    pub async fn ttl(&self, key: &str) -> redis::RedisResult<Option<u64>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let ttl: i64 = conn.ttl(key).await?;
+
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
+    pub async fn set_flag(&self, key: &str, ttl: u64) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.set_ex(key, 1, ttl).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_value(&self, key: &str) -> redis::RedisResult<Option<String>> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let value: Option<String> = conn.get(key).await?;
+
+        Ok(value)
+    }
+
+    pub async fn set_value(&self, key: &str, value: &str, ttl: u64) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.set_ex(key, value, ttl).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_keys(&self, keys: &[String]) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.del(keys).await?;
+
+        Ok(())
+    }
+
+    /// Returns the current cache generation of a group with the entry stored under it.
+    /// Entries are written back with the generation read here, so a response rendered
+    /// while the group changed never lands in the new generation.
+    pub async fn get_group_cache(
+        &self,
+        group_id: i64,
+        field: &str,
+    ) -> redis::RedisResult<(u64, Option<String>)> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let generation: Option<u64> = conn.get(group_generation_key(group_id)).await?;
+        let generation = generation.unwrap_or(0);
+        let entry = conn
+            .hget(group_cache_key(group_id, generation), field)
+            .await?;
+
+        Ok((generation, entry))
+    }
+
+    pub async fn set_group_cache(
+        &self,
+        group_id: i64,
+        generation: u64,
+        field: &str,
+        value: &str,
+        ttl: i64,
+    ) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let key = group_cache_key(group_id, generation);
+        let _: () = redis::pipe()
+            .hset(&key, field, value)
+            .expire(&key, ttl)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Moves a group to a new cache generation, the old entries expire on their own.
+    pub async fn invalidate_group_cache(&self, group_id: i64) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.incr(group_generation_key(group_id), 1).await?;
+
+        Ok(())
+    }
+
+    pub async fn publish(&self, channel: &str, payload: &str) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let _: () = conn.publish(channel, payload).await?;
+
+        Ok(())
+    }
+
+    /// Opens a dedicated connection subscribed to the channel.
+    pub async fn subscribe(&self, channel: &str) -> redis::RedisResult<PubSub> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(channel).await?;
+
+        Ok(pubsub)
+    }
+
+    pub async fn acquire_lock(&self, key: &str, ttl: u64) -> redis::RedisResult<bool> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(result.is_some())
+    }
+
+    /// Takes the lock for `owner`, or extends it when `owner` already holds it, for work
+    /// one instance keeps doing until it stops renewing.
+    pub async fn hold_lock(&self, key: &str, owner: &str, ttl: u64) -> redis::RedisResult<bool> {
+        let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+        let held: i64 = redis::Script::new(
+            r"
+            if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'EX', ARGV[2]) then
+                return 1
+            end
+            if redis.call('GET', KEYS[1]) == ARGV[1] then
+                redis.call('EXPIRE', KEYS[1], ARGV[2])
+                return 1
+            end
+            return 0
+            ",
+        )
+        .key(key)
+        .arg(owner)
+        .arg(ttl)
+        .invoke_async(&mut conn)
+        .await?;
+
+        Ok(held == 1)
+    }
+}
+
+impl Session {
+    fn from_fields(token: &str, fields: HashMap<String, String>) -> Option<Self> {
+        let timestamp = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|t| t.parse().ok())
+                .and_then(|t| DateTime::from_timestamp(t, 0))
+        };
+
+        Some(Self {
+            id: fields.get("id")?.clone(),
+            token: token.to_string(),
+            kind: match fields.get("kind").map(String::as_str) {
+                Some("student") => AccountKind::Student,
+                _ => AccountKind::Teacher,
+            },
+            user_id: fields.get("user_id")?.parse().ok()?,
+            device: fields.get("device").cloned().unwrap_or_default(),
+            ip: fields.get("ip").cloned().unwrap_or_default(),
+            created_at: timestamp("created_at")?,
+            last_seen: timestamp("last_seen")?,
+            impersonator_id: fields.get("impersonator_id").and_then(|i| i.parse().ok()),
+            read_only: fields.get("read_only").is_some_and(|r| r == "true"),
+        })
+    }
 }
@@ -0,0 +1,128 @@
+use crate::{
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{ActorType, AddApiKeyRequest, ApiKey, ApiKeyCreated},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{generate_api_key, hash_api_key},
+    },
+    traits::{ApiKeys, Groups},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+const KEY_PREFIX_LENGTH: usize = 11;
+
+#[utoipa::path(
+    get,
+    path = "/get_api_keys",
+    tag = "API keys",
+    responses(
+        (status = 200, description = "All API keys", body = [Vec<ApiKey>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_api_keys(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<ApiKey>>, AppError> {
+    let keys = app_state.db.get_api_keys().await?;
+    Ok(Json(keys))
+}
+
+#[utoipa::path(
+    post,
+    path = "/add_api_key",
+    tag = "API keys",
+    request_body = AddApiKeyRequest,
+    responses(
+        (status = 200, description = "Created key, the token is shown once", body = ApiKeyCreated),
+        (status = 422, description = "Invalid scopes or expiry", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn add_api_key(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Json(payload): Json<AddApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.scopes.is_empty() {
+        return Err(AppError::Validation(String::from(
+            "At least one scope is required",
+        )));
+    }
+    if !payload.scopes.iter().all(|s| s.is_key_scope()) {
+        return Err(AppError::Validation(String::from(
+            "API keys can only hold data scopes, not self-service, impersonation or account management",
+        )));
+    }
+    if payload.expires_at.is_some_and(|e| e <= chrono::Utc::now()) {
+        return Err(AppError::Validation(String::from(
+            "Expiry must be in the future",
+        )));
+    }
+    for group_id in &payload.group_ids {
+        app_state.db.get_group_by_id(*group_id).await?;
+    }
+
+    let token = generate_api_key();
+    let key = app_state
+        .db
+        .add_api_key(
+            &payload,
+            &token[..KEY_PREFIX_LENGTH],
+            &hash_api_key(&token),
+            match audit.actor_type {
+                ActorType::Teacher => audit.actor_id,
+                _ => None,
+            },
+        )
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_api_key", "api_key", key.id).after(&key),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(ApiKeyCreated { key, token })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/delete_api_key/{api_key_id}",
+    tag = "API keys",
+    params(
+        ("api_key_id" = i64, Path, description = "API key identificator")
+    ),
+    responses(
+        (status = 200, description = "Key revoked"),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn delete_api_key(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Path(api_key_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let before = app_state.db.get_api_key_by_id(api_key_id).await?;
+    let result = app_state.db.delete_api_key(api_key_id).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_api_key", "api_key", api_key_id).before(&before),
+        )
+        .await;
+    Ok((StatusCode::OK, Json(result)))
+}
@@ -0,0 +1,30 @@
+use crate::{
+    config::AppState,
+    errors::AppError,
+    models::{AuditEntry, AuditLogQuery},
+    traits::AuditLog,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+#[utoipa::path(
+    get,
+    path = "/get_audit_log",
+    tag = "Audit",
+    params(AuditLogQuery),
+    responses(
+        (status = 200, description = "Audit entries, newest first", body = [Vec<AuditEntry>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_audit_log(
+    State(app_state): State<AppState>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    let entries = app_state.db.get_audit_entries(&query).await?;
+    Ok(Json(entries))
+}
@@ -1,10 +1,14 @@
 use crate::{
+    auth::GroupScope,
     config::AppState,
     errors::{AppError, ErrorResponse},
-    models::{FcmGroupRequest, FcmTeachersRequest}
+    models::{FcmGroupRequest, FcmTeachersRequest},
+    services::audit::{AuditContext, AuditEvent},
+    telegram::send_group_alert,
+    traits::NotificationPreferences,
 };
 use axum::{
-    Json,
+    Extension, Json,
     extract::State,
     http::StatusCode,
     response::IntoResponse,
@@ -25,9 +29,22 @@ use axum::{
 )]
 pub async fn send_notifications_to_teachers(
     State(mut app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<FcmTeachersRequest>,
 )-> impl IntoResponse {
+   let teacher_ids: Vec<_> = payload.teacher_ids.iter().map(|id| id.to_string()).collect();
+   audit
+       .record(
+           &app_state,
+           AuditEvent::new("send_notifications_to_teachers", "teacher", teacher_ids.join(","))
+               .after(&payload),
+       )
+       .await;
    for teacher_id in payload.teacher_ids {
+       match app_state.db.get_notification_preference(teacher_id).await {
+           Ok(preference) if preference.push_changes => {}
+           _ => continue,
+       }
        match app_state.fcm.send_to_teacher(teacher_id).await {
            Ok(_) => (StatusCode::OK, Json(200)).into_response(),
            Err(e) => AppError::BadRequest(e.to_string()).into_response(),
@@ -44,6 +61,7 @@ pub async fn send_notifications_to_teachers(
     responses(
         (status = 200, description = "Succesfully sent notifications"),
         (status = 400, description = "Error", body = [ErrorResponse]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
     ),
     security(
         ("bearer_auth"=[])
@@ -51,8 +69,20 @@ pub async fn send_notifications_to_teachers(
 )]
 pub async fn send_notifications_to_group(
     State(mut app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<FcmGroupRequest>,
 )-> impl IntoResponse {
+    if let Err(e) = scope.ensure(payload.group_id) {
+        return e.into_response();
+    }
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("send_notifications_to_group", "group", payload.group_id),
+        )
+        .await;
+    tokio::spawn(send_group_alert(app_state.clone(), payload.group_id));
     match app_state.fcm.send_to_group(payload.group_id).await {
        Ok(_) => (StatusCode::OK, Json(200)).into_response(),
        Err(e) => {dbg!(&e); AppError::BadRequest(e.to_string()).into_response()},
@@ -0,0 +1,111 @@
+use crate::{
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{AddGroupGrantsRequest, GroupGrant},
+    services::audit::{AuditContext, AuditEvent},
+    traits::{GroupGrants, Groups, Teachers},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+#[utoipa::path(
+    get,
+    path = "/get_group_grants/{teacher_id}",
+    tag = "Group grants",
+    params(
+        ("teacher_id" = i64, Path, description = "Teacher identificator")
+    ),
+    responses(
+        (status = 200, description = "Groups the teacher may manage", body = [Vec<GroupGrant>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_group_grants(
+    State(app_state): State<AppState>,
+    Path(teacher_id): Path<i64>,
+) -> Result<Json<Vec<GroupGrant>>, AppError> {
+    let grants = app_state.db.get_group_grants(teacher_id).await?;
+    Ok(Json(grants))
+}
+
+#[utoipa::path(
+    post,
+    path = "/add_group_grants",
+    tag = "Group grants",
+    request_body = AddGroupGrantsRequest,
+    responses(
+        (status = 200, description = "Grants added", body = [Vec<GroupGrant>]),
+        (status = 500, description = "Database error", body = [ErrorResponse])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn add_group_grants(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Json(payload): Json<AddGroupGrantsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    app_state.db.get_teacher_by_id(payload.teacher_id).await?;
+    for group_id in &payload.group_ids {
+        app_state.db.get_group_by_id(*group_id).await?;
+    }
+
+    let before = app_state.db.get_group_grants(payload.teacher_id).await?;
+    let result = app_state
+        .db
+        .add_group_grants(payload.teacher_id, &payload.group_ids)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_group_grants", "group_grant", payload.teacher_id)
+                .before(&before)
+                .after(&result),
+        )
+        .await;
+    Ok((StatusCode::OK, Json(result)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/delete_group_grant/{teacher_id}/{group_id}",
+    tag = "Group grants",
+    params(
+        ("teacher_id" = i64, Path, description = "Teacher identificator"),
+        ("group_id" = i64, Path, description = "Group identificator")
+    ),
+    responses(
+        (status = 200, description = "Grant deleted"),
+        (status = 500, description = "Database error", body = [ErrorResponse])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn delete_group_grant(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Path((teacher_id, group_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = app_state
+        .db
+        .delete_group_grant(teacher_id, group_id)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_group_grant", "group_grant", teacher_id).before(&GroupGrant {
+                teacher_id,
+                group_id,
+            }),
+        )
+        .await;
+    Ok((StatusCode::OK, Json(result)))
+}
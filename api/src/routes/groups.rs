@@ -1,27 +1,44 @@
 use crate::{
     config::AppState,
-    errors::{AppError, ErrorResponse},
-    models::{AddGroupRequest, group::Group},
+    errors::{AppError, ErrorResponse, FieldError},
+    models::{
+        AddGroupRequest, EditGroupRequest, GroupBundle, GroupBundleQuery, GroupFilter, PageQuery,
+        group::Group,
+    },
+    services::{
+        audit::{AuditContext, AuditEvent},
+        cache::invalidate_groups,
+        concurrency::{check_version, guarded},
+        listing::{ListQuery, Paged},
+        schedule::weekday_of,
+    },
     traits::Groups,
 };
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
+use chrono::Local;
+use validator::Validate;
 
 #[utoipa::path(
     get,
     path = "/get_groups",
     tag = "Groups",
+    params(PageQuery, GroupFilter),
     responses(
-        (status = 200, description = "Get groups list", body = [Vec<Group>])
+        (status = 200, description = "Get groups list", body = [Vec<Group>], headers(("X-Total-Count" = i64, description = "Rows matching the filters"))),
+        (status = 422, description = "Unknown sort field", body = [ErrorResponse]),
     )
 )]
-pub async fn get_groups(State(app_state): State<AppState>) -> Result<Json<Vec<Group>>, AppError> {
-    let groups = app_state.db.get_groups().await?;
-    Ok(Json(groups))
+pub async fn get_groups(
+    State(app_state): State<AppState>,
+    query: ListQuery<GroupFilter>,
+) -> Result<Paged<Group>, AppError> {
+    let groups = app_state.db.list_groups(&query).await?;
+    Ok(groups)
 }
 
 #[utoipa::path(
@@ -42,10 +59,53 @@ pub async fn get_group_by_id(
 ) -> impl IntoResponse {
     match app_state.db.get_group_by_id(group_id).await {
         Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(_) => AppError::NotFound.into_response(),
+        Err(e) => AppError::Database(e).into_response(),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/get_group_bundle/{group_id}",
+    tag = "Groups",
+    params(
+        ("group_id" = i64, Path, description = "Group identificator"),
+        GroupBundleQuery,
+    ),
+    responses(
+        (status = 200, description = "Group with its subjects, teacher links, pairs and changes", body = GroupBundle),
+        (status = 404, description = "Not found", body = [ErrorResponse]),
+        (status = 422, description = "Range ends before it starts", body = [ErrorResponse]),
+    ),
+)]
+pub async fn get_group_bundle(
+    State(app_state): State<AppState>,
+    Path(group_id): Path<i64>,
+    Query(query): Query<GroupBundleQuery>,
+) -> Result<Json<GroupBundle>, AppError> {
+    let from = query.from.unwrap_or_else(|| Local::now().date_naive());
+    let weekdays: Vec<i8> = match query.to {
+        Some(to) if to < from => {
+            return Err(AppError::FieldValidation(vec![FieldError::new(
+                "to",
+                "Must not be before from",
+            )]));
+        }
+        Some(to) => from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .take(7)
+            .map(weekday_of)
+            .collect(),
+        None => (1..=7).collect(),
+    };
+
+    let bundle = app_state
+        .db
+        .get_group_bundle(group_id, &weekdays, from, query.to)
+        .await?;
+    Ok(Json(bundle))
+}
+
 #[utoipa::path(
     post,
     path = "/add_group",
@@ -53,6 +113,8 @@ pub async fn get_group_by_id(
     request_body = AddGroupRequest,
     responses(
         (status = 200, description = "Added group", body = [Group]),
+        (status = 409, description = "Group name is taken", body = [ErrorResponse]),
+        (status = 422, description = "Invalid name or shift", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -61,21 +123,30 @@ pub async fn get_group_by_id(
 )]
 pub async fn add_group(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<AddGroupRequest>,
-) -> impl IntoResponse {
-    match app_state.db.add_group(&payload.name, payload.shift).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+) -> Result<Json<Group>, AppError> {
+    payload.validate()?;
+    let result = app_state.db.add_group(&payload.name, payload.shift).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_group", "group", result.id).after(&result),
+        )
+        .await;
+
+    Ok(Json(result))
 }
 
 #[utoipa::path(
     patch,
     path = "/edit_group",
     tag = "Groups",
-    request_body = Group,
+    request_body = EditGroupRequest,
     responses(
         (status = 200, description = "Group edited", body = [Group]),
+        (status = 409, description = "Group name is taken or the group was changed since `version`", body = [ErrorResponse]),
+        (status = 422, description = "Invalid name or shift", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -84,12 +155,29 @@ pub async fn add_group(
 )]
 pub async fn edit_group(
     State(app_state): State<AppState>,
-    Json(payload): Json<Group>,
-) -> impl IntoResponse {
-    match app_state.db.update_group(payload.id, &payload.name, payload.shift).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+    audit: AuditContext,
+    Json(payload): Json<EditGroupRequest>,
+) -> Result<Json<Group>, AppError> {
+    payload.validate()?;
+    let before = app_state.db.get_group_by_id(payload.id).await?;
+    check_version(before.version, payload.version)?;
+    let result = guarded(
+        app_state
+            .db
+            .update_group(payload.id, &payload.name, payload.shift, payload.version)
+            .await,
+        payload.version.is_some(),
+    )?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("edit_group", "group", payload.id)
+                .before(&before)
+                .after(&result),
+        )
+        .await;
+
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -109,10 +197,18 @@ pub async fn edit_group(
 )]
 pub async fn delete_group(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Path(group_id): Path<i64>,
-) -> impl IntoResponse {
-    match app_state.db.delete_group(group_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => {dbg!(&e);AppError::Database(e).into_response()},
-    }
+) -> Result<Json<u16>, AppError> {
+    let before = app_state.db.get_group_by_id(group_id).await?;
+    let result = app_state.db.delete_group(group_id).await?;
+    invalidate_groups(&app_state, [group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_group", "group", group_id).before(&before),
+        )
+        .await;
+
+    Ok(Json(result))
 }
@@ -0,0 +1,174 @@
+use std::net::SocketAddr;
+
+use axum::{
+    Extension, Json,
+    extract::{ConnectInfo, State},
+};
+
+use crate::{
+    auth::Role,
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{AccountKind, ImpersonateRequest, ImpersonationResponse, Session, Teacher},
+    services::audit::{AuditContext, AuditEvent},
+    traits::{Students, Teachers},
+};
+
+/// Seconds an impersonation session lives, it is never extended by activity.
+const IMPERSONATION_TTL: i64 = 3600;
+
+/// Only an admin's own session can start an impersonation, never another impersonation.
+fn ensure_original(session: &Session) -> Result<(), AppError> {
+    match session.impersonator_id {
+        Some(_) => Err(AppError::Forbidden),
+        None => Ok(()),
+    }
+}
+
+/// Acting as another admin would hand out the same rights under a different name.
+fn ensure_impersonable(admin: &Teacher, teacher: &Teacher) -> Result<(), AppError> {
+    if teacher.id == admin.id || teacher.role == Role::Admin {
+        return Err(AppError::Forbidden);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/impersonate",
+    tag = "Auth",
+    request_body = ImpersonateRequest,
+    responses(
+        (status = 200, description = "Session acting as the user, read-only unless writes are allowed", body = ImpersonationResponse),
+        (status = 403, description = "The user can't be impersonated", body = [ErrorResponse]),
+        (status = 404, description = "User not found", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn impersonate(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(admin): Extension<Teacher>,
+    Extension(session): Extension<Session>,
+    audit: AuditContext,
+    Json(payload): Json<ImpersonateRequest>,
+) -> Result<Json<ImpersonationResponse>, AppError> {
+    ensure_original(&session)?;
+
+    let role = match payload.kind {
+        AccountKind::Teacher => {
+            let teacher = app_state.db.get_teacher_by_id(payload.id).await?;
+            ensure_impersonable(&admin, &teacher)?;
+            teacher.role
+        }
+        AccountKind::Student => {
+            app_state.db.get_student_by_id(payload.id).await?;
+            Role::Student
+        }
+    };
+
+    let impersonation = app_state
+        .redis
+        .create_impersonation_session(
+            payload.kind,
+            payload.id,
+            admin.id,
+            !payload.allow_writes,
+            &addr.ip().to_string(),
+            IMPERSONATION_TTL,
+        )
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("impersonate", payload.kind.as_str(), payload.id).after(&impersonation),
+        )
+        .await;
+
+    Ok(Json(ImpersonationResponse {
+        token: impersonation.token,
+        kind: payload.kind,
+        id: payload.id,
+        role,
+        read_only: impersonation.read_only,
+        expires_in: IMPERSONATION_TTL,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn teacher(id: i64, role: Role) -> Teacher {
+        Teacher {
+            id,
+            login: format!("teacher{}", id),
+            password_hash: String::new(),
+            full_name: format!("Teacher {}", id),
+            role,
+            email: None,
+            must_change_password: false,
+            totp_secret: None,
+            totp_enabled: false,
+        }
+    }
+
+    fn session(impersonator_id: Option<i64>) -> Session {
+        Session {
+            id: String::from("s"),
+            token: String::from("t"),
+            kind: AccountKind::Teacher,
+            user_id: 2,
+            device: String::new(),
+            ip: String::new(),
+            created_at: Utc::now(),
+            last_seen: Utc::now(),
+            impersonator_id,
+            read_only: impersonator_id.is_some(),
+        }
+    }
+
+    #[test]
+    fn nested_impersonation_is_refused() {
+        assert!(ensure_original(&session(None)).is_ok());
+        assert!(matches!(
+            ensure_original(&session(Some(1))),
+            Err(AppError::Forbidden)
+        ));
+    }
+
+    #[test]
+    fn admin_cant_impersonate_themselves() {
+        let admin = teacher(1, Role::Admin);
+        assert!(matches!(
+            ensure_impersonable(&admin, &admin),
+            Err(AppError::Forbidden)
+        ));
+    }
+
+    #[test]
+    fn other_admins_cant_be_impersonated() {
+        assert!(matches!(
+            ensure_impersonable(&teacher(1, Role::Admin), &teacher(2, Role::Admin)),
+            Err(AppError::Forbidden)
+        ));
+    }
+
+    #[test]
+    fn staff_can_be_impersonated() {
+        let admin = teacher(1, Role::Admin);
+        for role in [Role::Dispatcher, Role::Curator, Role::Teacher] {
+            assert!(ensure_impersonable(&admin, &teacher(2, role)).is_ok());
+        }
+    }
+
+    #[test]
+    fn impersonation_is_read_only_unless_writes_are_allowed() {
+        let request: ImpersonateRequest =
+            serde_json::from_str(r#"{"kind":"teacher","id":2}"#).unwrap();
+        assert!(!request.allow_writes);
+    }
+}
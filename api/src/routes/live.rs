@@ -0,0 +1,47 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
+
+use crate::{
+    config::AppState,
+    models::{LiveEvent, LiveQuery},
+};
+
+/// Keeps proxies from closing a stream that has been quiet for a while.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[utoipa::path(
+    get,
+    path = "/live_events",
+    tag = "Live",
+    params(LiveQuery),
+    responses(
+        (status = 200, description = "Server-sent events named after their kind with a JSON `LiveEvent` as data. \
+            A `resync` event means events were missed and the data should be fetched again.",
+            content_type = "text/event-stream", body = LiveEvent),
+    )
+)]
+pub async fn live_events(
+    State(app_state): State<AppState>,
+    Query(query): Query<LiveQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events =
+        BroadcastStream::new(app_state.live.subscribe()).filter_map(move |event| match event {
+            Ok(event) if query.matches(&event) => Event::default()
+                .event(event.kind.as_str())
+                .json_data(&event)
+                .ok()
+                .map(Ok),
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(_)) => Some(Ok(Event::default().event("resync"))),
+        });
+
+    Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE))
+}
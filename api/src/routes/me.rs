@@ -0,0 +1,429 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::Local;
+
+use crate::{
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{
+        ChangeOwnPasswordRequest, DisableTotpRequest, EditNotificationPreferenceRequest,
+        NotificationPreference, RecoveryCodesResponse, ScheduleChange, ScheduleRow, Session,
+        SessionInfo, Teacher, TeacherProfile, TotpCodeRequest, TotpSetupResponse,
+    },
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{hash_password, verify_password},
+        totp::{generate_recovery_codes, generate_secret, provisioning_uri, verify_totp},
+    },
+    traits::{NotificationPreferences, RecoveryCodes, ScheduleChanges, Schedules, Teachers},
+};
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "Me",
+    responses(
+        (status = 200, description = "Current teacher profile", body = TeacherProfile)
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_me(Extension(teacher): Extension<Teacher>) -> Json<TeacherProfile> {
+    Json(TeacherProfile::from(teacher))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me/password",
+    tag = "Me",
+    request_body = ChangeOwnPasswordRequest,
+    responses(
+        (status = 200, description = "Password changed", body = TeacherProfile),
+        (status = 400, description = "Old password is wrong", body = [ErrorResponse]),
+        (status = 422, description = "Password does not satisfy the policy", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn change_my_password(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    Extension(session): Extension<Session>,
+    audit: AuditContext,
+    Json(payload): Json<ChangeOwnPasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if !verify_password(&payload.old_password, &teacher.password_hash) {
+        return Err(AppError::BadRequest(String::from("Invalid password")));
+    }
+    if payload.new_password == payload.old_password {
+        return Err(AppError::Validation(String::from(
+            "New password must differ from the old one",
+        )));
+    }
+    app_state
+        .password_policy
+        .validate(&payload.new_password, &teacher.login)
+        .map_err(AppError::Validation)?;
+
+    let new_hash = hash_password(&payload.new_password);
+    let result = app_state
+        .db
+        .update_teacher_hash(teacher.id, &new_hash, false)
+        .await?;
+    app_state
+        .redis
+        .revoke_sessions(teacher.id, Some(&session.id))
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("change_my_password", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/schedule",
+    tag = "Me",
+    responses(
+        (status = 200, description = "Pairs of the current teacher", body = [Vec<ScheduleRow>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_my_schedule(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+) -> Result<Json<Vec<ScheduleRow>>, AppError> {
+    let pairs = app_state.db.get_teacher_pairs(teacher.id).await?;
+    Ok(Json(pairs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/changes",
+    tag = "Me",
+    responses(
+        (status = 200, description = "Upcoming changes of the current teacher", body = [Vec<ScheduleChange>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_my_changes(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+) -> Result<Json<Vec<ScheduleChange>>, AppError> {
+    let today = Local::now().date_naive();
+    let changes = app_state.db.get_teacher_changes(teacher.id, today).await?;
+    Ok(Json(changes))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/notifications",
+    tag = "Me",
+    responses(
+        (status = 200, description = "Notification subscriptions", body = NotificationPreference)
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_my_notifications(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+) -> Result<Json<NotificationPreference>, AppError> {
+    let preference = app_state.db.get_notification_preference(teacher.id).await?;
+    Ok(Json(preference))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me/notifications",
+    tag = "Me",
+    request_body = EditNotificationPreferenceRequest,
+    responses(
+        (status = 200, description = "Notification subscriptions updated", body = NotificationPreference)
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn update_my_notifications(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+    Json(payload): Json<EditNotificationPreferenceRequest>,
+) -> Result<Json<NotificationPreference>, AppError> {
+    let before = app_state.db.get_notification_preference(teacher.id).await?;
+    let preference = app_state
+        .db
+        .update_notification_preference(teacher.id, &payload)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new(
+                "update_my_notifications",
+                "notification_preference",
+                teacher.id,
+            )
+            .before(&before)
+            .after(&preference),
+        )
+        .await;
+    Ok(Json(preference))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/sessions",
+    tag = "Me",
+    responses(
+        (status = 200, description = "Active sessions of the current teacher", body = [Vec<SessionInfo>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_my_sessions(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    Extension(current): Extension<Session>,
+) -> Result<Json<Vec<SessionInfo>>, AppError> {
+    let sessions = app_state
+        .redis
+        .list_sessions(teacher.id)
+        .await?
+        .into_iter()
+        .map(|session| SessionInfo {
+            current: session.id == current.id,
+            session,
+        })
+        .collect();
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/sessions/{session_id}",
+    tag = "Me",
+    params(
+        ("session_id" = String, Path, description = "Session identificator")
+    ),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 404, description = "Session not found", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn revoke_my_session(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    if !app_state
+        .redis
+        .revoke_session(teacher.id, &session_id)
+        .await?
+    {
+        return Err(AppError::NotFound);
+    }
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("revoke_my_session", "session", session_id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(200)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/setup",
+    tag = "Me",
+    responses(
+        (status = 200, description = "New TOTP secret, not active until confirmed", body = TotpSetupResponse),
+        (status = 409, description = "Two-factor authentication is already enabled", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn setup_totp(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+) -> Result<Json<TotpSetupResponse>, AppError> {
+    if teacher.totp_enabled {
+        return Err(AppError::Conflict);
+    }
+
+    let secret = generate_secret();
+    let provisioning_uri = provisioning_uri(&app_state, &secret, &teacher.login)?;
+    app_state
+        .db
+        .update_teacher_totp(teacher.id, Some(&secret), false)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("setup_totp", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok(Json(TotpSetupResponse {
+        secret,
+        provisioning_uri,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/enable",
+    tag = "Me",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled, recovery codes are shown once", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or setup was not started", body = [ErrorResponse]),
+        (status = 409, description = "Two-factor authentication is already enabled", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn enable_totp(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    if teacher.totp_enabled {
+        return Err(AppError::Conflict);
+    }
+    if teacher.totp_secret.is_none() {
+        return Err(AppError::BadRequest(String::from(
+            "Two-factor setup was not started",
+        )));
+    }
+    if !verify_totp(&app_state, &teacher, &payload.code).await? {
+        return Err(AppError::BadRequest(String::from("Invalid code")));
+    }
+
+    app_state
+        .db
+        .update_teacher_totp(teacher.id, teacher.totp_secret.as_deref(), true)
+        .await?;
+    let (codes, hashes) = generate_recovery_codes();
+    app_state
+        .db
+        .replace_recovery_codes(teacher.id, &hashes)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("enable_totp", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok(Json(RecoveryCodesResponse { codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/disable",
+    tag = "Me",
+    request_body = DisableTotpRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = TeacherProfile),
+        (status = 400, description = "Invalid password or code", body = [ErrorResponse]),
+        (status = 403, description = "Two-factor authentication is required for the role", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn disable_totp(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+    Json(payload): Json<DisableTotpRequest>,
+) -> Result<Json<TeacherProfile>, AppError> {
+    if app_state.mfa.is_required(teacher.role) {
+        return Err(AppError::Forbidden);
+    }
+    if !verify_password(&payload.password, &teacher.password_hash)
+        || !verify_totp(&app_state, &teacher, &payload.code).await?
+    {
+        return Err(AppError::BadRequest(String::from(
+            "Invalid password or code",
+        )));
+    }
+
+    let result = app_state
+        .db
+        .update_teacher_totp(teacher.id, None, false)
+        .await?;
+    app_state.db.delete_recovery_codes(teacher.id).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("disable_totp", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok(Json(TeacherProfile::from(result)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/recovery_codes",
+    tag = "Me",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes, the old ones stop working", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    State(app_state): State<AppState>,
+    Extension(teacher): Extension<Teacher>,
+    audit: AuditContext,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    if !teacher.totp_enabled || !verify_totp(&app_state, &teacher, &payload.code).await? {
+        return Err(AppError::BadRequest(String::from("Invalid code")));
+    }
+
+    let (codes, hashes) = generate_recovery_codes();
+    app_state
+        .db
+        .replace_recovery_codes(teacher.id, &hashes)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("regenerate_recovery_codes", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok(Json(RecoveryCodesResponse { codes }))
+}
@@ -1,7 +1,17 @@
+pub mod api_keys;
+pub mod audit;
 pub mod fcm;
+pub mod group_grants;
 pub mod groups;
+pub mod impersonation;
+pub mod live;
+pub mod me;
 pub mod schedule;
 pub mod schedule_changes;
+pub mod student_me;
+pub mod students;
 pub mod subjects;
+pub mod sync;
 pub mod teacher_links;
 pub mod teachers;
+pub mod v1;
@@ -1,16 +1,25 @@
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::HeaderMap,
+    response::Response,
 };
 
 use crate::{
+    auth::GroupScope,
     config::AppState,
     errors::{AppError, ErrorResponse},
     models::{AddScheduleRequest, Schedule},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        cache::{cached_group_read, invalidate_groups},
+        concurrency::{check_version, guarded},
+        live::publish_schedule_update,
+        validation::CrossChecks,
+    },
     traits::Schedules,
 };
+use validator::Validate;
 
 #[utoipa::path(
     get,
@@ -20,17 +29,22 @@ use crate::{
         ("group_id" = i64, Path, description = "Group identificator")
     ),
     responses(
-        (status = 200, description = "Get schedule", body = [Vec<Schedule>])
+        (status = 200, description = "Get schedule", body = [Vec<Schedule>], headers(
+            ("ETag" = String, description = "Send back in If-None-Match to revalidate"),
+            ("Last-Modified" = String),
+        )),
+        (status = 304, description = "Schedule is unchanged since the given ETag or date"),
     ),
 )]
 pub async fn get_schedule(
     State(app_state): State<AppState>,
     Path(group_id): Path<i64>,
-) -> impl IntoResponse {
-    match app_state.db.get_schedule(group_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    cached_group_read(&app_state, group_id, "schedule", &headers, || async {
+        Ok((app_state.db.get_schedule(group_id).await?, None))
+    })
+    .await
 }
 
 #[utoipa::path(
@@ -43,6 +57,7 @@ pub async fn get_schedule(
     ),
     responses(
         (status = 200, description = "Day deleted"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -51,12 +66,43 @@ pub async fn get_schedule(
 )]
 pub async fn delete_day(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Path((group_id, weekday)): Path<(i64, i8)>,
-) -> impl IntoResponse {
-    match app_state.db.delete_day(group_id, weekday).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+) -> Result<Json<i64>, AppError> {
+    scope.ensure(group_id)?;
+
+    let before: Vec<_> = app_state
+        .db
+        .get_schedule(group_id)
+        .await?
+        .into_iter()
+        .filter(|day| day.weekday == weekday)
+        .collect();
+    let result = app_state.db.delete_day(group_id, weekday).await?;
+    invalidate_groups(&app_state, [group_id]).await;
+    let teacher_ids = before
+        .iter()
+        .flat_map(|day| day.pairs.iter().map(|p| p.teacher_id))
+        .collect();
+    tokio::spawn(publish_schedule_update(
+        app_state.clone(),
+        group_id,
+        weekday,
+        teacher_ids,
+    ));
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new(
+                "delete_day",
+                "schedule_day",
+                format!("{}:{}", group_id, weekday),
+            )
+            .before(&before),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -67,6 +113,7 @@ pub async fn delete_day(
         ("pair_id" = i64, Path, description = "Pair identificator")),
     responses(
         (status = 200, description = "Pair deleted"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -75,12 +122,28 @@ pub async fn delete_day(
 )]
 pub async fn delete_pair(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Path(pair_id): Path<i64>,
-) -> impl IntoResponse {
-    match app_state.db.delete_pair(pair_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+) -> Result<Json<i64>, AppError> {
+    let pair = app_state.db.get_pair(pair_id).await?;
+    scope.ensure(pair.group_id)?;
+
+    let result = app_state.db.delete_pair(pair_id).await?;
+    invalidate_groups(&app_state, [pair.group_id]).await;
+    tokio::spawn(publish_schedule_update(
+        app_state.clone(),
+        pair.group_id,
+        pair.weekday,
+        vec![pair.teacher_id],
+    ));
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_pair", "pair", pair_id).before(&pair),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -90,6 +153,8 @@ pub async fn delete_pair(
     request_body = AddScheduleRequest, 
     responses(
         (status = 200, description = "Pairs added", body = [Vec<Schedule>]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "Invalid pairs", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -98,12 +163,51 @@ pub async fn delete_pair(
 )]
 pub async fn add_pairs(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<AddScheduleRequest>,
-) -> impl IntoResponse {
-    match app_state.db.add_pairs(payload).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
+) -> Result<Json<Vec<Schedule>>, AppError> {
+    payload.validate()?;
+    scope.ensure(payload.group_id)?;
+
+    let mut checks = CrossChecks::new();
+    for (index, pair) in payload.pairs.iter().enumerate() {
+        checks
+            .subject_in_group(
+                &app_state,
+                format!("pairs[{}].subject_id", index),
+                pair.subject_id,
+                payload.group_id,
+            )
+            .await?;
+        checks
+            .teacher_exists(
+                &app_state,
+                format!("pairs[{}].teacher_id", index),
+                pair.teacher_id,
+            )
+            .await?;
     }
+    checks.finish()?;
+
+    let event = AuditEvent::new(
+        "add_pairs",
+        "schedule_day",
+        format!("{}:{}", payload.group_id, payload.weekday),
+    )
+    .after(&payload);
+    let (group_id, weekday) = (payload.group_id, payload.weekday);
+    let teacher_ids = payload.pairs.iter().map(|p| p.teacher_id).collect();
+    let result = app_state.db.add_pairs(payload).await?;
+    invalidate_groups(&app_state, [group_id]).await;
+    tokio::spawn(publish_schedule_update(
+        app_state.clone(),
+        group_id,
+        weekday,
+        teacher_ids,
+    ));
+    audit.record(&app_state, event).await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -113,15 +217,74 @@ pub async fn add_pairs(
     request_body = Schedule, 
     responses(
         (status = 200, description = "Day edited", body = [Vec<Schedule>]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 409, description = "A pair was changed since its `version`", body = [ErrorResponse]),
+        (status = 422, description = "Invalid pairs", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
         ("bearer_auth"=[])
     )
 )]
-pub async fn edit_pairs(State(app_state): State<AppState>, Json(payload): Json<Schedule>) -> impl IntoResponse{
-    match app_state.db.edit_pairs(payload).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
+pub async fn edit_pairs(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Json(payload): Json<Schedule>,
+) -> Result<Json<Vec<Schedule>>, AppError> {
+    payload.validate()?;
+    scope.ensure(payload.group_id)?;
+    let mut before = Vec::with_capacity(payload.pairs.len());
+    let mut checks = CrossChecks::new();
+    for (index, pair) in payload.pairs.iter().enumerate() {
+        let existing = app_state.db.get_pair(pair.id).await?;
+        scope.ensure(existing.group_id)?;
+        check_version(existing.version, pair.version)?;
+        checks.pair_in_group(format!("pairs[{}].id", index), &existing, payload.group_id);
+        before.push(existing);
+
+        checks
+            .subject_in_group(
+                &app_state,
+                format!("pairs[{}].subject_id", index),
+                pair.subject_id,
+                payload.group_id,
+            )
+            .await?;
+        checks
+            .teacher_exists(
+                &app_state,
+                format!("pairs[{}].teacher_id", index),
+                pair.teacher_id,
+            )
+            .await?;
     }
+    checks.finish()?;
+
+    let event = AuditEvent::new(
+        "edit_pairs",
+        "schedule_day",
+        format!("{}:{}", payload.group_id, payload.weekday),
+    )
+    .before(&before)
+    .after(&payload);
+    let (group_id, weekday) = (payload.group_id, payload.weekday);
+    // Both the old and the new teacher of an edited pair see it change.
+    let teacher_ids = payload
+        .pairs
+        .iter()
+        .map(|p| p.teacher_id)
+        .chain(before.iter().map(|p| p.teacher_id))
+        .collect();
+    let versioned = payload.pairs.iter().any(|p| p.version.is_some());
+    let result = guarded(app_state.db.edit_pairs(payload).await, versioned)?;
+    invalidate_groups(&app_state, [group_id]).await;
+    tokio::spawn(publish_schedule_update(
+        app_state.clone(),
+        group_id,
+        weekday,
+        teacher_ids,
+    ));
+    audit.record(&app_state, event).await;
+    Ok(Json(result))
 }
@@ -1,36 +1,86 @@
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::HeaderMap,
+    response::Response,
 };
 
 use crate::{
+    auth::GroupScope,
     config::AppState,
     errors::{AppError, ErrorResponse},
-    models::ScheduleChange,
-    traits::ScheduleChanges,
+    models::{ChangeFilter, LiveEventKind, PageQuery, ScheduleChange},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        cache::{cached_group_read, invalidate_groups},
+        concurrency::{check_version, guarded},
+        email::send_change_notices,
+        listing::ListQuery,
+        live::publish_changes,
+        validation::CrossChecks,
+    },
+    traits::{ScheduleChanges, Schedules},
 };
+use validator::Validate;
+
+fn change_ids(changes: &[ScheduleChange]) -> String {
+    changes
+        .iter()
+        .map(|c| c.schedule_id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Cache field of a changes page, built from the parsed query so unknown parameters and
+/// their order don't make new entries.
+fn changes_field(query: &ListQuery<ChangeFilter>) -> String {
+    let sort: Vec<_> = query
+        .sort
+        .iter()
+        .map(|(column, descending)| format!("{}{}", if *descending { "-" } else { "" }, column))
+        .collect();
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    format!(
+        "changes?limit={}&offset={}&sort={}&from={}&to={}",
+        optional(query.limit.map(|l| l.to_string())),
+        query.offset,
+        sort.join(","),
+        optional(query.filter.from.map(|d| d.to_string())),
+        optional(query.filter.to.map(|d| d.to_string())),
+    )
+}
 
 #[utoipa::path(
 get,
     path = "/get_schedule_changes/{group_id}",
     tag = "Schedule changes",
     params(
-        ("group_id" = i64, Path, description = "Group identificator")
+        ("group_id" = i64, Path, description = "Group identificator"),
+        PageQuery,
+        ChangeFilter,
     ),
     responses(
-        (status = 200, description = "Get schedule changes", body = [Vec<ScheduleChange>])
+        (status = 200, description = "Get schedule changes", body = [Vec<ScheduleChange>], headers(
+            ("X-Total-Count" = i64, description = "Rows matching the filters"),
+            ("ETag" = String, description = "Send back in If-None-Match to revalidate"),
+            ("Last-Modified" = String),
+        )),
+        (status = 304, description = "Changes are unchanged since the given ETag or date"),
+        (status = 422, description = "Unknown sort field", body = [ErrorResponse]),
     ),
 )]
 pub async fn get_schedule_changes(
     State(app_state): State<AppState>,
     Path(group_id): Path<i64>,
-) -> impl IntoResponse {
-    match app_state.db.get_schedule_changes(group_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+    headers: HeaderMap,
+    query: ListQuery<ChangeFilter>,
+) -> Result<Response, AppError> {
+    let field = changes_field(&query);
+    cached_group_read(&app_state, group_id, &field, &headers, || async {
+        let page = app_state.db.list_schedule_changes(group_id, &query).await?;
+        Ok((page.items, Some(page.total)))
+    })
+    .await
 }
 
 #[utoipa::path(
@@ -40,6 +90,8 @@ pub async fn get_schedule_changes(
     request_body = Vec<ScheduleChange>,
     responses(
         (status = 200, description = "Schedule changes added"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "Invalid changes", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -48,27 +100,39 @@ pub async fn get_schedule_changes(
 )]
 pub async fn add_schedule_changes(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<Vec<ScheduleChange>>,
-) -> impl IntoResponse {
-    match app_state.db.add_schedule_changes(payload).await {
-        Ok(result) => {
-            let mut teacher_ids = Vec::new();
-            let mut group_ids = Vec::new();
-
-            for change in &result {
-                if !group_ids.contains(&change.group_id) {
-                    group_ids.push(change.group_id);
-                }
+) -> Result<Json<Vec<ScheduleChange>>, AppError> {
+    payload.validate()?;
+    let mut checks = CrossChecks::new();
+    for (index, change) in payload.iter().enumerate() {
+        scope.ensure(change.group_id)?;
+        let pair = app_state.db.get_pair(change.schedule_id).await?;
+        scope.ensure(pair.group_id)?;
+        checks
+            .change(&app_state, &format!("[{}].", index), change, &pair)
+            .await?;
+    }
+    checks.finish()?;
 
-                if !teacher_ids.contains(&change.new_teacher_id) {
-                    teacher_ids.push(change.new_teacher_id);
-                }
-            }
+    let result = app_state.db.add_schedule_changes(payload).await?;
+    invalidate_groups(&app_state, result.iter().map(|c| c.group_id)).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_schedule_changes", "schedule_change", change_ids(&result))
+                .after(&result),
+        )
+        .await;
+    tokio::spawn(send_change_notices(app_state.clone(), result.clone(), false));
+    tokio::spawn(publish_changes(
+        app_state.clone(),
+        LiveEventKind::ChangeAdded,
+        result.clone(),
+    ));
 
-            (StatusCode::OK, Json(result)).into_response()
-        }
-        Err(e) => AppError::Database(e).into_response(),
-    }
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -78,6 +142,7 @@ pub async fn add_schedule_changes(
     request_body = Vec<i64>,
     responses(
         (status = 200, description = "Schedule changes deleted"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -86,12 +151,36 @@ pub async fn add_schedule_changes(
 )]
 pub async fn delete_schedule_changes(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<Vec<i64>>,
-) -> impl IntoResponse {
-    match app_state.db.delete_schedule_changes(payload).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
+) -> Result<Json<i64>, AppError> {
+    let removed = app_state.db.get_changes_by_ids(payload.clone()).await?;
+    for change in &removed {
+        scope.ensure(change.group_id)?;
     }
+
+    let result = app_state.db.delete_schedule_changes(payload).await?;
+    invalidate_groups(&app_state, removed.iter().map(|c| c.group_id)).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new(
+                "delete_schedule_changes",
+                "schedule_change",
+                change_ids(&removed),
+            )
+            .before(&removed),
+        )
+        .await;
+    tokio::spawn(publish_changes(
+        app_state.clone(),
+        LiveEventKind::ChangeRemoved,
+        removed.clone(),
+    ));
+    tokio::spawn(send_change_notices(app_state.clone(), removed, true));
+
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -101,6 +190,9 @@ pub async fn delete_schedule_changes(
     request_body = ScheduleChange,
     responses(
         (status = 200, description = "Schedule changes edited"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 409, description = "Change was edited since `version`", body = [ErrorResponse]),
+        (status = 422, description = "Invalid change", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -109,14 +201,61 @@ pub async fn delete_schedule_changes(
 )]
 pub async fn edit_schedule_changes(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<ScheduleChange>,
-) -> impl IntoResponse {
-    match app_state.db.edit_schedule_changes(payload.clone()).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => {
-            dbg!(payload.clone());
-            dbg!(&e);
-            AppError::Database(e).into_response()
+) -> Result<Json<ScheduleChange>, AppError> {
+    payload.validate()?;
+    scope.ensure(payload.group_id)?;
+    let pair = app_state.db.get_pair(payload.schedule_id).await?;
+    scope.ensure(pair.group_id)?;
+    let mut checks = CrossChecks::new();
+    checks.change(&app_state, "", &payload, &pair).await?;
+    checks.finish()?;
+    let before = app_state
+        .db
+        .get_changes_by_ids(vec![payload.schedule_id])
+        .await?;
+    for change in &before {
+        scope.ensure(change.group_id)?;
+        if let Some(stored) = change.version {
+            check_version(stored, payload.version)?;
         }
     }
+
+    // Without a stored change the update finds nothing and answers 404.
+    let versioned = payload.version.is_some() && !before.is_empty();
+    let result = guarded(
+        app_state.db.edit_schedule_changes(payload).await,
+        versioned,
+    )?;
+    invalidate_groups(
+        &app_state,
+        before.iter().map(|c| c.group_id).chain([result.group_id]),
+    )
+    .await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new(
+                "edit_schedule_changes",
+                "schedule_change",
+                result.schedule_id,
+            )
+            .before(&before)
+            .after(&result),
+        )
+        .await;
+    tokio::spawn(send_change_notices(
+        app_state.clone(),
+        vec![result.clone()],
+        false,
+    ));
+    tokio::spawn(publish_changes(
+        app_state.clone(),
+        LiveEventKind::ChangeEdited,
+        vec![result.clone()],
+    ));
+
+    Ok(Json(result))
 }
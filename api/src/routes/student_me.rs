@@ -0,0 +1,135 @@
+use axum::{Extension, Json, extract::State, http::StatusCode, response::IntoResponse};
+
+use crate::{
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{ChangeOwnPasswordRequest, ScheduleChange, ScheduleRow, Session, Student},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{hash_password, verify_password},
+    },
+    traits::{ScheduleChanges, Schedules, Students},
+};
+
+#[utoipa::path(
+    get,
+    path = "/student/me",
+    tag = "Student",
+    responses(
+        (status = 200, description = "Current student profile", body = Student)
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_student_profile(Extension(student): Extension<Student>) -> Json<Student> {
+    Json(student)
+}
+
+#[utoipa::path(
+    get,
+    path = "/student/me/schedule",
+    tag = "Student",
+    responses(
+        (status = 200, description = "Pairs of the student's group and subgroup", body = [Vec<ScheduleRow>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_student_schedule(
+    State(app_state): State<AppState>,
+    Extension(student): Extension<Student>,
+) -> Result<Json<Vec<ScheduleRow>>, AppError> {
+    let pairs = app_state
+        .db
+        .get_subgroup_pairs(student.group_id, student.subgroup)
+        .await?;
+    Ok(Json(pairs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/student/me/changes",
+    tag = "Student",
+    responses(
+        (status = 200, description = "Upcoming changes of the pairs the student attends", body = [Vec<ScheduleChange>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_student_changes(
+    State(app_state): State<AppState>,
+    Extension(student): Extension<Student>,
+) -> Result<Json<Vec<ScheduleChange>>, AppError> {
+    let mut changes = app_state.db.get_schedule_changes(student.group_id).await?;
+    if student.subgroup.is_some() {
+        let pair_ids: Vec<i64> = app_state
+            .db
+            .get_subgroup_pairs(student.group_id, student.subgroup)
+            .await?
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        changes.retain(|c| pair_ids.contains(&c.schedule_id));
+    }
+
+    Ok(Json(changes))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/student/me/password",
+    tag = "Student",
+    request_body = ChangeOwnPasswordRequest,
+    responses(
+        (status = 200, description = "Password changed", body = Student),
+        (status = 400, description = "Old password is wrong", body = [ErrorResponse]),
+        (status = 422, description = "Password does not satisfy the policy", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn change_student_password(
+    State(app_state): State<AppState>,
+    Extension(student): Extension<Student>,
+    Extension(session): Extension<Session>,
+    audit: AuditContext,
+    Json(payload): Json<ChangeOwnPasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let (Some(login), Some(password_hash)) = (&student.login, &student.password_hash) else {
+        return Err(AppError::Forbidden);
+    };
+    if !verify_password(&payload.old_password, password_hash) {
+        return Err(AppError::BadRequest(String::from("Invalid password")));
+    }
+    if payload.new_password == payload.old_password {
+        return Err(AppError::Validation(String::from(
+            "New password must differ from the old one",
+        )));
+    }
+    app_state
+        .password_policy
+        .validate(&payload.new_password, login)
+        .map_err(AppError::Validation)?;
+
+    let new_hash = hash_password(&payload.new_password);
+    let result = app_state
+        .db
+        .update_student_hash(student.id, &new_hash, false)
+        .await?;
+    app_state
+        .redis
+        .revoke_student_sessions(student.id, Some(&session.id))
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("change_student_password", "student", student.id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(result)))
+}
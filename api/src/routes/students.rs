@@ -0,0 +1,260 @@
+use crate::{
+    auth::GroupScope,
+    config::AppState,
+    errors::{AppError, ErrorResponse},
+    models::{
+        AddStudentsRequest, EditStudentRequest, IssueStudentPasswordRequest, Student,
+        StudentCreated, StudentPasswordResponse,
+    },
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{generate_code, hash_password},
+    },
+    traits::{Groups, Students},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+const STUDENT_PASSWORD_LENGTH: usize = 10;
+const MAX_SUBGROUP: i8 = 9;
+
+fn validate_subgroup(subgroup: Option<i8>) -> Result<(), AppError> {
+    match subgroup {
+        Some(s) if !(1..=MAX_SUBGROUP).contains(&s) => Err(AppError::Validation(format!(
+            "Subgroup must be between 1 and {}",
+            MAX_SUBGROUP
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_students/{group_id}",
+    tag = "Students",
+    params(
+        ("group_id" = i64, Path, description = "Group identificator")
+    ),
+    responses(
+        (status = 200, description = "Students of the group", body = [Vec<Student>]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_students(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    Path(group_id): Path<i64>,
+) -> Result<Json<Vec<Student>>, AppError> {
+    scope.ensure(group_id)?;
+
+    let students = app_state.db.get_students(group_id).await?;
+    Ok(Json(students))
+}
+
+#[utoipa::path(
+    post,
+    path = "/add_students",
+    tag = "Students",
+    request_body = AddStudentsRequest,
+    responses(
+        (status = 200, description = "Added students, one-time passwords are shown once", body = [Vec<StudentCreated>]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "Invalid subgroup or repeated login", body = [ErrorResponse]),
+        (status = 409, description = "Login is taken", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn add_students(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Json(payload): Json<AddStudentsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    scope.ensure(payload.group_id)?;
+    app_state.db.get_group_by_id(payload.group_id).await?;
+
+    let mut logins = Vec::new();
+    for student in &payload.students {
+        validate_subgroup(student.subgroup)?;
+        if let Some(login) = &student.login {
+            if logins.contains(&login) {
+                return Err(AppError::Validation(format!("Login {} is repeated", login)));
+            }
+            logins.push(login);
+        }
+    }
+
+    let passwords: Vec<Option<String>> = payload
+        .students
+        .iter()
+        .map(|s| {
+            s.login
+                .as_ref()
+                .map(|_| generate_code(STUDENT_PASSWORD_LENGTH))
+        })
+        .collect();
+    let hashes: Vec<Option<String>> = passwords
+        .iter()
+        .map(|p| p.as_deref().map(hash_password))
+        .collect();
+    let students = app_state
+        .db
+        .add_students(payload.group_id, &payload.students, &hashes)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_students", "group", payload.group_id).after(&students),
+        )
+        .await;
+
+    let created: Vec<StudentCreated> = students
+        .into_iter()
+        .zip(passwords)
+        .map(|(student, password)| StudentCreated { student, password })
+        .collect();
+    Ok((StatusCode::OK, Json(created)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/edit_student",
+    tag = "Students",
+    request_body = EditStudentRequest,
+    responses(
+        (status = 200, description = "Student edited", body = Student),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "Invalid subgroup", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn edit_student(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Json(payload): Json<EditStudentRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    validate_subgroup(payload.subgroup)?;
+    let before = app_state.db.get_student_by_id(payload.id).await?;
+    scope.ensure(before.group_id)?;
+    scope.ensure(payload.group_id)?;
+    app_state.db.get_group_by_id(payload.group_id).await?;
+
+    let result = app_state.db.edit_student(&payload).await?;
+    if result.login != before.login {
+        app_state
+            .redis
+            .revoke_student_sessions(result.id, None)
+            .await?;
+    }
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("edit_student", "student", result.id)
+                .before(&before)
+                .after(&result),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(result)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/delete_student/{student_id}",
+    tag = "Students",
+    params(
+        ("student_id" = i64, Path, description = "Student identificator")
+    ),
+    responses(
+        (status = 200, description = "Student deleted"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn delete_student(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Path(student_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let before = app_state.db.get_student_by_id(student_id).await?;
+    scope.ensure(before.group_id)?;
+
+    let result = app_state.db.delete_student(student_id).await?;
+    app_state
+        .redis
+        .revoke_student_sessions(student_id, None)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_student", "student", student_id).before(&before),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(result)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/issue_student_password",
+    tag = "Students",
+    request_body = IssueStudentPasswordRequest,
+    responses(
+        (status = 200, description = "One-time password, the student changes it on first login", body = StudentPasswordResponse),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "The student has no login", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn issue_student_password(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Json(payload): Json<IssueStudentPasswordRequest>,
+) -> Result<Json<StudentPasswordResponse>, AppError> {
+    let student = app_state.db.get_student_by_id(payload.id).await?;
+    scope.ensure(student.group_id)?;
+    if student.login.is_none() {
+        return Err(AppError::Validation(String::from(
+            "The student has no login",
+        )));
+    }
+
+    let password = generate_code(STUDENT_PASSWORD_LENGTH);
+    app_state
+        .db
+        .update_student_hash(student.id, &hash_password(&password), true)
+        .await?;
+    app_state
+        .redis
+        .revoke_student_sessions(student.id, None)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("issue_student_password", "student", student.id),
+        )
+        .await;
+
+    Ok(Json(StudentPasswordResponse { password }))
+}
@@ -1,11 +1,19 @@
-use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::{Path, State}, response::IntoResponse, Extension, Json};
 
 use crate::{
+    auth::GroupScope,
     config::AppState,
     errors::{AppError, ErrorResponse},
-    models::{AddSubjectRequest, EditSubjectRequest, Subject},
+    models::{AddSubjectRequest, EditSubjectRequest, PageQuery, Subject, SubjectFilter},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        cache::invalidate_groups,
+        concurrency::{check_version, guarded},
+        listing::ListQuery,
+    },
     traits::Subjects,
 };
+use validator::Validate;
 
 #[utoipa::path(
     post,
@@ -14,6 +22,8 @@ use crate::{
     request_body = AddSubjectRequest,
     responses(
         (status = 200, description = "Added subject", body = [Subject]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 422, description = "Invalid name or unknown group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -22,16 +32,25 @@ use crate::{
 )]
 pub async fn add_subject(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<AddSubjectRequest>,
-) -> impl IntoResponse {
-    match app_state
+) -> Result<Json<Subject>, AppError> {
+    payload.validate()?;
+    scope.ensure(payload.group_id)?;
+
+    let result = app_state
         .db
         .add_subject(&payload.name, &payload.group_id)
-        .await
-    {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+        .await?;
+    invalidate_groups(&app_state, [result.group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_subject", "subject", result.id).after(&result),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -41,17 +60,43 @@ pub async fn add_subject(
     request_body = EditSubjectRequest,
     responses(
         (status = 200, description = "Subject edited", body = [Subject]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
+        (status = 409, description = "Subject was changed since `version`", body = [ErrorResponse]),
+        (status = 422, description = "Invalid name", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse])
     ),
     security(
         ("bearer_auth"=[])
     ) 
 )]
-pub async fn edit_subject(State(app_state): State<AppState>, Json(payload): Json<EditSubjectRequest>) -> impl IntoResponse{
-   match app_state.db.edit_subject(&payload.id, &payload.new_name).await{
-       Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-       Err(e) => AppError::Database(e).into_response()
-   } 
+pub async fn edit_subject(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Json(payload): Json<EditSubjectRequest>,
+) -> Result<Json<Subject>, AppError> {
+    payload.validate()?;
+    let subject = app_state.db.get_subject_by_id(payload.id).await?;
+    scope.ensure(subject.group_id)?;
+    check_version(subject.version, payload.version)?;
+
+    let result = guarded(
+        app_state
+            .db
+            .edit_subject(&payload.id, &payload.new_name, payload.version)
+            .await,
+        payload.version.is_some(),
+    )?;
+    invalidate_groups(&app_state, [subject.group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("edit_subject", "subject", payload.id)
+                .before(&subject)
+                .after(&result),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -60,6 +105,7 @@ pub async fn edit_subject(State(app_state): State<AppState>, Json(payload): Json
     tag = "Subjects",
     responses(
         (status = 200, description = "Subject deleted"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse])
     ),
     security(
@@ -69,11 +115,24 @@ pub async fn edit_subject(State(app_state): State<AppState>, Json(payload): Json
         ("subject_id" = i64, Path, description = "Subject identificator")
     ),
 )]
-pub async fn delete_subject(State(app_state): State<AppState>, Path(subject_id): Path<i64>) -> impl IntoResponse{
-    match app_state.db.delete_subject(subject_id).await{
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+pub async fn delete_subject(
+    State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
+    Path(subject_id): Path<i64>,
+) -> Result<Json<i16>, AppError> {
+    let subject = app_state.db.get_subject_by_id(subject_id).await?;
+    scope.ensure(subject.group_id)?;
+
+    let result = app_state.db.delete_subject(subject_id).await?;
+    invalidate_groups(&app_state, [subject.group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_subject", "subject", subject_id).before(&subject),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -81,16 +140,19 @@ pub async fn delete_subject(State(app_state): State<AppState>, Path(subject_id):
     path = "/get_subjects_by_group_id/{group_id}",
     tag = "Subjects",
     responses(
-        (status = 200, description = "List of subjects", body = [Vec<Subject>]),
+        (status = 200, description = "List of subjects", body = [Vec<Subject>], headers(("X-Total-Count" = i64, description = "Rows matching the filters"))),
+        (status = 422, description = "Unknown sort field", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     params(
-        ("group_id" = i64, Path, description = "Group identificator")
+        ("group_id" = i64, Path, description = "Group identificator"),
+        PageQuery,
+        SubjectFilter,
     ),
 )]
-pub async fn get_subjects_by_group_id(State(app_state): State<AppState>, Path(group_id): Path<i64>) -> impl IntoResponse{
-    match app_state.db.get_subjects_by_group_id(group_id).await{
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+pub async fn get_subjects_by_group_id(State(app_state): State<AppState>, Path(group_id): Path<i64>, query: ListQuery<SubjectFilter>) -> impl IntoResponse{
+    match app_state.db.list_subjects_by_group_id(group_id, &query).await{
+        Ok(result) => result.into_response(),
         Err(e) => AppError::Database(e).into_response()
     }
 }
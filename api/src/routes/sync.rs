@@ -0,0 +1,42 @@
+use crate::{
+    config::AppState,
+    errors::{AppError, ErrorResponse, FieldError},
+    models::{SyncQuery, SyncResponse},
+    traits::DeltaSync,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use chrono::DateTime;
+
+#[utoipa::path(
+    get,
+    path = "/sync",
+    tag = "Sync",
+    params(SyncQuery),
+    responses(
+        (status = 200, description = "Rows created, updated or deleted since the cursor", body = SyncResponse),
+        (status = 422, description = "Invalid cursor", body = [ErrorResponse]),
+    )
+)]
+pub async fn sync(
+    State(app_state): State<AppState>,
+    Query(query): Query<SyncQuery>,
+) -> Result<Json<SyncResponse>, AppError> {
+    let since = match &query.since {
+        Some(cursor) => Some(
+            cursor
+                .parse::<i64>()
+                .ok()
+                .and_then(DateTime::from_timestamp_micros)
+                .ok_or_else(|| {
+                    AppError::FieldValidation(vec![FieldError::new("since", "Invalid cursor")])
+                })?,
+        ),
+        None => None,
+    };
+
+    let delta = app_state.db.get_sync_delta(since, query.group_id).await?;
+    Ok(Json(delta))
+}
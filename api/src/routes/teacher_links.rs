@@ -1,16 +1,23 @@
 use crate::{
+    auth::GroupScope,
     config::AppState,
     errors::{AppError, ErrorResponse},
     models::{teacher_links::TeacherLink},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        cache::invalidate_groups,
+    },
     traits::TeacherLinks,
 };
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
 };
 
+fn link_id(link: &TeacherLink) -> String {
+    format!("{}:{}:{}", link.group_id, link.teacher_id, link.subject_id)
+}
+
 #[utoipa::path(
     get,
     path = "/get_teacher_links/{group_id}",
@@ -37,6 +44,7 @@ pub async fn get_teacher_links(
     request_body = TeacherLink,
     responses(
         (status = 200, description = "Added link", body = [TeacherLink]),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -45,13 +53,25 @@ pub async fn get_teacher_links(
 )]
 pub async fn add_teacher_link(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<TeacherLink>,
-) -> impl IntoResponse {
+) -> Result<Json<TeacherLink>, AppError> {
+    scope.ensure(payload.group_id)?;
+
     //ФИКС ДУБЛИКАТОВ
-    match app_state.db.add_teacher_link(payload.group_id, payload.teacher_id, payload.subject_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+    let result = app_state
+        .db
+        .add_teacher_link(payload.group_id, payload.teacher_id, payload.subject_id)
+        .await?;
+    invalidate_groups(&app_state, [payload.group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_teacher_link", "teacher_link", link_id(&result)).after(&result),
+        )
+        .await;
+    Ok(Json(result))
 }
 
 #[utoipa::path(
@@ -61,6 +81,7 @@ pub async fn add_teacher_link(
     request_body = TeacherLink,
     responses(
         (status = 200, description = "Deleted teacher link"),
+        (status = 403, description = "No access to the group", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -69,10 +90,23 @@ pub async fn add_teacher_link(
 )]
 pub async fn delete_teacher_link(
     State(app_state): State<AppState>,
+    Extension(scope): Extension<GroupScope>,
+    audit: AuditContext,
     Json(payload): Json<TeacherLink>,
-) -> impl IntoResponse {
-    match app_state.db.delete_teacher_link(payload.group_id, payload.teacher_id, payload.subject_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+) -> Result<Json<i16>, AppError> {
+    scope.ensure(payload.group_id)?;
+
+    let result = app_state
+        .db
+        .delete_teacher_link(payload.group_id, payload.teacher_id, payload.subject_id)
+        .await?;
+    invalidate_groups(&app_state, [payload.group_id]).await;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_teacher_link", "teacher_link", link_id(&payload))
+                .before(&payload),
+        )
+        .await;
+    Ok(Json(result))
 }
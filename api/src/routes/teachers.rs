@@ -1,12 +1,20 @@
 use crate::{
+    auth::Role,
     config::AppState,
     errors::{AppError, ErrorResponse},
     models::{
-        AddTeacherRequest, EditTeacherFullnameRequest, EditTeacherLoginRequest,
-        EditTeacherPasswordRequest, Teacher, TeacherSafe,
+        AddTeacherRequest, EditTeacherEmailRequest, EditTeacherFullnameRequest,
+        EditTeacherLoginRequest, EditTeacherPasswordRequest, EditTeacherRoleRequest,
+        IssueResetCodeRequest, PageQuery, ResetCodeResponse, RoleInfo, Session, TeacherFilter,
+        TeacherProfile, TeacherSafe,
     },
-    services::auth::hash_password,
-    traits::Teachers,
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{generate_code, hash_password},
+        cache::invalidate_groups,
+        listing::ListQuery,
+    },
+    traits::{Schedules, Teachers},
 };
 use axum::{
     Json,
@@ -14,6 +22,10 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
+use validator::Validate;
+
+const RESET_CODE_LENGTH: usize = 10;
+const RESET_CODE_TTL: u64 = 86400;
 
 #[utoipa::path(
     post,
@@ -21,7 +33,9 @@ use axum::{
     tag = "Teachers",
     request_body = AddTeacherRequest,
     responses(
-        (status = 200, description = "Added teacher", body = [Teacher]),
+        (status = 200, description = "Added teacher", body = [TeacherProfile]),
+        (status = 409, description = "Login is taken", body = [ErrorResponse]),
+        (status = 422, description = "Invalid fields or password does not satisfy the policy", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -30,17 +44,35 @@ use axum::{
 )]
 pub async fn add_teacher(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<AddTeacherRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+    app_state
+        .password_policy
+        .validate(&payload.password, &payload.login)
+        .map_err(AppError::Validation)?;
+
     let password_hash = hash_password(&payload.password);
-    match app_state
+    let result = app_state
         .db
-        .add_teacher(&payload.login, &password_hash, &payload.full_name)
-        .await
-    {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => {dbg!(&e);AppError::Database(e).into_response()},
-    }
+        .add_teacher(
+            &payload.login,
+            &password_hash,
+            &payload.full_name,
+            payload.email.as_deref(),
+            true,
+        )
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("add_teacher", "teacher", result.id)
+                .after(&TeacherProfile::from(result.clone())),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
 }
 
 #[utoipa::path(
@@ -52,6 +84,7 @@ pub async fn add_teacher(
     ),
     responses(
         (status = 200, description = "Deleted teacher"),
+        (status = 409, description = "The last admin can't be deleted", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -60,12 +93,29 @@ pub async fn add_teacher(
 )]
 pub async fn delete_teacher(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Path(teacher_id): Path<i64>,
-) -> impl IntoResponse {
-    match app_state.db.delete_teacher(teacher_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
+) -> Result<impl IntoResponse, AppError> {
+    let before = app_state.db.get_teacher_by_id(teacher_id).await?;
+    if before.role == Role::Admin
+        && app_state.db.count_teachers_with_role(Role::Admin).await? <= 1
+    {
+        return Err(AppError::Conflict);
     }
+    // Pairs of the teacher go with them, so their groups' schedules change.
+    let pairs = app_state.db.get_teacher_pairs(teacher_id).await?;
+    let result = app_state.db.delete_teacher(teacher_id).await?;
+    invalidate_groups(&app_state, pairs.iter().map(|p| p.group_id)).await;
+    app_state.redis.revoke_sessions(teacher_id, None).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("delete_teacher", "teacher", teacher_id)
+                .before(&TeacherProfile::from(before)),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(result)))
 }
 
 #[utoipa::path(
@@ -74,7 +124,8 @@ pub async fn delete_teacher(
     tag = "Teachers",
     request_body = EditTeacherPasswordRequest,
     responses(
-        (status = 200, description = "Edited teachers password", body = [Teacher]),
+        (status = 200, description = "Edited teachers password", body = [TeacherProfile]),
+        (status = 422, description = "Password does not satisfy the policy", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -83,17 +134,29 @@ pub async fn delete_teacher(
 )]
 pub async fn update_teacher_password(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<EditTeacherPasswordRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
+    let teacher = app_state.db.get_teacher_by_id(payload.id).await?;
+    app_state
+        .password_policy
+        .validate(&payload.password, &teacher.login)
+        .map_err(AppError::Validation)?;
+
     let new_hash = hash_password(&payload.password);
-    match app_state
+    let result = app_state
         .db
-        .update_teacher_hash(payload.id, &new_hash)
-        .await
-    {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+        .update_teacher_hash(payload.id, &new_hash, true)
+        .await?;
+    app_state.redis.revoke_sessions(payload.id, None).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("update_teacher_password", "teacher", payload.id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
 }
 
 #[utoipa::path(
@@ -102,7 +165,9 @@ pub async fn update_teacher_password(
     tag = "Teachers",
     request_body = EditTeacherLoginRequest,
     responses(
-        (status = 200, description = "Edited teacher login", body = [Teacher]),
+        (status = 200, description = "Edited teacher login", body = [TeacherProfile]),
+        (status = 409, description = "Login is taken", body = [ErrorResponse]),
+        (status = 422, description = "Invalid login", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -111,16 +176,26 @@ pub async fn update_teacher_password(
 )]
 pub async fn update_teacher_login(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<EditTeacherLoginRequest>,
-) -> impl IntoResponse {
-    match app_state
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+    let before = app_state.db.get_teacher_by_id(payload.id).await?;
+    let result = app_state
         .db
         .update_teacher_login(payload.id, &payload.login)
-        .await
-    {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => AppError::Database(e).into_response(),
-    }
+        .await?;
+    app_state.redis.revoke_sessions(payload.id, None).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("update_teacher_login", "teacher", payload.id)
+                .before(&TeacherProfile::from(before))
+                .after(&TeacherProfile::from(result.clone())),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
 }
 
 #[utoipa::path(
@@ -129,7 +204,8 @@ pub async fn update_teacher_login(
     tag = "Teachers",
     request_body = EditTeacherFullnameRequest,
     responses(
-        (status = 200, description = "Edited teacher fullname", body = [Teacher]),
+        (status = 200, description = "Edited teacher fullname", body = [TeacherProfile]),
+        (status = 422, description = "Invalid full name", body = [ErrorResponse]),
         (status = 500, description = "Database error", body = [ErrorResponse]),
     ),
     security(
@@ -138,29 +214,153 @@ pub async fn update_teacher_login(
 )]
 pub async fn update_teacher_fullname(
     State(app_state): State<AppState>,
+    audit: AuditContext,
     Json(payload): Json<EditTeacherFullnameRequest>,
-) -> impl IntoResponse {
-    match app_state
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+    let before = app_state.db.get_teacher_by_id(payload.id).await?;
+    let result = app_state
         .db
         .update_teacher_fullname(payload.id, &payload.full_name)
-        .await
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("update_teacher_fullname", "teacher", payload.id)
+                .before(&TeacherProfile::from(before))
+                .after(&TeacherProfile::from(result.clone())),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/update_teacher_email",
+    tag = "Teachers",
+    request_body = EditTeacherEmailRequest,
+    responses(
+        (status = 200, description = "Edited teacher email", body = [TeacherProfile]),
+        (status = 422, description = "Invalid email", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn update_teacher_email(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Json(payload): Json<EditTeacherEmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+    let before = app_state.db.get_teacher_by_id(payload.id).await?;
+    let result = app_state
+        .db
+        .update_teacher_email(payload.id, payload.email.as_deref())
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("update_teacher_email", "teacher", payload.id)
+                .before(&TeacherProfile::from(before))
+                .after(&TeacherProfile::from(result.clone())),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/update_teacher_role",
+    tag = "Teachers",
+    request_body = EditTeacherRoleRequest,
+    responses(
+        (status = 200, description = "Edited teacher role", body = [TeacherProfile]),
+        (status = 409, description = "The last admin can't be demoted", body = [ErrorResponse]),
+        (status = 422, description = "The role is reserved for students", body = [ErrorResponse]),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn update_teacher_role(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Json(payload): Json<EditTeacherRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.role == Role::Student {
+        return Err(AppError::Validation(String::from(
+            "Teachers can't hold the student role",
+        )));
+    }
+    let teacher = app_state.db.get_teacher_by_id(payload.id).await?;
+
+    if teacher.role == Role::Admin
+        && payload.role != Role::Admin
+        && app_state.db.count_teachers_with_role(Role::Admin).await? <= 1
     {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
-        Err(e) => {dbg!(&e);AppError::Database(e).into_response()},
+        return Err(AppError::Conflict);
     }
+
+    let result = app_state
+        .db
+        .update_teacher_role(payload.id, payload.role)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("update_teacher_role", "teacher", payload.id)
+                .before(&TeacherProfile::from(teacher))
+                .after(&TeacherProfile::from(result.clone())),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(TeacherProfile::from(result))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_roles",
+    tag = "Teachers",
+    responses(
+        (status = 200, description = "Roles with their permissions", body = [Vec<RoleInfo>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_roles() -> Json<Vec<RoleInfo>> {
+    Json(
+        Role::ALL
+            .into_iter()
+            .map(|role| RoleInfo {
+                role,
+                permissions: role.permissions().to_vec(),
+            })
+            .collect(),
+    )
 }
 
 #[utoipa::path(
     get,
     path = "/get_teachers",
     tag = "Teachers",
+    params(PageQuery, TeacherFilter),
     responses(
-        (status = 200, description = "Get teacher list", body = [Vec<TeacherSafe>])
+        (status = 200, description = "Get teacher list", body = [Vec<TeacherSafe>], headers(("X-Total-Count" = i64, description = "Rows matching the filters"))),
+        (status = 422, description = "Unknown sort field", body = [ErrorResponse]),
     )
 )]
-pub async fn get_teachers(State(app_state): State<AppState>) -> impl IntoResponse {
-    match app_state.db.get_teachers().await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+pub async fn get_teachers(
+    State(app_state): State<AppState>,
+    query: ListQuery<TeacherFilter>,
+) -> impl IntoResponse {
+    match app_state.db.list_teachers(&query).await {
+        Ok(result) => result.into_response(),
         Err(e) => AppError::Database(e).into_response(),
     }
 }
@@ -173,7 +373,7 @@ pub async fn get_teachers(State(app_state): State<AppState>) -> impl IntoRespons
         ("teacher_id" = i64, Path, description = "Teacher identificator")
     ),
     responses(
-        (status = 200, description = "Get teacher", body = [Teacher])
+        (status = 200, description = "Get teacher", body = [TeacherProfile])
     ),
     security(
         ("bearer_auth"=[])
@@ -184,7 +384,97 @@ pub async fn get_teacher_by_id(
     Path(teacher_id): Path<i64>,
 ) -> impl IntoResponse {
     match app_state.db.get_teacher_by_id(teacher_id).await {
-        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        Ok(result) => (StatusCode::OK, Json(TeacherProfile::from(result))).into_response(),
         Err(e) => AppError::Database(e).into_response(),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/get_teacher_sessions/{teacher_id}",
+    tag = "Teachers",
+    params(
+        ("teacher_id" = i64, Path, description = "Teacher identificator")
+    ),
+    responses(
+        (status = 200, description = "Active sessions of the teacher", body = [Vec<Session>])
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn get_teacher_sessions(
+    State(app_state): State<AppState>,
+    Path(teacher_id): Path<i64>,
+) -> Result<Json<Vec<Session>>, AppError> {
+    let sessions = app_state.redis.list_sessions(teacher_id).await?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/revoke_teacher_sessions/{teacher_id}",
+    tag = "Teachers",
+    params(
+        ("teacher_id" = i64, Path, description = "Teacher identificator")
+    ),
+    responses(
+        (status = 200, description = "Number of revoked sessions", body = usize),
+        (status = 500, description = "Redis error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn revoke_teacher_sessions(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Path(teacher_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let revoked = app_state.redis.revoke_sessions(teacher_id, None).await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("revoke_teacher_sessions", "teacher", teacher_id),
+        )
+        .await;
+
+    Ok((StatusCode::OK, Json(revoked)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/issue_reset_code",
+    tag = "Teachers",
+    request_body = IssueResetCodeRequest,
+    responses(
+        (status = 200, description = "One-time password reset code", body = ResetCodeResponse),
+        (status = 500, description = "Database error", body = [ErrorResponse]),
+    ),
+    security(
+        ("bearer_auth"=[])
+    )
+)]
+pub async fn issue_reset_code(
+    State(app_state): State<AppState>,
+    audit: AuditContext,
+    Json(payload): Json<IssueResetCodeRequest>,
+) -> Result<Json<ResetCodeResponse>, AppError> {
+    let teacher = app_state.db.get_teacher_by_id(payload.id).await?;
+    let code = generate_code(RESET_CODE_LENGTH);
+    app_state
+        .redis
+        .set_reset_code(teacher.id, &hash_password(&code), RESET_CODE_TTL)
+        .await?;
+    audit
+        .record(
+            &app_state,
+            AuditEvent::new("issue_reset_code", "teacher", teacher.id),
+        )
+        .await;
+
+    Ok(Json(ResetCodeResponse {
+        code,
+        expires_in: RESET_CODE_TTL,
+    }))
+}
@@ -0,0 +1,614 @@
+//! Resource-oriented `/api/v1` paths. Each one is rewritten onto the legacy route that
+//! implements it, so handlers, permission checks and documentation are shared while the
+//! legacy paths stay available as deprecated aliases.
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::{ConnectInfo, Path, Request},
+    http::{
+        Extensions, HeaderValue, Method, StatusCode,
+        header::{CONTENT_LENGTH, CONTENT_TYPE, LINK},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{MethodFilter, on},
+};
+use serde_json::{Map, Value};
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+use crate::errors::AppError;
+
+pub const PREFIX: &str = "/api/v1";
+
+/// Largest request body read while moving path parameters into it.
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// How path parameters reach a legacy handler that expects them in the JSON body.
+pub enum Inject {
+    None,
+    /// Sets body fields, given as `(path parameter, body field)` pairs
+    Fields(&'static [(&'static str, &'static str)]),
+    /// Sends the parameter as a one-element JSON list
+    List(&'static str),
+}
+
+pub struct V1Route {
+    pub method: Method,
+    pub path: &'static str,
+    pub legacy_method: Method,
+    pub legacy_path: &'static str,
+    pub inject: Inject,
+    /// Status sent instead of the legacy 200 on success
+    pub status: StatusCode,
+}
+
+const fn alias(
+    method: Method,
+    path: &'static str,
+    legacy_method: Method,
+    legacy_path: &'static str,
+) -> V1Route {
+    V1Route {
+        method,
+        path,
+        legacy_method,
+        legacy_path,
+        inject: Inject::None,
+        status: StatusCode::OK,
+    }
+}
+
+// Builders take `self` by value as struct update syntax can't drop a `Method` in a static.
+impl V1Route {
+    const fn injecting(mut self, inject: Inject) -> Self {
+        self.inject = inject;
+        self
+    }
+
+    const fn responding(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+const fn get(path: &'static str, legacy_path: &'static str) -> V1Route {
+    alias(Method::GET, path, Method::GET, legacy_path)
+}
+
+const fn create(path: &'static str, legacy_path: &'static str) -> V1Route {
+    alias(Method::POST, path, Method::POST, legacy_path).responding(StatusCode::CREATED)
+}
+
+const fn remove(path: &'static str, legacy_path: &'static str) -> V1Route {
+    alias(Method::DELETE, path, Method::DELETE, legacy_path).responding(StatusCode::NO_CONTENT)
+}
+
+pub static ROUTES: &[V1Route] = &[
+    //Auth
+    alias(Method::POST, "/auth/login", Method::POST, "/login"),
+    alias(Method::POST, "/auth/login/2fa", Method::POST, "/login/2fa"),
+    alias(
+        Method::POST,
+        "/auth/student/login",
+        Method::POST,
+        "/student/login",
+    ),
+    alias(Method::POST, "/auth/logout", Method::POST, "/logout"),
+    alias(
+        Method::POST,
+        "/auth/password_reset",
+        Method::POST,
+        "/reset_password",
+    ),
+    get("/auth/oidc/login", "/oidc/login"),
+    get("/auth/oidc/callback", "/oidc/callback"),
+    //Me
+    get("/me", "/me"),
+    alias(Method::PUT, "/me/password", Method::PATCH, "/me/password"),
+    get("/me/schedule", "/me/schedule"),
+    get("/me/changes", "/me/changes"),
+    get("/me/notifications", "/me/notifications"),
+    alias(
+        Method::PATCH,
+        "/me/notifications",
+        Method::PATCH,
+        "/me/notifications",
+    ),
+    alias(Method::POST, "/me/2fa/setup", Method::POST, "/me/2fa/setup"),
+    alias(
+        Method::POST,
+        "/me/2fa/enable",
+        Method::POST,
+        "/me/2fa/enable",
+    ),
+    alias(
+        Method::POST,
+        "/me/2fa/disable",
+        Method::POST,
+        "/me/2fa/disable",
+    ),
+    alias(
+        Method::POST,
+        "/me/2fa/recovery_codes",
+        Method::POST,
+        "/me/2fa/recovery_codes",
+    ),
+    get("/me/sessions", "/me/sessions"),
+    remove("/me/sessions/{session_id}", "/me/sessions/{session_id}"),
+    get("/student/me", "/student/me"),
+    get("/student/me/schedule", "/student/me/schedule"),
+    get("/student/me/changes", "/student/me/changes"),
+    alias(
+        Method::PUT,
+        "/student/me/password",
+        Method::PATCH,
+        "/student/me/password",
+    ),
+    //Groups
+    get("/groups", "/get_groups"),
+    create("/groups", "/add_group"),
+    get("/groups/{group_id}", "/get_group_by_id/{group_id}"),
+    get("/groups/{group_id}/bundle", "/get_group_bundle/{group_id}"),
+    alias(
+        Method::PATCH,
+        "/groups/{group_id}",
+        Method::PATCH,
+        "/edit_group",
+    )
+    .injecting(Inject::Fields(&[("group_id", "id")])),
+    remove("/groups/{group_id}", "/delete_group/{group_id}"),
+    get(
+        "/groups/{group_id}/subjects",
+        "/get_subjects_by_group_id/{group_id}",
+    ),
+    get("/groups/{group_id}/schedule", "/get_schedule/{group_id}"),
+    create("/groups/{group_id}/schedule", "/add_pairs")
+        .injecting(Inject::Fields(&[("group_id", "group_id")])),
+    alias(
+        Method::PATCH,
+        "/groups/{group_id}/schedule",
+        Method::PATCH,
+        "/edit_pairs",
+    )
+    .injecting(Inject::Fields(&[("group_id", "group_id")])),
+    remove(
+        "/groups/{group_id}/schedule/{weekday}",
+        "/delete_day/{group_id}/{weekday}",
+    ),
+    get(
+        "/groups/{group_id}/changes",
+        "/get_schedule_changes/{group_id}",
+    ),
+    get(
+        "/groups/{group_id}/teacher_links",
+        "/get_teacher_links/{group_id}",
+    ),
+    create("/groups/{group_id}/teacher_links", "/add_teacher_link")
+        .injecting(Inject::Fields(&[("group_id", "group_id")])),
+    remove(
+        "/groups/{group_id}/teacher_links/{teacher_id}/{subject_id}",
+        "/delete_teacher_link",
+    )
+    .injecting(Inject::Fields(&[
+        ("group_id", "group_id"),
+        ("teacher_id", "teacher_id"),
+        ("subject_id", "subject_id"),
+    ])),
+    get("/groups/{group_id}/students", "/get_students/{group_id}"),
+    create("/groups/{group_id}/students", "/add_students")
+        .injecting(Inject::Fields(&[("group_id", "group_id")])),
+    alias(
+        Method::POST,
+        "/groups/{group_id}/notifications",
+        Method::POST,
+        "/send_notifications_to_group",
+    )
+    .injecting(Inject::Fields(&[("group_id", "group_id")])),
+    //Subjects
+    create("/subjects", "/add_subject"),
+    alias(
+        Method::PATCH,
+        "/subjects/{subject_id}",
+        Method::PATCH,
+        "/edit_subject",
+    )
+    .injecting(Inject::Fields(&[("subject_id", "id")])),
+    remove("/subjects/{subject_id}", "/delete_subject/{subject_id}"),
+    //Schedule
+    remove("/pairs/{pair_id}", "/delete_pair/{pair_id}"),
+    create("/changes", "/add_schedule_changes"),
+    alias(
+        Method::PATCH,
+        "/changes/{schedule_id}",
+        Method::PATCH,
+        "/edit_schedule_changes",
+    )
+    .injecting(Inject::Fields(&[("schedule_id", "schedule_id")])),
+    remove("/changes/{schedule_id}", "/delete_schedule_changes")
+        .injecting(Inject::List("schedule_id")),
+    //Teachers
+    get("/teachers", "/get_teachers"),
+    create("/teachers", "/add_teacher"),
+    get("/teachers/{teacher_id}", "/get_teacher_by_id/{teacher_id}"),
+    remove("/teachers/{teacher_id}", "/delete_teacher/{teacher_id}"),
+    alias(
+        Method::PUT,
+        "/teachers/{teacher_id}/password",
+        Method::PATCH,
+        "/update_teacher_password",
+    )
+    .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    alias(
+        Method::PUT,
+        "/teachers/{teacher_id}/login",
+        Method::PATCH,
+        "/update_teacher_login",
+    )
+    .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    alias(
+        Method::PUT,
+        "/teachers/{teacher_id}/full_name",
+        Method::PATCH,
+        "/update_teacher_fullname",
+    )
+    .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    alias(
+        Method::PUT,
+        "/teachers/{teacher_id}/email",
+        Method::PATCH,
+        "/update_teacher_email",
+    )
+    .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    alias(
+        Method::PUT,
+        "/teachers/{teacher_id}/role",
+        Method::PATCH,
+        "/update_teacher_role",
+    )
+    .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    create("/teachers/{teacher_id}/reset_code", "/issue_reset_code")
+        .injecting(Inject::Fields(&[("teacher_id", "id")])),
+    get(
+        "/teachers/{teacher_id}/sessions",
+        "/get_teacher_sessions/{teacher_id}",
+    ),
+    remove(
+        "/teachers/{teacher_id}/sessions",
+        "/revoke_teacher_sessions/{teacher_id}",
+    ),
+    get(
+        "/teachers/{teacher_id}/group_grants",
+        "/get_group_grants/{teacher_id}",
+    ),
+    create("/teachers/{teacher_id}/group_grants", "/add_group_grants")
+        .injecting(Inject::Fields(&[("teacher_id", "teacher_id")])),
+    remove(
+        "/teachers/{teacher_id}/group_grants/{group_id}",
+        "/delete_group_grant/{teacher_id}/{group_id}",
+    ),
+    alias(
+        Method::POST,
+        "/notifications/teachers",
+        Method::POST,
+        "/send_notifications_to_teachers",
+    ),
+    get("/roles", "/get_roles"),
+    get("/events", "/live_events"),
+    get("/sync", "/sync"),
+    //Students
+    alias(
+        Method::PATCH,
+        "/students/{student_id}",
+        Method::PATCH,
+        "/edit_student",
+    )
+    .injecting(Inject::Fields(&[("student_id", "id")])),
+    remove("/students/{student_id}", "/delete_student/{student_id}"),
+    create("/students/{student_id}/password", "/issue_student_password")
+        .injecting(Inject::Fields(&[("student_id", "id")])),
+    //Administration
+    get("/audit_log", "/get_audit_log"),
+    get("/api_keys", "/get_api_keys"),
+    create("/api_keys", "/add_api_key"),
+    remove("/api_keys/{api_key_id}", "/delete_api_key/{api_key_id}"),
+    create("/impersonations", "/impersonate"),
+];
+
+fn path_value(value: &str) -> Value {
+    value
+        .parse::<i64>()
+        .map(Value::from)
+        .unwrap_or_else(|_| Value::from(value))
+}
+
+async fn inject_body(
+    body: Body,
+    inject: &Inject,
+    params: &[(String, String)],
+) -> Result<Vec<u8>, AppError> {
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| path_value(value))
+            .unwrap_or(Value::Null)
+    };
+
+    let value = match inject {
+        Inject::None => return Err(AppError::Internal),
+        Inject::List(name) => Value::Array(vec![param(name)]),
+        Inject::Fields(fields) => {
+            let bytes = to_bytes(body, MAX_BODY_SIZE)
+                .await
+                .map_err(|_| AppError::BadRequest(String::from("Request body is too large")))?;
+            let mut object: Map<String, Value> = if bytes.is_empty() {
+                Map::new()
+            } else {
+                serde_json::from_slice(&bytes).map_err(|_| {
+                    AppError::BadRequest(String::from("Request body must be a JSON object"))
+                })?
+            };
+            for (name, field) in fields.iter() {
+                object.insert(field.to_string(), param(name));
+            }
+            Value::Object(object)
+        }
+    };
+
+    serde_json::to_vec(&value).map_err(|_| AppError::Internal)
+}
+
+/// Fills the path parameters into the legacy path and keeps the query string.
+fn legacy_uri(route: &V1Route, params: &[(String, String)], query: Option<&str>) -> String {
+    let mut path = route.legacy_path.to_string();
+    for (name, value) in params {
+        path = path.replace(&format!("{{{}}}", name), value);
+    }
+    match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    }
+}
+
+async fn rewrite(
+    req: Request<Body>,
+    route: &V1Route,
+    params: &[(String, String)],
+) -> Result<Request<Body>, AppError> {
+    let (mut parts, body) = req.into_parts();
+
+    // The legacy router adds its path parameters to any already in the extensions, so it
+    // gets none of ours but the peer address.
+    let connect_info = parts.extensions.remove::<ConnectInfo<SocketAddr>>();
+    parts.extensions = Extensions::new();
+    if let Some(connect_info) = connect_info {
+        parts.extensions.insert(connect_info);
+    }
+    parts.uri = legacy_uri(route, params, parts.uri.query())
+        .parse()
+        .map_err(|_| AppError::NotFound)?;
+    parts.method = route.legacy_method.clone();
+
+    let body = match route.inject {
+        Inject::None => body,
+        _ => {
+            let bytes = inject_body(body, &route.inject, params).await?;
+            parts
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            parts.headers.remove(CONTENT_LENGTH);
+            Body::from(bytes)
+        }
+    };
+
+    Ok(Request::from_parts(parts, body))
+}
+
+/// Runs a `/api/v1` request through the legacy route it maps to.
+async fn forward(
+    legacy: Router,
+    route: &'static V1Route,
+    params: Vec<(String, String)>,
+    req: Request<Body>,
+) -> Response {
+    let req = match rewrite(req, route, &params).await {
+        Ok(req) => req,
+        Err(e) => return e.into_response(),
+    };
+    let Ok(mut response) = legacy.oneshot(req).await;
+    if response.status() == StatusCode::OK && route.status != StatusCode::OK {
+        *response.status_mut() = route.status;
+        if route.status == StatusCode::NO_CONTENT {
+            *response.body_mut() = Body::empty();
+            response.headers_mut().remove(CONTENT_TYPE);
+            response.headers_mut().remove(CONTENT_LENGTH);
+        }
+    }
+
+    response
+}
+
+fn method_filter(method: &Method) -> MethodFilter {
+    match *method {
+        Method::GET => MethodFilter::GET,
+        Method::POST => MethodFilter::POST,
+        Method::PUT => MethodFilter::PUT,
+        Method::PATCH => MethodFilter::PATCH,
+        _ => MethodFilter::DELETE,
+    }
+}
+
+/// Routes of `/api/v1`, to be nested under [`PREFIX`]. Requests are handed to `legacy`,
+/// the router of the legacy paths, so handlers and permission checks are shared.
+pub fn router(legacy: Router) -> Router {
+    let mut router = Router::new();
+    for route in ROUTES {
+        let legacy = legacy.clone();
+        router = router.route(
+            route.path,
+            on(
+                method_filter(&route.method),
+                move |Path(params): Path<Vec<(String, String)>>, req: Request<Body>| {
+                    forward(legacy, route, params, req)
+                },
+            ),
+        );
+    }
+
+    router.fallback(|| async { AppError::NotFound })
+}
+
+/// Marks responses of the legacy paths as deprecated in favour of `/api/v1`.
+pub async fn deprecate_legacy(req: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    headers.insert(
+        LINK,
+        HeaderValue::from_static("</api/v1>; rel=\"successor-version\""),
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json,
+        http::header::ALLOW,
+        routing::{delete, patch, post},
+    };
+
+    fn route(legacy_path: &str) -> &'static V1Route {
+        ROUTES
+            .iter()
+            .find(|route| route.legacy_path == legacy_path)
+            .unwrap()
+    }
+
+    fn params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+        params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// `/api/v1` over legacy handlers that echo what they receive.
+    fn app() -> Router {
+        let legacy =
+            Router::new()
+                .route(
+                    "/add_group",
+                    post(|Json(body): Json<Value>| async { Json(body) }),
+                )
+                .route(
+                    "/edit_group",
+                    patch(|Json(body): Json<Value>| async { Json(body) }),
+                )
+                .route(
+                    "/delete_group/{group_id}",
+                    delete(|Path(group_id): Path<i64>| async move { Json(group_id) }),
+                )
+                .route(
+                    "/logout",
+                    post(|ConnectInfo(addr): ConnectInfo<SocketAddr>| async move {
+                        Json(addr.to_string())
+                    }),
+                )
+                .route(
+                    "/delete_schedule_changes",
+                    delete(|Json(ids): Json<Vec<i64>>| async move {
+                        if ids == [9] {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::BAD_REQUEST
+                        }
+                    }),
+                );
+        Router::new().nest(PREFIX, router(legacy))
+    }
+
+    async fn send(method: Method, uri: &str, body: &str) -> Response {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app().oneshot(req).await.unwrap()
+    }
+
+    async fn json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), MAX_BODY_SIZE).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn legacy_uri_fills_parameters_and_keeps_query() {
+        let route = route("/delete_group_grant/{teacher_id}/{group_id}");
+        let params = params(&[("teacher_id", "3"), ("group_id", "7")]);
+        assert_eq!(legacy_uri(route, &params, None), "/delete_group_grant/3/7");
+        assert_eq!(
+            legacy_uri(route, &params, Some("dry_run=1")),
+            "/delete_group_grant/3/7?dry_run=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn path_parameters_are_injected_into_the_body() {
+        let response = send(Method::PATCH, "/api/v1/groups/5", r#"{"name":"ИС-21"}"#).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await,
+            serde_json::json!({ "id": 5, "name": "ИС-21" })
+        );
+    }
+
+    #[tokio::test]
+    async fn list_parameter_is_sent_as_a_list() {
+        let response = send(Method::DELETE, "/api/v1/changes/9", "").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn peer_address_reaches_the_legacy_handler() {
+        let response = send(Method::POST, "/api/v1/auth/logout", "").await;
+        assert_eq!(json(response).await, "10.0.0.1:4000");
+    }
+
+    #[tokio::test]
+    async fn create_responds_with_created() {
+        let response = send(Method::POST, "/api/v1/groups", r#"{"name":"ИС-21"}"#).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(json(response).await, serde_json::json!({ "name": "ИС-21" }));
+    }
+
+    #[tokio::test]
+    async fn remove_responds_with_no_content() {
+        let response = send(Method::DELETE, "/api/v1/groups/5", "").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers().get(CONTENT_TYPE).is_none());
+        let body = to_bytes(response.into_body(), MAX_BODY_SIZE).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn wrong_method_lists_allowed_ones() {
+        let response = send(Method::PUT, "/api/v1/groups/5", "").await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let allow = response.headers()[ALLOW].to_str().unwrap();
+        for method in ["GET", "PATCH", "DELETE"] {
+            assert!(allow.contains(method), "{} is not in {}", method, allow);
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_path_is_not_found() {
+        let response = send(Method::GET, "/api/v1/nothing", "").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
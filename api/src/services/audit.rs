@@ -0,0 +1,107 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+
+use crate::{
+    config::AppState,
+    models::{ActorType, ApiKey, Session, Student, Teacher},
+    traits::AuditLog,
+};
+
+/// Who performs a request and under which request id, taken from the extensions
+/// the auth middlewares leave behind.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor_type: ActorType,
+    pub actor_id: Option<i64>,
+    pub actor_name: Option<String>,
+    pub impersonator_id: Option<i64>,
+    pub request_id: Option<String>,
+}
+
+pub struct AuditEvent {
+    pub action: &'static str,
+    pub entity: &'static str,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &'static str, entity: &'static str, entity_id: impl ToString) -> Self {
+        Self {
+            action,
+            entity,
+            entity_id: Some(entity_id.to_string()),
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn before(mut self, data: &impl Serialize) -> Self {
+        self.before = serde_json::to_value(data).ok();
+        self
+    }
+
+    pub fn after(mut self, data: &impl Serialize) -> Self {
+        self.after = serde_json::to_value(data).ok();
+        self
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let request_id = parts
+            .headers
+            .get("x-request-id")
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_string);
+
+        let (actor_type, actor_id, actor_name) =
+            if let Some(teacher) = parts.extensions.get::<Teacher>() {
+                (
+                    ActorType::Teacher,
+                    Some(teacher.id),
+                    Some(teacher.full_name.clone()),
+                )
+            } else if let Some(key) = parts.extensions.get::<ApiKey>() {
+                (ActorType::ApiKey, Some(key.id), Some(key.name.clone()))
+            } else if let Some(student) = parts.extensions.get::<Student>() {
+                (
+                    ActorType::Student,
+                    Some(student.id),
+                    Some(student.full_name.clone()),
+                )
+            } else {
+                (ActorType::Anonymous, None, None)
+            };
+
+        let impersonator_id = parts
+            .extensions
+            .get::<Session>()
+            .and_then(|s| s.impersonator_id);
+
+        Ok(Self {
+            actor_type,
+            actor_id,
+            actor_name,
+            impersonator_id,
+            request_id,
+        })
+    }
+}
+
+impl AuditContext {
+    /// Failing to write the audit trail is logged but never fails the request itself.
+    pub async fn record(&self, app_state: &AppState, event: AuditEvent) {
+        if let Err(e) = app_state.db.add_audit_entry(self, &event).await {
+            eprintln!(
+                "Failed to record audit entry {} {:?}: {}",
+                event.action, event.entity_id, e
+            );
+        }
+    }
+}
@@ -2,17 +2,20 @@ use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
 };
-use rand::rngs::OsRng;
+use rand::{Rng, rngs::OsRng};
+use sha2::{Digest, Sha256};
+
+pub const API_KEY_PREFIX: &str = "sl_";
+
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
 
 pub fn hash_password(password: &str) -> String {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
-    let password_hash = argon2
+    argon2
         .hash_password(password.as_bytes(), &salt)
         .unwrap()
-        .to_string();
-
-    password_hash
+        .to_string()
 }
 
 pub fn verify_password(password: &str, hash: &str) -> bool {
@@ -21,3 +24,26 @@ pub fn verify_password(password: &str, hash: &str) -> bool {
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok()
 }
+
+/// Random code without look-alike characters, meant to be read out or typed by hand.
+pub fn generate_code(length: usize) -> String {
+    let mut rng = OsRng;
+    (0..length)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Returns a new API key token; its first characters double as the public key prefix.
+pub fn generate_api_key() -> String {
+    let secret: String = OsRng
+        .sample_iter(rand::distributions::Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!("{}{}", API_KEY_PREFIX, secret)
+}
+
+/// API keys are long random strings, so a fast digest is enough and allows lookup by hash.
+pub fn hash_api_key(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
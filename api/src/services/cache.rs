@@ -0,0 +1,158 @@
+use std::future::Future;
+
+use axum::{
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{
+            CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        },
+    },
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{config::AppState, errors::AppError, services::listing::TOTAL_COUNT_HEADER};
+
+/// Longest time an entry is kept, entries also expire at midnight as past changes drop out.
+const MAX_TTL: i64 = 3600;
+
+/// A rendered group read as it is stored in Redis.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    last_modified: DateTime<Utc>,
+    total: Option<i64>,
+    body: String,
+}
+
+impl CachedResponse {
+    fn new(body: String, total: Option<i64>) -> Self {
+        Self {
+            etag: format!("\"{:x}\"", Sha256::digest(body.as_bytes())),
+            last_modified: Utc::now(),
+            total,
+            body,
+        }
+    }
+
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        // If-Modified-Since is ignored when If-None-Match is present, as RFC 9110 requires.
+        if let Some(value) = headers.get(IF_NONE_MATCH) {
+            return value.to_str().is_ok_and(|tags| {
+                tags.split(',')
+                    .map(|tag| tag.trim().trim_start_matches("W/"))
+                    .any(|tag| tag == "*" || tag == self.etag)
+            });
+        }
+        headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .is_some_and(|since| self.last_modified.timestamp() <= since.timestamp())
+    }
+
+    fn respond(self, headers: &HeaderMap) -> Response {
+        let not_modified = self.not_modified(headers);
+        let mut response = if not_modified {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let mut response = self.body.into_response();
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            if let Some(total) = self.total {
+                response
+                    .headers_mut()
+                    .insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+            }
+            response
+        };
+
+        let response_headers = response.headers_mut();
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            response_headers.insert(ETAG, etag);
+        }
+        let last_modified = self
+            .last_modified
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        if let Ok(last_modified) = HeaderValue::from_str(&last_modified) {
+            response_headers.insert(LAST_MODIFIED, last_modified);
+        }
+        response_headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        response
+    }
+}
+
+fn ttl() -> i64 {
+    let now = Local::now();
+    let midnight = (now.date_naive() + TimeDelta::days(1))
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest());
+
+    match midnight {
+        Some(midnight) => (midnight - now).num_seconds().clamp(1, MAX_TTL),
+        None => MAX_TTL,
+    }
+}
+
+/// Serves a read of one group's data from Redis, calling `render` on a miss. `render`
+/// returns the response items and the total for paged lists. A failing cache only logs,
+/// the read is then served from the database.
+pub async fn cached_group_read<T, F, Fut>(
+    app_state: &AppState,
+    group_id: i64,
+    field: &str,
+    headers: &HeaderMap,
+    render: F,
+) -> Result<Response, AppError>
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<i64>), AppError>>,
+{
+    let generation = match app_state.redis.get_group_cache(group_id, field).await {
+        Ok((generation, Some(entry))) => match serde_json::from_str::<CachedResponse>(&entry) {
+            Ok(cached) => return Ok(cached.respond(headers)),
+            Err(_) => Some(generation),
+        },
+        Ok((generation, None)) => Some(generation),
+        Err(e) => {
+            eprintln!("Failed to read cache of group {}: {}", group_id, e);
+            None
+        }
+    };
+
+    let (items, total) = render().await?;
+    let body = serde_json::to_string(&items).map_err(|_| AppError::Internal)?;
+    let cached = CachedResponse::new(body, total);
+
+    if let Some(generation) = generation
+        && let Ok(entry) = serde_json::to_string(&cached)
+        && let Err(e) = app_state
+            .redis
+            .set_group_cache(group_id, generation, field, &entry, ttl())
+            .await
+    {
+        eprintln!("Failed to cache group {}: {}", group_id, e);
+    }
+
+    Ok(cached.respond(headers))
+}
+
+/// Drops the cached reads of the groups, called after their schedule, changes, subjects
+/// or teacher links change. Failures are logged, entries then live until they expire.
+pub async fn invalidate_groups(app_state: &AppState, group_ids: impl IntoIterator<Item = i64>) {
+    let mut group_ids: Vec<i64> = group_ids.into_iter().collect();
+    group_ids.sort_unstable();
+    group_ids.dedup();
+
+    for group_id in group_ids {
+        if let Err(e) = app_state.redis.invalidate_group_cache(group_id).await {
+            eprintln!("Failed to invalidate cache of group {}: {}", group_id, e);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::errors::AppError;
+
+/// Rejects an edit made against an older version of the row. Edits that carry no
+/// version are applied over whatever is stored.
+pub fn check_version(stored: i32, sent: Option<i32>) -> Result<(), AppError> {
+    match sent {
+        Some(version) if version != stored => Err(AppError::VersionConflict),
+        _ => Ok(()),
+    }
+}
+
+/// Maps the result of a version-guarded update. The row was read just before, so an
+/// update matching nothing lost the race to another edit.
+pub fn guarded<T>(result: Result<T, sqlx::Error>, versioned: bool) -> Result<T, AppError> {
+    match result {
+        Err(sqlx::Error::RowNotFound) if versioned => Err(AppError::VersionConflict),
+        result => result.map_err(AppError::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_edits_always_apply() {
+        assert!(check_version(3, None).is_ok());
+    }
+
+    #[test]
+    fn matching_version_applies() {
+        assert!(check_version(3, Some(3)).is_ok());
+    }
+
+    #[test]
+    fn stale_version_conflicts() {
+        assert!(matches!(
+            check_version(3, Some(2)),
+            Err(AppError::VersionConflict)
+        ));
+    }
+
+    #[test]
+    fn missed_versioned_update_conflicts() {
+        let result: Result<(), _> = guarded(Err(sqlx::Error::RowNotFound), true);
+        assert!(matches!(result, Err(AppError::VersionConflict)));
+    }
+
+    #[test]
+    fn missed_unversioned_update_is_not_a_conflict() {
+        let result: Result<(), _> = guarded(Err(sqlx::Error::RowNotFound), false);
+        assert!(matches!(result, Err(AppError::Database(_))));
+    }
+
+    #[test]
+    fn successful_update_passes_through() {
+        assert!(matches!(guarded(Ok(7), true), Ok(7)));
+    }
+}
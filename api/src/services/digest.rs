@@ -0,0 +1,85 @@
+use chrono::{Days, Local, NaiveDate, TimeDelta};
+use std::time::Duration;
+
+use crate::{
+    config::AppState,
+    errors::AppError,
+    services::{email::render_daily_digest, schedule::teacher_day},
+    traits::{NotificationPreferences, Teachers},
+};
+
+pub fn spawn_daily_digest(app_state: AppState, hour: u32) {
+    if app_state.mailer.is_none() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(until_next_run(hour)).await;
+
+            let date = Local::now().date_naive() + Days::new(1);
+            if let Err(e) = send_daily_digest(&app_state, date).await {
+                eprintln!("Failed to send daily digest: {}", e);
+            }
+        }
+    });
+}
+
+fn until_next_run(hour: u32) -> Duration {
+    let now = Local::now().naive_local();
+    let mut next = now.date().and_hms_opt(hour, 0, 0).unwrap_or(now);
+    if next <= now {
+        next += TimeDelta::days(1);
+    }
+
+    (next - now).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+pub async fn send_daily_digest(app_state: &AppState, date: NaiveDate) -> Result<(), AppError> {
+    let Some(mailer) = app_state.mailer.clone() else {
+        return Ok(());
+    };
+
+    //Several API instances may run the same loop, only one of them sends the digest
+    if !app_state
+        .redis
+        .acquire_lock(&format!("digest:{}", date), 86400)
+        .await?
+    {
+        return Ok(());
+    }
+
+    for teacher in app_state.db.get_teachers_with_email().await? {
+        if !app_state
+            .db
+            .get_notification_preference(teacher.id)
+            .await?
+            .email_digest
+        {
+            continue;
+        }
+
+        let lines = teacher_day(app_state, teacher.id, date).await?;
+        if lines.is_empty() {
+            continue;
+        }
+
+        let Some(email) = teacher.email.as_deref() else {
+            continue;
+        };
+        let rendered = render_daily_digest(&teacher.full_name, date, &lines);
+        if let Err(e) = mailer
+            .send(
+                email,
+                &format!("Расписание на {}", date.format("%d.%m.%Y")),
+                rendered.text,
+                rendered.html,
+            )
+            .await
+        {
+            eprintln!("Failed to send daily digest to {}: {}", email, e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use chrono::NaiveDate;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+};
+use std::collections::HashMap;
+
+use crate::{
+    config::{AppState, SmtpConfig},
+    models::ScheduleChange,
+    services::schedule::{DayEntry, change_entry},
+    traits::{NotificationPreferences, Schedules, Teachers},
+};
+
+const CHANGE_NOTICE_HTML: &str = include_str!("../templates/email/change_notice.html");
+const CHANGE_NOTICE_TEXT: &str = include_str!("../templates/email/change_notice.txt");
+const DAILY_DIGEST_HTML: &str = include_str!("../templates/email/daily_digest.html");
+const DAILY_DIGEST_TEXT: &str = include_str!("../templates/email/daily_digest.txt");
+
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn init(config: &SmtpConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = if config.tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        };
+        builder = builder.port(config.port);
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: config.from.parse()?,
+        })
+    }
+
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        text: String,
+        html: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(text, html))?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+pub struct RenderedEmail {
+    pub text: String,
+    pub html: String,
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render(
+    text_template: &str,
+    html_template: &str,
+    name: &str,
+    date: Option<NaiveDate>,
+    lines: &[DayEntry],
+) -> RenderedEmail {
+    let date = date
+        .map(|d| d.format("%d.%m.%Y").to_string())
+        .unwrap_or_default();
+
+    let text_rows = lines
+        .iter()
+        .map(|line| {
+            let note = line
+                .note
+                .as_ref()
+                .map(|n| format!(" — {}", n))
+                .unwrap_or_default();
+            format!(
+                "{}, пара {} ({}–{}): {}, группа {}, каб. {}{}",
+                line.date.format("%d.%m.%Y"),
+                line.pair_number,
+                line.start_time.format("%H:%M"),
+                line.end_time.format("%H:%M"),
+                line.subject,
+                line.group,
+                line.cabinet,
+                note
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let html_rows = lines
+        .iter()
+        .map(|line| {
+            format!(
+                "        <tr><td>{}</td><td>{}</td><td>{}–{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                line.date.format("%d.%m.%Y"),
+                line.pair_number,
+                line.start_time.format("%H:%M"),
+                line.end_time.format("%H:%M"),
+                escape_html(&line.group),
+                escape_html(&line.subject),
+                escape_html(&line.cabinet),
+                escape_html(line.note.as_deref().unwrap_or(""))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    RenderedEmail {
+        text: text_template
+            .replace("{{name}}", name)
+            .replace("{{date}}", &date)
+            .replace("{{rows}}", &text_rows),
+        html: html_template
+            .replace("{{name}}", &escape_html(name))
+            .replace("{{date}}", &date)
+            .replace("{{rows}}", &html_rows),
+    }
+}
+
+pub fn render_change_notice(name: &str, lines: &[DayEntry]) -> RenderedEmail {
+    render(CHANGE_NOTICE_TEXT, CHANGE_NOTICE_HTML, name, None, lines)
+}
+
+pub fn render_daily_digest(name: &str, date: NaiveDate, lines: &[DayEntry]) -> RenderedEmail {
+    render(
+        DAILY_DIGEST_TEXT,
+        DAILY_DIGEST_HTML,
+        name,
+        Some(date),
+        lines,
+    )
+}
+
+pub async fn send_change_notices(
+    app_state: AppState,
+    changes: Vec<ScheduleChange>,
+    reverted: bool,
+) {
+    let Some(mailer) = app_state.mailer.clone() else {
+        return;
+    };
+
+    let mut lines: HashMap<i64, Vec<DayEntry>> = HashMap::new();
+
+    for change in &changes {
+        let mut teacher_ids = vec![change.new_teacher_id];
+        if let Ok(pair) = app_state.db.get_pair(change.schedule_id).await
+            && pair.teacher_id != change.new_teacher_id
+        {
+            teacher_ids.push(pair.teacher_id);
+        }
+
+        for teacher_id in teacher_ids {
+            let mut line = change_entry(&app_state, change).await;
+            if reverted {
+                line.note = Some("Изменение отменено".to_string());
+            }
+            lines.entry(teacher_id).or_default().push(line);
+        }
+    }
+
+    for (teacher_id, lines) in lines {
+        let Ok(teacher) = app_state.db.get_teacher_by_id(teacher_id).await else {
+            continue;
+        };
+        let Some(email) = teacher.email.filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        match app_state.db.get_notification_preference(teacher_id).await {
+            Ok(preference) if preference.email_changes => {}
+            _ => continue,
+        }
+
+        let rendered = render_change_notice(&teacher.full_name, &lines);
+        if let Err(e) = mailer
+            .send(
+                &email,
+                "Изменения в расписании",
+                rendered.text,
+                rendered.html,
+            )
+            .await
+        {
+            eprintln!("Failed to send change notice to {}: {}", email, e);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{
+        HeaderMap, HeaderValue,
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+use crate::errors::AppError;
+
+/// Largest JSON body transcoded, anything bigger is a bug rather than a timetable.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Encoding {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "application/*" | "*/*" => Some(Encoding::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Encoding::MessagePack)
+            }
+            "application/cbor" => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Picks the accepted encoding with the highest quality, the earliest one on a tie.
+    fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(ACCEPT).and_then(|h| h.to_str().ok()) else {
+            return Encoding::Json;
+        };
+
+        let mut best = None;
+        for item in accept.split(',') {
+            let mut params = item.split(';');
+            let media_type = params
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let quality = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if let Some(encoding) = Encoding::from_media_type(&media_type)
+                && quality > 0.0
+                && best.is_none_or(|(best_quality, _)| quality > best_quality)
+            {
+                best = Some((quality, encoding));
+            }
+        }
+
+        best.map_or(Encoding::Json, |(_, encoding)| encoding)
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::MessagePack => "application/msgpack",
+            Encoding::Cbor => "application/cbor",
+        }
+    }
+
+    /// Appended inside the quotes of an ETag, a representation needs its own tag.
+    fn etag_suffix(self) -> &'static str {
+        match self {
+            Encoding::Json => "",
+            Encoding::MessagePack => "-msgpack",
+            Encoding::Cbor => "-cbor",
+        }
+    }
+
+    fn encode(self, value: &Value) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).ok(),
+            Encoding::MessagePack => rmp_serde::to_vec_named(value).ok(),
+            Encoding::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf).ok().map(|_| buf)
+            }
+        }
+    }
+}
+
+/// Lets the handler compare If-None-Match against the ETag of its JSON body.
+fn strip_etag_suffix(headers: &mut HeaderMap, suffix: &str) {
+    let Some(tags) = headers.get(IF_NONE_MATCH).and_then(|h| h.to_str().ok()) else {
+        return;
+    };
+    let quoted_suffix = format!("{}\"", suffix);
+    let tags = tags
+        .split(',')
+        .map(|tag| match tag.trim().strip_suffix(&quoted_suffix) {
+            Some(tag) => format!("{}\"", tag),
+            None => tag.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Ok(tags) = HeaderValue::from_str(&tags) {
+        headers.insert(IF_NONE_MATCH, tags);
+    }
+}
+
+fn add_etag_suffix(headers: &mut HeaderMap, suffix: &str) {
+    let Some(etag) = headers.get(ETAG).and_then(|h| h.to_str().ok()) else {
+        return;
+    };
+    let etag = match etag.strip_suffix('"') {
+        Some(tag) => format!("{}{}\"", tag, suffix),
+        None => return,
+    };
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+}
+
+/// Serves JSON responses as MessagePack or CBOR when the client asks for it in `Accept`.
+/// Handlers keep producing JSON, bodies are transcoded on the way out.
+pub async fn negotiate_encoding(mut req: Request<Body>, next: Next) -> Response {
+    let encoding = Encoding::negotiate(req.headers());
+    if encoding != Encoding::Json {
+        strip_etag_suffix(req.headers_mut(), encoding.etag_suffix());
+    }
+
+    let mut response = next.run(req).await;
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("accept"));
+    if encoding == Encoding::Json {
+        return response;
+    }
+    add_etag_suffix(response.headers_mut(), encoding.etag_suffix());
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY_SIZE).await else {
+        return AppError::Internal.into_response();
+    };
+    let Some(encoded) = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|value| encoding.encode(&value))
+    else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts.headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+    );
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: Option<&'static str>) -> Encoding {
+        let mut headers = HeaderMap::new();
+        if let Some(accept) = accept {
+            headers.insert(ACCEPT, HeaderValue::from_static(accept));
+        }
+        Encoding::negotiate(&headers)
+    }
+
+    #[test]
+    fn json_without_accept() {
+        assert_eq!(negotiate(None), Encoding::Json);
+        assert_eq!(negotiate(Some("*/*")), Encoding::Json);
+        assert_eq!(negotiate(Some("text/html")), Encoding::Json);
+    }
+
+    #[test]
+    fn known_media_types_are_picked() {
+        assert_eq!(
+            negotiate(Some("application/msgpack")),
+            Encoding::MessagePack
+        );
+        assert_eq!(
+            negotiate(Some("Application/X-Msgpack")),
+            Encoding::MessagePack
+        );
+        assert_eq!(negotiate(Some("application/cbor")), Encoding::Cbor);
+    }
+
+    #[test]
+    fn highest_quality_wins() {
+        assert_eq!(
+            negotiate(Some("application/json;q=0.5, application/cbor")),
+            Encoding::Cbor
+        );
+        assert_eq!(
+            negotiate(Some("application/cbor; q=0.2, application/msgpack; q=0.8")),
+            Encoding::MessagePack
+        );
+    }
+
+    #[test]
+    fn earliest_wins_a_tie() {
+        assert_eq!(
+            negotiate(Some("application/cbor, application/msgpack")),
+            Encoding::Cbor
+        );
+    }
+
+    #[test]
+    fn zero_quality_is_refused() {
+        assert_eq!(negotiate(Some("application/cbor;q=0")), Encoding::Json);
+    }
+}
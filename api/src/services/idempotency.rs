@@ -0,0 +1,256 @@
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode, Uri, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::AppState,
+    errors::AppError,
+    models::{ApiKey, Session},
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+const MAX_KEY_LENGTH: usize = 255;
+/// How long a finished request is replayed for its key.
+const RESPONSE_TTL: u64 = 86400;
+/// How long a key stays locked when the request never finishes.
+const LOCK_TTL: u64 = 60;
+
+/// A finished request as it is stored in Redis.
+#[derive(Serialize, Deserialize)]
+struct StoredResponse {
+    fingerprint: String,
+    status: u16,
+    content_type: Option<String>,
+    body: String,
+}
+
+impl StoredResponse {
+    fn replay(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = (status, self.body).into_response();
+        let headers = response.headers_mut();
+        headers.remove(CONTENT_TYPE);
+        if let Some(content_type) = self
+            .content_type
+            .and_then(|c| HeaderValue::from_str(&c).ok())
+        {
+            headers.insert(CONTENT_TYPE, content_type);
+        }
+        headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+
+        response
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Keys are kept per account, two accounts never see each other's responses.
+fn actor(req: &Request<Body>) -> Option<String> {
+    if let Some(session) = req.extensions().get::<Session>() {
+        Some(format!("{}:{}", session.kind.as_str(), session.user_id))
+    } else {
+        req.extensions()
+            .get::<ApiKey>()
+            .map(|key| format!("api_key:{}", key.id))
+    }
+}
+
+/// Covers the query string too, requests differing only there are different requests.
+fn fingerprint(method: &Method, uri: &Uri, body: &Bytes) -> String {
+    let target = uri.path_and_query().map_or(uri.path(), |p| p.as_str());
+    let mut data = format!("{} {}\n", method, target).into_bytes();
+    data.extend_from_slice(body);
+    sha256(&data)
+}
+
+/// Replays the response stored for the key, if the request matches the one it was
+/// stored for.
+async fn stored_response(
+    app_state: &AppState,
+    response_key: &str,
+    fingerprint: &str,
+) -> Result<Option<Response>, AppError> {
+    let Some(stored) = app_state.redis.get_value(response_key).await? else {
+        return Ok(None);
+    };
+    let stored: StoredResponse = serde_json::from_str(&stored).map_err(|_| AppError::Internal)?;
+    if stored.fingerprint != fingerprint {
+        return Err(AppError::IdempotencyKeyReused);
+    }
+    Ok(Some(stored.replay()))
+}
+
+/// Replays the stored response of a POST retried with the same `Idempotency-Key`, so a
+/// request repeated after a lost response is not applied twice. Reusing a key for
+/// another request is rejected, as is a retry while the first attempt still runs.
+/// Only successful responses are stored, a failed request can be retried with its key.
+pub async fn idempotency(
+    State(app_state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    if req.method() != Method::POST {
+        return Ok(next.run(req).await);
+    }
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| {
+            AppError::BadRequest(String::from(
+                "Idempotency-Key must be 1 to 255 visible ASCII characters",
+            ))
+        })?;
+    let Some(actor) = actor(&req) else {
+        return Ok(next.run(req).await);
+    };
+    let key_hash = sha256(key.as_bytes());
+    let response_key = format!("idempotency:{}:{}", actor, key_hash);
+    let lock_key = format!("idempotency_lock:{}:{}", actor, key_hash);
+
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AppError::BadRequest(String::from("Request body is too large")))?;
+    let fingerprint = fingerprint(&parts.method, &parts.uri, &bytes);
+
+    if let Some(response) = stored_response(&app_state, &response_key, &fingerprint).await? {
+        return Ok(response);
+    }
+    if !app_state.redis.acquire_lock(&lock_key, LOCK_TTL).await? {
+        return Err(AppError::RequestInProgress);
+    }
+    // The first attempt may have finished between the lookup and the lock.
+    match stored_response(&app_state, &response_key, &fingerprint).await {
+        Ok(None) => {}
+        Ok(Some(response)) => {
+            release(&app_state, lock_key).await;
+            return Ok(response);
+        }
+        Err(e) => {
+            release(&app_state, lock_key).await;
+            return Err(e);
+        }
+    }
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    let response = if response.status().is_success() {
+        store(&app_state, &response_key, fingerprint, response).await
+    } else {
+        response
+    };
+
+    release(&app_state, lock_key).await;
+
+    Ok(response)
+}
+
+async fn release(app_state: &AppState, lock_key: String) {
+    if let Err(e) = app_state.redis.delete_keys(&[lock_key]).await {
+        eprintln!("Failed to release idempotency lock: {}", e);
+    }
+}
+
+/// Stores a successful response and hands it on. A failing store only logs, the
+/// request itself has already been applied.
+async fn store(
+    app_state: &AppState,
+    response_key: &str,
+    fingerprint: String,
+    response: Response,
+) -> Response {
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read response for idempotency key: {}", e);
+            return AppError::Internal.into_response();
+        }
+    };
+
+    if let Ok(body) = String::from_utf8(bytes.to_vec()) {
+        let stored = StoredResponse {
+            fingerprint,
+            status: parts.status.as_u16(),
+            content_type: parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|c| c.to_str().ok())
+                .map(String::from),
+            body,
+        };
+        if let Ok(stored) = serde_json::to_string(&stored)
+            && let Err(e) = app_state
+                .redis
+                .set_value(response_key, &stored, RESPONSE_TTL)
+                .await
+        {
+            eprintln!("Failed to store response for idempotency key: {}", e);
+        }
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(method: Method, uri: &'static str, body: &'static str) -> String {
+        fingerprint(
+            &method,
+            &Uri::from_static(uri),
+            &Bytes::from_static(body.as_bytes()),
+        )
+    }
+
+    #[test]
+    fn same_request_has_same_fingerprint() {
+        assert_eq!(
+            print(Method::POST, "/add_group?x=1", "{}"),
+            print(Method::POST, "/add_group?x=1", "{}")
+        );
+    }
+
+    #[test]
+    fn fingerprint_covers_method_target_and_body() {
+        let base = print(Method::POST, "/add_group", "{}");
+        assert_ne!(base, print(Method::PUT, "/add_group", "{}"));
+        assert_ne!(base, print(Method::POST, "/add_groups", "{}"));
+        assert_ne!(base, print(Method::POST, "/add_group?dry_run=1", "{}"));
+        assert_ne!(base, print(Method::POST, "/add_group", "{\"name\":\"a\"}"));
+    }
+
+    #[tokio::test]
+    async fn replay_restores_the_stored_response() {
+        let stored = StoredResponse {
+            fingerprint: String::new(),
+            status: 201,
+            content_type: Some(String::from("application/json")),
+            body: String::from("{\"id\":1}"),
+        };
+        let response = stored.replay();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[REPLAYED_HEADER], "true");
+        let body = to_bytes(response.into_body(), MAX_BODY_SIZE).await.unwrap();
+        assert_eq!(&body[..], b"{\"id\":1}");
+    }
+}
@@ -0,0 +1,262 @@
+use axum::{
+    Json,
+    extract::{FromRequestParts, Query},
+    http::{HeaderValue, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{FromRow, MySql, MySqlPool, QueryBuilder, mysql::MySqlRow};
+
+use crate::{
+    errors::{AppError, FieldError},
+    models::{ChangeFilter, GroupFilter, PageQuery, SubjectFilter, TeacherFilter},
+};
+
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+const MAX_LIMIT: u32 = 1000;
+
+/// Filters of one list endpoint and the fields it can be sorted by.
+pub trait Listable {
+    /// Field names accepted in `sort`, mapped to their SQL columns
+    const SORT_FIELDS: &'static [(&'static str, &'static str)];
+    /// Order used when `sort` is missing
+    const DEFAULT_SORT: &'static str;
+    /// Unique column appended to every order so pages never overlap
+    const KEY: &'static str;
+
+    /// Appends ` AND ...` conditions to a query ending in a `WHERE` clause.
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, MySql>);
+}
+
+/// Shared extractor for list endpoints: paging and sorting plus the filters `F`.
+pub struct ListQuery<F> {
+    pub filter: F,
+    pub limit: Option<u32>,
+    pub offset: u32,
+    /// SQL columns with a descending flag
+    pub sort: Vec<(&'static str, bool)>,
+}
+
+/// One page of a list, sent as a JSON array with the total in `X-Total-Count`.
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+}
+
+/// Escapes `%`, `_` and `\` so user input matches literally in `LIKE`.
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn parse_sort<F: Listable>(sort: &str) -> Result<Vec<(&'static str, bool)>, AppError> {
+    sort.split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let (name, descending) = match field.strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (field, false),
+            };
+            F::SORT_FIELDS
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(_, column)| (*column, descending))
+                .ok_or_else(|| {
+                    let known: Vec<_> = F::SORT_FIELDS.iter().map(|(known, _)| *known).collect();
+                    AppError::FieldValidation(vec![FieldError::new(
+                        "sort",
+                        format!(
+                            "Unknown field `{}`, expected one of {}",
+                            name,
+                            known.join(", ")
+                        ),
+                    )])
+                })
+        })
+        .collect()
+}
+
+impl<S: Send + Sync, F: Listable + DeserializeOwned + Send> FromRequestParts<S> for ListQuery<F> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Parsed twice since flattened structs lose the number parsing of query strings.
+        let Query(page) = Query::<PageQuery>::try_from_uri(&parts.uri)
+            .map_err(|e| AppError::BadRequest(e.body_text()))?;
+        let Query(filter) = Query::<F>::try_from_uri(&parts.uri)
+            .map_err(|e| AppError::BadRequest(e.body_text()))?;
+
+        let sort = parse_sort::<F>(page.sort.as_deref().unwrap_or(F::DEFAULT_SORT))?;
+
+        Ok(Self {
+            filter,
+            limit: page.limit.map(|limit| limit.min(MAX_LIMIT)),
+            offset: page.offset.unwrap_or(0),
+            sort,
+        })
+    }
+}
+
+impl<F: Listable> ListQuery<F> {
+    /// Runs `SELECT columns` over the rows `from` pushes, returning the requested page and
+    /// how many rows match in total. `from` has to end inside a `WHERE` clause.
+    pub async fn fetch<T>(
+        &self,
+        db: &MySqlPool,
+        columns: &str,
+        from: impl Fn(&mut QueryBuilder<'_, MySql>),
+    ) -> Result<Paged<T>, sqlx::Error>
+    where
+        T: for<'r> FromRow<'r, MySqlRow> + Send + Unpin,
+    {
+        let mut count: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) ");
+        from(&mut count);
+        self.filter.push_filters(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(db).await?;
+
+        let mut select: QueryBuilder<MySql> = QueryBuilder::new(format!("SELECT {} ", columns));
+        from(&mut select);
+        self.filter.push_filters(&mut select);
+        select.push(" ORDER BY ");
+        for (column, descending) in &self.sort {
+            select
+                .push(column)
+                .push(if *descending { " DESC, " } else { " ASC, " });
+        }
+        select.push(F::KEY).push(" ASC");
+        // MySQL has no OFFSET without LIMIT, its documented "all rows" value is the largest u64.
+        select
+            .push(" LIMIT ")
+            .push_bind(self.limit.map_or(u64::MAX, u64::from))
+            .push(" OFFSET ")
+            .push_bind(self.offset);
+
+        let items = select.build_query_as::<T>().fetch_all(db).await?;
+
+        Ok(Paged { items, total })
+    }
+}
+
+impl<T: Serialize> IntoResponse for Paged<T> {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.items).into_response();
+        response
+            .headers_mut()
+            .insert(TOTAL_COUNT_HEADER, HeaderValue::from(self.total));
+        response
+    }
+}
+
+impl Listable for GroupFilter {
+    const SORT_FIELDS: &'static [(&'static str, &'static str)] =
+        &[("id", "id"), ("name", "name"), ("shift", "shift")];
+    const DEFAULT_SORT: &'static str = "name";
+    const KEY: &'static str = "id";
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, MySql>) {
+        if let Some(name) = &self.name {
+            builder
+                .push(" AND name LIKE ")
+                .push_bind(format!("{}%", escape_like(name)));
+        }
+        if let Some(shift) = self.shift {
+            builder.push(" AND shift=").push_bind(shift);
+        }
+    }
+}
+
+impl Listable for TeacherFilter {
+    const SORT_FIELDS: &'static [(&'static str, &'static str)] =
+        &[("id", "id"), ("full_name", "full_name"), ("role", "role")];
+    const DEFAULT_SORT: &'static str = "full_name";
+    const KEY: &'static str = "id";
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, MySql>) {
+        if let Some(search) = &self.search {
+            builder
+                .push(" AND full_name LIKE ")
+                .push_bind(format!("%{}%", escape_like(search)));
+        }
+    }
+}
+
+impl Listable for SubjectFilter {
+    const SORT_FIELDS: &'static [(&'static str, &'static str)] = &[("id", "id"), ("name", "name")];
+    const DEFAULT_SORT: &'static str = "name";
+    const KEY: &'static str = "id";
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, MySql>) {
+        if let Some(search) = &self.search {
+            builder
+                .push(" AND name LIKE ")
+                .push_bind(format!("%{}%", escape_like(search)));
+        }
+    }
+}
+
+impl Listable for ChangeFilter {
+    const SORT_FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("schedule_id", "schedule_id"),
+        ("date", "date"),
+        ("new_start_time", "new_start_time"),
+    ];
+    const DEFAULT_SORT: &'static str = "date,new_start_time";
+    const KEY: &'static str = "schedule_id";
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, MySql>) {
+        if let Some(from) = self.from {
+            builder.push(" AND date >= ").push_bind(from);
+        }
+        if let Some(to) = self.to {
+            builder.push(" AND date <= ").push_bind(to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_maps_fields_to_columns() {
+        let sort = parse_sort::<GroupFilter>("name, -shift").unwrap();
+        assert_eq!(sort, vec![("name", false), ("shift", true)]);
+    }
+
+    #[test]
+    fn sort_skips_empty_fields() {
+        let sort = parse_sort::<GroupFilter>(",-id,").unwrap();
+        assert_eq!(sort, vec![("id", true)]);
+    }
+
+    #[test]
+    fn sort_rejects_unknown_fields() {
+        for sort in [
+            "password_hash",
+            "-name; DROP TABLE groups",
+            "--name",
+            "name desc",
+        ] {
+            assert!(matches!(
+                parse_sort::<GroupFilter>(sort),
+                Err(AppError::FieldValidation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("c:\\"), "c:\\\\");
+        assert_eq!(escape_like("ИС-21"), "ИС-21");
+    }
+}
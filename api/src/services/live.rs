@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+
+use crate::{
+    config::AppState,
+    models::{LiveEvent, LiveEventKind, ScheduleChange},
+    traits::Schedules,
+};
+
+/// Redis channel every API instance publishes to and relays from.
+const LIVE_CHANNEL: &str = "live_events";
+
+/// Events a slow subscriber may fall behind by before it is told to resync.
+const BUFFER: usize = 256;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Events of this instance's subscribers. Nothing is sent to it directly, everything
+/// goes through Redis so that clients of every instance see the same events.
+#[derive(Clone)]
+pub struct Live {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Live {
+    pub fn init() -> Self {
+        Self {
+            sender: broadcast::channel(BUFFER).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Forwards events published by any instance to the local subscribers.
+pub fn spawn_live_relay(app_state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = relay(&app_state).await {
+                eprintln!("Live event relay stopped: {}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn relay(app_state: &AppState) -> redis::RedisResult<()> {
+    let mut pubsub = app_state.redis.subscribe(LIVE_CHANNEL).await?;
+    let mut messages = pubsub.on_message();
+
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<LiveEvent>(&payload) {
+            // Having no subscribers on this instance is not an error.
+            Ok(event) => {
+                let _ = app_state.live.sender.send(event);
+            }
+            Err(e) => eprintln!("Skipping malformed live event: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+async fn publish(app_state: &AppState, event: &LiveEvent) {
+    let Ok(payload) = serde_json::to_string(event) else {
+        return;
+    };
+    if let Err(e) = app_state.redis.publish(LIVE_CHANNEL, &payload).await {
+        eprintln!(
+            "Failed to publish {} event of group {}: {}",
+            event.kind.as_str(),
+            event.group_id,
+            e
+        );
+    }
+}
+
+/// Publishes one event per change, addressed to its group and to the teachers of the
+/// replaced and the replacing pair.
+pub async fn publish_changes(
+    app_state: AppState,
+    kind: LiveEventKind,
+    changes: Vec<ScheduleChange>,
+) {
+    for change in changes {
+        let mut teacher_ids = vec![change.new_teacher_id];
+        if let Ok(pair) = app_state.db.get_pair(change.schedule_id).await
+            && pair.teacher_id != change.new_teacher_id
+        {
+            teacher_ids.push(pair.teacher_id);
+        }
+
+        let event = LiveEvent {
+            kind,
+            group_id: change.group_id,
+            teacher_ids,
+            change: Some(change),
+            weekday: None,
+        };
+        publish(&app_state, &event).await;
+    }
+}
+
+pub async fn publish_schedule_update(
+    app_state: AppState,
+    group_id: i64,
+    weekday: i8,
+    mut teacher_ids: Vec<i64>,
+) {
+    teacher_ids.sort_unstable();
+    teacher_ids.dedup();
+
+    let event = LiveEvent {
+        kind: LiveEventKind::ScheduleUpdated,
+        group_id,
+        teacher_ids,
+        change: None,
+        weekday: Some(weekday),
+    };
+    publish(&app_state, &event).await;
+}
@@ -1,2 +1,17 @@
+pub mod audit;
 pub mod auth;
+pub mod cache;
+pub mod concurrency;
+pub mod digest;
+pub mod email;
+pub mod encoding;
+pub mod idempotency;
+pub mod listing;
+pub mod live;
 pub mod notifications;
+pub mod oidc;
+pub mod rate_limit;
+pub mod schedule;
+pub mod sync;
+pub mod totp;
+pub mod validation;
@@ -2,12 +2,12 @@ use fcm_service::{FcmMessage, FcmNotification, FcmService, Target};
 use std::sync::Arc;
 
 #[derive(Clone)]
-pub struct FCM {
+pub struct Fcm {
     service: Arc<FcmService>,
     message: FcmMessage,
 }
 
-impl FCM {
+impl Fcm {
     pub fn init() -> Self {
         let mut notification = FcmNotification::new();
         notification.set_title("Расписание обновлено!".to_string());
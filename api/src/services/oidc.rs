@@ -0,0 +1,287 @@
+use openidconnect::{
+    AdditionalClaims, AuthenticationFlow, AuthorizationCode, Client, ClientId, ClientSecret,
+    CsrfToken, EmptyExtraTokenFields, EndpointMaybeSet, EndpointNotSet, EndpointSet, IdTokenClaims,
+    IdTokenFields, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    StandardErrorResponse, StandardTokenResponse,
+    core::{
+        CoreAuthDisplay, CoreAuthPrompt, CoreErrorResponseType, CoreGenderClaim, CoreJsonWebKey,
+        CoreJweContentEncryptionAlgorithm, CoreJwsSigningAlgorithm, CoreProviderMetadata,
+        CoreResponseType, CoreRevocableToken, CoreRevocationErrorResponse,
+        CoreTokenIntrospectionResponse, CoreTokenType,
+    },
+    reqwest,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    auth::Role,
+    config::{AppState, OidcConfig, OidcMatchField},
+    errors::AppError,
+    models::{Teacher, TeacherProfile},
+    services::{
+        audit::{AuditContext, AuditEvent},
+        auth::{generate_code, hash_password},
+    },
+    traits::Teachers,
+};
+
+/// Seconds a started login may take before the state stored in Redis expires.
+pub const OIDC_STATE_TTL: u64 = 600;
+
+/// Claims outside the OpenID standard set, such as the groups claim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExtraClaims(Map<String, Value>);
+
+impl AdditionalClaims for ExtraClaims {}
+
+type OidcIdTokenFields = IdTokenFields<
+    ExtraClaims,
+    EmptyExtraTokenFields,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+>;
+
+type OidcClient = Client<
+    ExtraClaims,
+    CoreAuthDisplay,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJsonWebKey,
+    CoreAuthPrompt,
+    StandardErrorResponse<CoreErrorResponseType>,
+    StandardTokenResponse<OidcIdTokenFields, CoreTokenType>,
+    CoreTokenIntrospectionResponse,
+    CoreRevocableToken,
+    CoreRevocationErrorResponse,
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// Verified identity returned by the provider.
+#[derive(Debug)]
+pub struct OidcIdentity {
+    pub match_value: Option<String>,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub full_name: Option<String>,
+    pub groups: Vec<String>,
+}
+
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub nonce: String,
+    pub pkce_verifier: String,
+}
+
+#[derive(Clone)]
+pub struct Oidc {
+    client: OidcClient,
+    http: reqwest::Client,
+    pub config: OidcConfig,
+}
+
+impl Oidc {
+    pub async fn init(config: &OidcConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        // Following redirects would let the provider point the server at arbitrary URLs.
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let metadata =
+            CoreProviderMetadata::discover_async(IssuerUrl::new(config.issuer_url.clone())?, &http)
+                .await?;
+        let client: OidcClient = Client::from_provider_metadata(
+            metadata,
+            ClientId::new(config.client_id.clone()),
+            config.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(RedirectUrl::new(config.redirect_url.clone())?);
+
+        Ok(Self {
+            client,
+            http,
+            config: config.clone(),
+        })
+    }
+
+    pub fn authorize(&self) -> AuthorizationRequest {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let mut request = self
+            .client
+            .authorize_url(
+                AuthenticationFlow::<CoreResponseType>::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random,
+            )
+            .set_pkce_challenge(pkce_challenge);
+        for scope in &self.config.scopes {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+        let (url, state, nonce) = request.url();
+
+        AuthorizationRequest {
+            url: url.to_string(),
+            state: state.into_secret(),
+            nonce: nonce.secret().clone(),
+            pkce_verifier: pkce_verifier.into_secret(),
+        }
+    }
+
+    /// Exchanges the authorization code and verifies the returned ID token.
+    pub async fn exchange(
+        &self,
+        code: String,
+        nonce: String,
+        pkce_verifier: String,
+    ) -> Result<OidcIdentity, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .exchange_code(AuthorizationCode::new(code))?
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier))
+            .request_async(&self.http)
+            .await?;
+        let id_token = response
+            .extra_fields()
+            .id_token()
+            .ok_or("Provider did not return an ID token")?;
+        let claims = id_token.claims(&self.client.id_token_verifier(), &Nonce::new(nonce))?;
+
+        Ok(OidcIdentity {
+            match_value: claim(claims, &self.config.match_claim),
+            username: claims.preferred_username().map(|u| u.to_string()),
+            email: claims.email().map(|e| e.to_string()),
+            email_verified: claims.email_verified(),
+            full_name: claims
+                .name()
+                .and_then(|n| n.get(None))
+                .map(|n| n.to_string()),
+            groups: groups(claims, &self.config.groups_claim),
+        })
+    }
+}
+
+fn claim(claims: &IdTokenClaims<ExtraClaims, CoreGenderClaim>, name: &str) -> Option<String> {
+    match name {
+        "sub" => Some(claims.subject().to_string()),
+        "email" => claims.email().map(|e| e.to_string()),
+        "preferred_username" => claims.preferred_username().map(|u| u.to_string()),
+        _ => claims
+            .additional_claims()
+            .0
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    }
+}
+
+fn groups(claims: &IdTokenClaims<ExtraClaims, CoreGenderClaim>, name: &str) -> Vec<String> {
+    match claims.additional_claims().0.get(name) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(value)) => vec![value.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Finds the teacher the provider account maps to, creating it when auto-provisioning
+/// is enabled, and keeps the role in sync with the groups claim.
+pub async fn resolve_teacher(
+    app_state: &AppState,
+    oidc: &Oidc,
+    identity: &OidcIdentity,
+    audit: &AuditContext,
+) -> Result<Teacher, AppError> {
+    let config = &oidc.config;
+    let Some(value) = &identity.match_value else {
+        return Err(AppError::Unauthorized);
+    };
+    // An email the provider hasn't verified could be anyone's, including an admin's.
+    let matches_email =
+        config.match_field == OidcMatchField::Email || config.match_claim == "email";
+    if matches_email && identity.email_verified != Some(true) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let found = match config.match_field {
+        OidcMatchField::Login => app_state.db.get_teacher_by_login(value).await,
+        OidcMatchField::Email => app_state.db.get_teacher_by_email(value).await,
+    };
+    let teacher = match found {
+        Ok(teacher) => teacher,
+        Err(sqlx::Error::RowNotFound) if config.auto_provision => {
+            provision_teacher(app_state, config, identity, value, audit).await?
+        }
+        Err(sqlx::Error::RowNotFound) => return Err(AppError::Forbidden),
+        Err(e) => return Err(e.into()),
+    };
+
+    let Some(role) = config.mapped_role(&identity.groups) else {
+        return Ok(teacher);
+    };
+    if role == teacher.role
+        || (teacher.role == Role::Admin
+            && app_state.db.count_teachers_with_role(Role::Admin).await? <= 1)
+    {
+        return Ok(teacher);
+    }
+
+    let updated = app_state.db.update_teacher_role(teacher.id, role).await?;
+    audit
+        .record(
+            app_state,
+            AuditEvent::new("oidc_sync_role", "teacher", teacher.id)
+                .before(&TeacherProfile::from(teacher))
+                .after(&TeacherProfile::from(updated.clone())),
+        )
+        .await;
+
+    Ok(updated)
+}
+
+async fn provision_teacher(
+    app_state: &AppState,
+    config: &OidcConfig,
+    identity: &OidcIdentity,
+    value: &str,
+    audit: &AuditContext,
+) -> Result<Teacher, AppError> {
+    let login = match config.match_field {
+        OidcMatchField::Login => value.to_string(),
+        OidcMatchField::Email => identity
+            .username
+            .clone()
+            .unwrap_or_else(|| value.to_string()),
+    };
+    let email = match config.match_field {
+        OidcMatchField::Login => identity.email.as_deref(),
+        OidcMatchField::Email => Some(value),
+    };
+    let full_name = identity.full_name.as_deref().unwrap_or(&login);
+    // Provisioned accounts sign in through the provider, so the local password is never handed out.
+    let password_hash = hash_password(&generate_code(32));
+
+    let teacher = app_state
+        .db
+        .add_teacher(&login, &password_hash, full_name, email, false)
+        .await?;
+    audit
+        .record(
+            app_state,
+            AuditEvent::new("oidc_provision", "teacher", teacher.id)
+                .after(&TeacherProfile::from(teacher.clone())),
+        )
+        .await;
+
+    Ok(teacher)
+}
@@ -0,0 +1,202 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{config::AppState, errors::AppError};
+
+const FAILURE_WINDOW: u64 = 900;
+const LOCKOUT_MEMORY: u64 = 86400;
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The client behind a trusted proxy, read from the right of `X-Forwarded-For` so
+/// addresses the client made up itself are never reached.
+fn forwarded_client(trusted_proxies: &[IpAddr], peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    headers
+        .get_all(FORWARDED_FOR)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map_while(|hop| hop.trim().parse::<IpAddr>().ok())
+        .find(|ip| !trusted_proxies.contains(ip))
+        .unwrap_or(peer)
+}
+
+/// Replaces the peer address with the client a trusted proxy forwarded the request for,
+/// so rate limits, lockouts and sessions see students rather than the load balancer.
+pub async fn client_address(
+    State(app_state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(addr)) = req.extensions().get::<ConnectInfo<SocketAddr>>().copied() {
+        let ip = forwarded_client(&app_state.limits.trusted_proxies, addr.ip(), req.headers());
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(ip, addr.port())));
+    }
+
+    next.run(req).await
+}
+
+/// Failed logins are counted both per login and per client address.
+fn login_subjects(app_state: &AppState, login: &str, ip: IpAddr) -> [(String, u64); 2] {
+    [
+        (
+            format!("login:{}", login.to_lowercase()),
+            app_state.limits.login_max_failures,
+        ),
+        (format!("ip:{}", ip), app_state.limits.ip_max_failures),
+    ]
+}
+
+pub async fn check_login_lock(
+    app_state: &AppState,
+    login: &str,
+    ip: IpAddr,
+) -> Result<(), AppError> {
+    for (subject, _) in login_subjects(app_state, login, ip) {
+        if let Some(ttl) = app_state
+            .redis
+            .ttl(&format!("login_lock:{}", subject))
+            .await?
+        {
+            return Err(AppError::RateLimit(ttl));
+        }
+    }
+
+    Ok(())
+}
+
+/// Each lockout of the same subject within a day doubles the next one.
+pub async fn record_login_failure(
+    app_state: &AppState,
+    login: &str,
+    ip: IpAddr,
+) -> Result<(), AppError> {
+    for (subject, max_failures) in login_subjects(app_state, login, ip) {
+        let failures_key = format!("login_failures:{}", subject);
+        let (failures, _) = app_state
+            .redis
+            .increment(&failures_key, FAILURE_WINDOW)
+            .await?;
+        if failures < max_failures {
+            continue;
+        }
+
+        let (level, _) = app_state
+            .redis
+            .increment(&format!("login_lockouts:{}", subject), LOCKOUT_MEMORY)
+            .await?;
+        let lockout = app_state
+            .limits
+            .lockout_seconds
+            .saturating_mul(1 << (level - 1).min(16))
+            .min(app_state.limits.max_lockout_seconds);
+
+        app_state
+            .redis
+            .set_flag(&format!("login_lock:{}", subject), lockout)
+            .await?;
+        app_state.redis.delete_keys(&[failures_key]).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn clear_login_failures(app_state: &AppState, login: &str) -> Result<(), AppError> {
+    let subject = format!("login:{}", login.to_lowercase());
+    app_state
+        .redis
+        .delete_keys(&[
+            format!("login_failures:{}", subject),
+            format!("login_lockouts:{}", subject),
+        ])
+        .await?;
+
+    Ok(())
+}
+
+/// Per-address request limit for public read endpoints, disabled when `RATE_LIMIT_REQUESTS=0`.
+pub async fn rate_limit(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let limits = &app_state.limits;
+    if limits.public_requests == 0 {
+        return Ok(next.run(req).await);
+    }
+
+    let (requests, retry_after) = app_state
+        .redis
+        .increment(&format!("rate:{}", addr.ip()), limits.public_window)
+        .await?;
+    if requests > limits.public_requests {
+        return Err(AppError::RateLimit(retry_after));
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn client(peer: [u8; 4], forwarded: Option<&'static str>) -> IpAddr {
+        let proxies = [IpAddr::from([10, 0, 0, 2]), IpAddr::from([10, 0, 0, 3])];
+        let mut headers = HeaderMap::new();
+        if let Some(forwarded) = forwarded {
+            headers.insert(FORWARDED_FOR, HeaderValue::from_static(forwarded));
+        }
+        forwarded_client(&proxies, IpAddr::from(peer), &headers)
+    }
+
+    #[test]
+    fn untrusted_peer_is_the_client() {
+        assert_eq!(
+            client([203, 0, 113, 9], Some("198.51.100.1")),
+            IpAddr::from([203, 0, 113, 9])
+        );
+    }
+
+    #[test]
+    fn trusted_proxy_forwards_the_client() {
+        assert_eq!(
+            client([10, 0, 0, 2], Some("198.51.100.1")),
+            IpAddr::from([198, 51, 100, 1])
+        );
+        assert_eq!(
+            client([10, 0, 0, 2], Some("198.51.100.1, 10.0.0.3")),
+            IpAddr::from([198, 51, 100, 1])
+        );
+    }
+
+    #[test]
+    fn spoofed_hops_are_not_trusted() {
+        assert_eq!(
+            client([10, 0, 0, 2], Some("1.2.3.4, 198.51.100.1")),
+            IpAddr::from([198, 51, 100, 1])
+        );
+        assert_eq!(
+            client([10, 0, 0, 2], Some("1.2.3.4, garbage")),
+            IpAddr::from([10, 0, 0, 2])
+        );
+    }
+
+    #[test]
+    fn trusted_proxy_without_header_is_the_client() {
+        assert_eq!(client([10, 0, 0, 2], None), IpAddr::from([10, 0, 0, 2]));
+    }
+}
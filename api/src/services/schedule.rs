@@ -0,0 +1,180 @@
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+use crate::{
+    config::AppState,
+    models::ScheduleChange,
+    traits::{Groups, ScheduleChanges, Schedules, Subjects, Teachers},
+};
+
+pub const WEEKDAYS: [&str; 7] = [
+    "Понедельник",
+    "Вторник",
+    "Среда",
+    "Четверг",
+    "Пятница",
+    "Суббота",
+    "Воскресенье",
+];
+
+pub struct DayEntry {
+    pub date: NaiveDate,
+    pub pair_number: i8,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub group: String,
+    pub subject: String,
+    pub teacher: String,
+    pub cabinet: String,
+    pub note: Option<String>,
+}
+
+pub fn weekday_of(date: NaiveDate) -> i8 {
+    date.weekday().number_from_monday() as i8
+}
+
+pub async fn group_name(app_state: &AppState, group_id: i64) -> String {
+    app_state
+        .db
+        .get_group_by_id(group_id)
+        .await
+        .map(|g| g.name)
+        .unwrap_or_default()
+}
+
+pub async fn subject_name(app_state: &AppState, subject_id: i64) -> String {
+    app_state
+        .db
+        .get_subject_by_id(subject_id)
+        .await
+        .map(|s| s.name)
+        .unwrap_or_default()
+}
+
+pub async fn teacher_name(app_state: &AppState, teacher_id: i64) -> String {
+    app_state
+        .db
+        .get_teacher_by_id(teacher_id)
+        .await
+        .map(|t| t.full_name)
+        .unwrap_or_default()
+}
+
+pub async fn change_entry(app_state: &AppState, change: &ScheduleChange) -> DayEntry {
+    let note = if change.is_canceled {
+        "Пара отменена"
+    } else {
+        "Замена"
+    };
+
+    DayEntry {
+        date: change.date,
+        pair_number: app_state
+            .db
+            .get_pair(change.schedule_id)
+            .await
+            .map(|p| p.pair_number)
+            .unwrap_or_default(),
+        start_time: change.new_start_time,
+        end_time: change.new_end_time,
+        group: group_name(app_state, change.group_id).await,
+        subject: subject_name(app_state, change.new_subject_id).await,
+        teacher: teacher_name(app_state, change.new_teacher_id).await,
+        cabinet: change.cabinet.clone(),
+        note: Some(note.to_string()),
+    }
+}
+
+pub async fn group_day(
+    app_state: &AppState,
+    group_id: i64,
+    date: NaiveDate,
+) -> Result<Vec<DayEntry>, sqlx::Error> {
+    let weekday = weekday_of(date);
+    let group = group_name(app_state, group_id).await;
+    let changes = app_state.db.get_schedule_changes(group_id).await?;
+    let mut entries = Vec::new();
+
+    for day in app_state.db.get_schedule(group_id).await? {
+        if day.weekday != weekday {
+            continue;
+        }
+
+        for pair in day.pairs {
+            match changes
+                .iter()
+                .find(|c| c.schedule_id == pair.id && c.date == date)
+            {
+                Some(change) => entries.push(change_entry(app_state, change).await),
+                None => entries.push(DayEntry {
+                    date,
+                    pair_number: pair.pair_number,
+                    start_time: pair.start_time,
+                    end_time: pair.end_time,
+                    group: group.clone(),
+                    subject: subject_name(app_state, pair.subject_id).await,
+                    teacher: teacher_name(app_state, pair.teacher_id).await,
+                    cabinet: pair.cabinet,
+                    note: None,
+                }),
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+pub async fn teacher_day(
+    app_state: &AppState,
+    teacher_id: i64,
+    date: NaiveDate,
+) -> Result<Vec<DayEntry>, sqlx::Error> {
+    let weekday = weekday_of(date);
+    let teacher = teacher_name(app_state, teacher_id).await;
+    let changes = app_state.db.get_changes_by_date(date).await?;
+    let mut entries = Vec::new();
+
+    for pair in app_state.db.get_teacher_pairs(teacher_id).await? {
+        if pair.weekday != weekday {
+            continue;
+        }
+
+        match changes.iter().find(|c| c.schedule_id == pair.id) {
+            Some(change) if change.is_canceled || change.new_teacher_id != teacher_id => {
+                let mut entry = change_entry(app_state, change).await;
+                entry.note = Some(if change.is_canceled {
+                    "Пара отменена".to_string()
+                } else {
+                    "Пару проводит другой преподаватель".to_string()
+                });
+                entries.push(entry);
+            }
+            Some(change) => entries.push(change_entry(app_state, change).await),
+            None => entries.push(DayEntry {
+                date,
+                pair_number: pair.pair_number,
+                start_time: pair.start_time,
+                end_time: pair.end_time,
+                group: group_name(app_state, pair.group_id).await,
+                subject: subject_name(app_state, pair.subject_id).await,
+                teacher: teacher.clone(),
+                cabinet: pair.cabinet,
+                note: None,
+            }),
+        }
+    }
+
+    for change in &changes {
+        if change.new_teacher_id != teacher_id || change.is_canceled {
+            continue;
+        }
+        if let Ok(pair) = app_state.db.get_pair(change.schedule_id).await
+            && pair.teacher_id != teacher_id
+        {
+            entries.push(change_entry(app_state, change).await);
+        }
+    }
+
+    entries.sort_by_key(|e| e.pair_number);
+
+    Ok(entries)
+}
@@ -0,0 +1,37 @@
+use chrono::Local;
+use std::time::Duration;
+
+use crate::{config::AppState, errors::AppError, traits::DeltaSync};
+
+/// Seconds between purges of the sync deletion log.
+const PURGE_INTERVAL: u64 = 3600;
+
+/// Purges deletions past their retention in the background, so `/sync` only reads.
+pub fn spawn_sync_purge(app_state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL));
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = purge_sync_deletions(&app_state).await {
+                eprintln!("Failed to purge sync deletions: {}", e);
+            }
+        }
+    });
+}
+
+async fn purge_sync_deletions(app_state: &AppState) -> Result<(), AppError> {
+    //Several API instances may run the same loop, only one of them purges each hour
+    let hour = Local::now().format("%Y-%m-%d %H");
+    if !app_state
+        .redis
+        .acquire_lock(&format!("sync_purge:{}", hour), PURGE_INTERVAL)
+        .await?
+    {
+        return Ok(());
+    }
+
+    app_state.db.purge_sync_deletions().await?;
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    config::AppState,
+    errors::AppError,
+    models::Teacher,
+    services::auth::{generate_code, hash_password},
+};
+
+pub const RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn totp(app_state: &AppState, secret: &str, login: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AppError::Internal)?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(app_state.mfa.issuer.clone()),
+        login.replace(':', "_"),
+    )
+    .map_err(|_| AppError::Internal)
+}
+
+pub fn provisioning_uri(
+    app_state: &AppState,
+    secret: &str,
+    login: &str,
+) -> Result<String, AppError> {
+    Ok(totp(app_state, secret, login)?.get_url())
+}
+
+/// Checks a TOTP code against the teacher's secret; each code is accepted only once.
+pub async fn verify_totp(
+    app_state: &AppState,
+    teacher: &Teacher,
+    code: &str,
+) -> Result<bool, AppError> {
+    let Some(secret) = &teacher.totp_secret else {
+        return Ok(false);
+    };
+    let code = code.trim();
+    let valid = totp(app_state, secret, &teacher.login)?
+        .check_current(code)
+        .map_err(|_| AppError::Internal)?;
+    if !valid {
+        return Ok(false);
+    }
+
+    Ok(app_state
+        .redis
+        .acquire_lock(&format!("totp_used:{}:{}", teacher.id, code), 90)
+        .await?)
+}
+
+/// Returns the plaintext codes to show once and their hashes to store.
+pub fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| generate_code(RECOVERY_CODE_LENGTH))
+        .collect();
+    let hashes = codes.iter().map(|c| hash_password(c)).collect();
+
+    (codes, hashes)
+}
@@ -0,0 +1,102 @@
+use crate::{
+    config::AppState,
+    errors::{AppError, FieldError},
+    models::{ScheduleChange, ScheduleRow},
+    services::schedule::weekday_of,
+    traits::{Subjects, Teachers},
+};
+
+/// Checks references the database can't express, such as a subject of another group.
+/// Problems are collected so a whole batch is reported at once.
+#[derive(Default)]
+pub struct CrossChecks {
+    details: Vec<FieldError>,
+}
+
+impl CrossChecks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn subject_in_group(
+        &mut self,
+        app_state: &AppState,
+        field: String,
+        subject_id: i64,
+        group_id: i64,
+    ) -> Result<(), AppError> {
+        match app_state.db.get_subject_by_id(subject_id).await {
+            Ok(subject) if subject.group_id == group_id => {}
+            Ok(_) => self.fail(field, "Subject belongs to another group"),
+            Err(sqlx::Error::RowNotFound) => self.fail(field, "Subject does not exist"),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    pub async fn teacher_exists(
+        &mut self,
+        app_state: &AppState,
+        field: String,
+        teacher_id: i64,
+    ) -> Result<(), AppError> {
+        match app_state.db.get_teacher_by_id(teacher_id).await {
+            Ok(_) => {}
+            Err(sqlx::Error::RowNotFound) => self.fail(field, "Teacher does not exist"),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    pub fn pair_in_group(&mut self, field: String, pair: &ScheduleRow, group_id: i64) {
+        if pair.group_id != group_id {
+            self.fail(field, "Pair belongs to another group");
+        }
+    }
+
+    /// Checks a change against the pair it replaces.
+    pub async fn change(
+        &mut self,
+        app_state: &AppState,
+        prefix: &str,
+        change: &ScheduleChange,
+        pair: &ScheduleRow,
+    ) -> Result<(), AppError> {
+        self.pair_in_group(format!("{}group_id", prefix), pair, change.group_id);
+        if weekday_of(change.date) != pair.weekday {
+            self.fail(
+                format!("{}date", prefix),
+                "Pair is not held on this weekday",
+            );
+        }
+        if change.is_canceled {
+            return Ok(());
+        }
+
+        self.subject_in_group(
+            app_state,
+            format!("{}new_subject_id", prefix),
+            change.new_subject_id,
+            pair.group_id,
+        )
+        .await?;
+        self.teacher_exists(
+            app_state,
+            format!("{}new_teacher_id", prefix),
+            change.new_teacher_id,
+        )
+        .await
+    }
+
+    fn fail(&mut self, field: String, message: &str) {
+        self.details.push(FieldError::new(field, message));
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.details.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::FieldValidation(self.details))
+        }
+    }
+}
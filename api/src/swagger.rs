@@ -1,15 +1,31 @@
-use crate::auth::handlers::{__path_login, __path_logout};
-use crate::routes::groups::{__path_get_group_by_id, __path_get_groups, __path_add_group, __path_edit_group, __path_delete_group};
+use crate::auth::handlers::{
+    __path_login, __path_login_mfa, __path_logout, __path_oidc_callback, __path_oidc_login,
+    __path_reset_password, __path_student_login,
+};
+use crate::routes::groups::{__path_get_group_by_id, __path_get_group_bundle, __path_get_groups, __path_add_group, __path_edit_group, __path_delete_group};
 use crate::routes::subjects::{__path_add_subject, __path_edit_subject, __path_delete_subject, __path_get_subjects_by_group_id};
-use crate::routes::teachers::{__path_add_teacher, __path_delete_teacher, __path_get_teachers, __path_get_teacher_by_id, __path_update_teacher_login, __path_update_teacher_fullname, __path_update_teacher_password};
+use crate::routes::teachers::{__path_add_teacher, __path_delete_teacher, __path_get_teachers, __path_get_teacher_by_id, __path_update_teacher_login, __path_update_teacher_fullname, __path_update_teacher_password, __path_update_teacher_email, __path_update_teacher_role, __path_get_roles, __path_get_teacher_sessions, __path_revoke_teacher_sessions, __path_issue_reset_code};
 use crate::routes::schedule::{__path_delete_pair, __path_delete_day, __path_add_pairs, __path_edit_pairs, __path_get_schedule};
 use crate::routes::schedule_changes::{__path_add_schedule_changes, __path_get_schedule_changes, __path_edit_schedule_changes, __path_delete_schedule_changes};
 use crate::routes::teacher_links::{__path_delete_teacher_link, __path_add_teacher_link, __path_get_teacher_links};
+use crate::routes::audit::__path_get_audit_log;
+use crate::routes::api_keys::{__path_get_api_keys, __path_add_api_key, __path_delete_api_key};
+use crate::routes::group_grants::{__path_get_group_grants, __path_add_group_grants, __path_delete_group_grant};
+use crate::routes::me::{__path_get_me, __path_change_my_password, __path_get_my_schedule, __path_get_my_changes, __path_get_my_notifications, __path_update_my_notifications, __path_get_my_sessions, __path_revoke_my_session, __path_setup_totp, __path_enable_totp, __path_disable_totp, __path_regenerate_recovery_codes};
+use crate::routes::students::{__path_get_students, __path_add_students, __path_edit_student, __path_delete_student, __path_issue_student_password};
+use crate::routes::student_me::{__path_get_student_profile, __path_get_student_schedule, __path_get_student_changes, __path_change_student_password};
+use crate::routes::impersonation::__path_impersonate;
+use crate::routes::live::__path_live_events;
+use crate::routes::sync::__path_sync;
 use crate::routes::fcm::{__path_send_notifications_to_teachers, __path_send_notifications_to_group};
+use crate::routes::v1::{Inject, PREFIX, ROUTES};
+use axum::http::{Method, StatusCode};
 use utoipa::{
     Modify, OpenApi,
     openapi::{
-        self, ComponentsBuilder,
+        self, ComponentsBuilder, Deprecated, RefOr,
+        path::{Operation, Parameter, ParameterIn, PathItem},
+        schema::{KnownFormat, ObjectBuilder, SchemaFormat, Type},
         security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
     },
 };
@@ -37,9 +53,109 @@ impl Modify for SecurityAddon {
     }
 }
 
+struct V1Addon;
+
+fn operation<'a>(item: &'a mut PathItem, method: &Method) -> &'a mut Option<Operation> {
+    match *method {
+        Method::GET => &mut item.get,
+        Method::POST => &mut item.post,
+        Method::PUT => &mut item.put,
+        Method::PATCH => &mut item.patch,
+        _ => &mut item.delete,
+    }
+}
+
+fn path_parameter(name: &str) -> Parameter {
+    let mut parameter = Parameter::new(name);
+    parameter.parameter_in = ParameterIn::Path;
+    parameter.schema = Some(
+        ObjectBuilder::new()
+            .schema_type(Type::Integer)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+            .into(),
+    );
+    parameter
+}
+
+/// Documents every `/api/v1` route with the operation of the legacy route it maps to,
+/// and marks the legacy operation deprecated.
+impl Modify for V1Addon {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        for route in ROUTES {
+            let Some(legacy) = openapi
+                .paths
+                .paths
+                .get_mut(route.legacy_path)
+                .and_then(|item| operation(item, &route.legacy_method).as_mut())
+            else {
+                eprintln!(
+                    "{} {} is not documented, leaving {}{} out of the docs",
+                    route.legacy_method, route.legacy_path, PREFIX, route.path
+                );
+                continue;
+            };
+            let mut v1 = legacy.clone();
+            legacy.deprecated = Some(Deprecated::True);
+            legacy.operation_id = legacy.operation_id.take().map(|id| format!("{}_legacy", id));
+
+            let injected: Vec<&str> = match route.inject {
+                Inject::None => Vec::new(),
+                Inject::Fields(fields) => fields.iter().map(|(name, _)| *name).collect(),
+                Inject::List(name) => vec![name],
+            };
+            let parameters = v1.parameters.get_or_insert_with(Vec::new);
+            for name in &injected {
+                if !parameters.iter().any(|p| p.name == *name) {
+                    parameters.push(path_parameter(name));
+                }
+            }
+            if let Inject::Fields(fields) = route.inject {
+                let moved: Vec<String> = fields
+                    .iter()
+                    .map(|(name, field)| format!("`{}` is taken from the `{}` path parameter.", field, name))
+                    .collect();
+                v1.description = Some(moved.join(" "));
+            }
+            if route.method == Method::DELETE {
+                v1.request_body = None;
+            }
+            if route.status != StatusCode::OK
+                && let Some(mut response) = v1.responses.responses.remove("200")
+            {
+                if route.status == StatusCode::NO_CONTENT
+                    && let RefOr::T(response) = &mut response
+                {
+                    response.content.clear();
+                }
+                v1.responses.responses.insert(route.status.as_u16().to_string(), response);
+            }
+
+            let item = openapi
+                .paths
+                .paths
+                .entry(format!("{}{}", PREFIX, route.path))
+                .or_default();
+            *operation(item, &route.method) = Some(v1);
+        }
+    }
+}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        get_me,
+        change_my_password,
+        get_my_schedule,
+        get_my_changes,
+        get_my_notifications,
+        update_my_notifications,
+        get_my_sessions,
+        revoke_my_session,
+        setup_totp,
+        enable_totp,
+        disable_totp,
+        regenerate_recovery_codes,
+
         send_notifications_to_group,
         send_notifications_to_teachers,
 
@@ -61,6 +177,37 @@ impl Modify for SecurityAddon {
         update_teacher_login,
         update_teacher_password,
         update_teacher_fullname,
+        update_teacher_email,
+        update_teacher_role,
+        get_roles,
+        get_teacher_sessions,
+        revoke_teacher_sessions,
+        issue_reset_code,
+
+        get_audit_log,
+
+        get_api_keys,
+        add_api_key,
+        delete_api_key,
+
+        get_group_grants,
+        add_group_grants,
+        delete_group_grant,
+
+        get_students,
+        add_students,
+        edit_student,
+        delete_student,
+        issue_student_password,
+
+        get_student_profile,
+        get_student_schedule,
+        get_student_changes,
+        change_student_password,
+
+        impersonate,
+        live_events,
+        sync,
 
         add_teacher_link,
         delete_teacher_link,
@@ -75,10 +222,16 @@ impl Modify for SecurityAddon {
         edit_group, 
         add_group, 
         get_groups, 
-        get_group_by_id, 
+        get_group_by_id,
+        get_group_bundle, 
 
         login, 
-        logout
+        login_mfa,
+        logout,
+        reset_password,
+        oidc_login,
+        oidc_callback,
+        student_login
     ),
     components(
         schemas(
@@ -87,12 +240,47 @@ impl Modify for SecurityAddon {
 
             crate::models::TeacherLink,
 
-            crate::models::Teacher,
             crate::models::TeacherSafe,
             crate::models::AddTeacherRequest,
             crate::models::EditTeacherLoginRequest,
             crate::models::EditTeacherPasswordRequest,
             crate::models::EditTeacherFullnameRequest,
+            crate::models::EditTeacherEmailRequest,
+            crate::models::EditTeacherRoleRequest,
+            crate::models::RoleInfo,
+            crate::models::Session,
+            crate::models::IssueResetCodeRequest,
+            crate::models::ResetCodeResponse,
+            crate::models::SessionInfo,
+            crate::models::AuditEntry,
+            crate::models::ActorType,
+            crate::models::ApiKey,
+            crate::models::AddApiKeyRequest,
+            crate::models::ApiKeyCreated,
+            crate::models::GroupGrant,
+            crate::models::AddGroupGrantsRequest,
+            crate::models::TeacherProfile,
+            crate::models::Student,
+            crate::models::NewStudent,
+            crate::models::AddStudentsRequest,
+            crate::models::StudentCreated,
+            crate::models::EditStudentRequest,
+            crate::models::IssueStudentPasswordRequest,
+            crate::models::StudentPasswordResponse,
+            crate::models::AccountKind,
+            crate::models::ImpersonateRequest,
+            crate::models::ImpersonationResponse,
+            crate::models::LiveEvent,
+            crate::models::LiveEventKind,
+            crate::models::SyncResponse,
+            crate::models::SyncDeletion,
+            crate::models::SyncEntity,
+            crate::models::ChangeOwnPasswordRequest,
+            crate::models::NotificationPreference,
+            crate::models::EditNotificationPreferenceRequest,
+            crate::models::ScheduleRow,
+            crate::auth::Role,
+            crate::auth::Permission,
 
             crate::models::Subject,
             crate::models::AddSubjectRequest,
@@ -100,11 +288,24 @@ impl Modify for SecurityAddon {
 
             crate::models::Group,
             crate::models::AddGroupRequest,
+            crate::models::EditGroupRequest,
+            crate::models::GroupBundle,
+            crate::models::BundlePair,
+            crate::models::BundleChange,
+            crate::models::BundleTeacherLink,
 
             crate::errors::ErrorResponse, 
+            crate::errors::FieldError,
             
             crate::models::auth::LoginRequest, 
-            crate::models::auth::LogoutRequest, 
+            crate::models::auth::LogoutRequest,
+            crate::models::auth::ResetPasswordRequest,
+            crate::models::MfaChallenge,
+            crate::models::LoginMfaRequest,
+            crate::models::TotpSetupResponse,
+            crate::models::TotpCodeRequest,
+            crate::models::DisableTotpRequest,
+            crate::models::RecoveryCodesResponse, 
             crate::models::auth::LoginResponse,
 
             crate::models::schedule::Schedule,
@@ -113,10 +314,30 @@ impl Modify for SecurityAddon {
             crate::models::schedule_changes::ScheduleChange
             )
         ),
-    modifiers(&SecurityAddon),
+    modifiers(&SecurityAddon, &V1Addon),
 )]
 pub struct ApiDoc;
 
 pub fn swagger_ui() -> SwaggerUi {
     SwaggerUi::new("/swagger").url("/api-docs/openapi.json", ApiDoc::openapi())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_v1_route_is_documented() {
+        let openapi = ApiDoc::openapi();
+        for route in ROUTES {
+            let path = format!("{}{}", PREFIX, route.path);
+            let documented = openapi
+                .paths
+                .paths
+                .get(&path)
+                .cloned()
+                .is_some_and(|mut item| operation(&mut item, &route.method).is_some());
+            assert!(documented, "{} {} is not documented", route.method, path);
+        }
+    }
+}
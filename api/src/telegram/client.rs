@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::TelegramConfig;
+
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse<T> {
+    pub ok: bool,
+    pub result: Option<T>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct GetUpdates {
+    offset: i64,
+    timeout: u64,
+    allowed_updates: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct SendMessage<'a> {
+    chat_id: i64,
+    text: &'a str,
+}
+
+#[derive(Clone)]
+pub struct TelegramBot {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl TelegramBot {
+    pub const POLL_TIMEOUT: u64 = 30;
+
+    pub fn init(config: &TelegramConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(Self::POLL_TIMEOUT + 10))
+            .build()
+            .unwrap();
+
+        Self {
+            http,
+            base_url: format!(
+                "{}/bot{}",
+                config.api_url.trim_end_matches('/'),
+                config.token
+            ),
+        }
+    }
+
+    async fn call<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        body: &B,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        let response: ApiResponse<T> = self
+            .http
+            .post(format!("{}/{}", self.base_url, method))
+            .json(body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => Err(response
+                .description
+                .unwrap_or_else(|| format!("Telegram method {} failed", method))
+                .into()),
+        }
+    }
+
+    pub async fn get_updates(
+        &self,
+        offset: i64,
+    ) -> Result<Vec<Update>, Box<dyn std::error::Error + Send + Sync>> {
+        self.call(
+            "getUpdates",
+            &GetUpdates {
+                offset,
+                timeout: Self::POLL_TIMEOUT,
+                allowed_updates: vec!["message"],
+            },
+        )
+        .await
+    }
+
+    pub async fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.call::<_, serde::de::IgnoredAny>("sendMessage", &SendMessage { chat_id, text })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use chrono::{Days, Local, NaiveDate};
+
+use crate::{
+    config::AppState,
+    models::Group,
+    services::schedule::{
+        DayEntry, WEEKDAYS, group_day, subject_name, teacher_day, teacher_name, weekday_of,
+    },
+    traits::{Groups, Schedules, Teachers, TelegramSubscriptions},
+};
+
+const HELP: &str = "Команды StudyLine:\n\
+/today <группа> — расписание на сегодня\n\
+/tomorrow <группа> — расписание на завтра\n\
+/week <группа> — расписание на неделю\n\
+/teacher <ФИО> — пары преподавателя на сегодня\n\
+/subscribe <группа> — получать уведомления об изменениях\n\
+/unsubscribe <группа> — отписаться от уведомлений\n\
+/subscriptions — список подписок\n\n\
+Если чат подписан на группу, её название можно не указывать.";
+
+pub enum Command {
+    Help,
+    Today(String),
+    Tomorrow(String),
+    Week(String),
+    Teacher(String),
+    Subscribe(String),
+    Unsubscribe(String),
+    Subscriptions,
+    Unknown,
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if !text.starts_with('/') {
+            return None;
+        }
+
+        let (command, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let command = command.split('@').next().unwrap_or(command);
+        let args = args.trim().to_string();
+
+        Some(match command {
+            "/start" | "/help" => Command::Help,
+            "/today" => Command::Today(args),
+            "/tomorrow" => Command::Tomorrow(args),
+            "/week" => Command::Week(args),
+            "/teacher" => Command::Teacher(args),
+            "/subscribe" => Command::Subscribe(args),
+            "/unsubscribe" => Command::Unsubscribe(args),
+            "/subscriptions" => Command::Subscriptions,
+            _ => Command::Unknown,
+        })
+    }
+}
+
+pub async fn handle_command(app_state: &AppState, chat_id: i64, command: Command) -> String {
+    let result = match command {
+        Command::Help => Ok(HELP.to_string()),
+        Command::Today(group) => day_reply(app_state, chat_id, &group, 0).await,
+        Command::Tomorrow(group) => day_reply(app_state, chat_id, &group, 1).await,
+        Command::Week(group) => week_reply(app_state, chat_id, &group).await,
+        Command::Teacher(name) => teacher_reply(app_state, &name).await,
+        Command::Subscribe(group) => subscribe_reply(app_state, chat_id, &group).await,
+        Command::Unsubscribe(group) => unsubscribe_reply(app_state, chat_id, &group).await,
+        Command::Subscriptions => subscriptions_reply(app_state, chat_id).await,
+        Command::Unknown => Ok(String::from("Неизвестная команда. Отправьте /help")),
+    };
+
+    match result {
+        Ok(reply) => reply,
+        Err(e) => {
+            eprintln!("Telegram command failed: {}", e);
+            String::from("Не удалось получить данные, попробуйте позже")
+        }
+    }
+}
+
+async fn find_group(app_state: &AppState, name: &str) -> Result<Option<Group>, sqlx::Error> {
+    let name = name.to_lowercase();
+    let groups = app_state.db.get_groups().await?;
+
+    Ok(groups.into_iter().find(|g| g.name.to_lowercase() == name))
+}
+
+async fn resolve_group(
+    app_state: &AppState,
+    chat_id: i64,
+    name: &str,
+) -> Result<Result<Group, String>, sqlx::Error> {
+    if !name.is_empty() {
+        return Ok(find_group(app_state, name)
+            .await?
+            .ok_or_else(|| format!("Группа «{}» не найдена", name)));
+    }
+
+    match app_state.db.get_chat_subscriptions(chat_id).await?.first() {
+        Some(subscription) => Ok(app_state
+            .db
+            .get_group_by_id(subscription.group_id)
+            .await
+            .map_err(|_| String::from("Группа не найдена"))),
+        None => Ok(Err(String::from("Укажите группу, например: /today ИС-21"))),
+    }
+}
+
+fn format_entry(entry: &DayEntry, show_group: bool) -> String {
+    let mut line = format!(
+        "{}. {}–{} {}",
+        entry.pair_number,
+        entry.start_time.format("%H:%M"),
+        entry.end_time.format("%H:%M"),
+        entry.subject
+    );
+    if show_group {
+        line.push_str(&format!(", группа {}", entry.group));
+    } else if !entry.teacher.is_empty() {
+        line.push_str(&format!(" ({})", entry.teacher));
+    }
+    if !entry.cabinet.is_empty() {
+        line.push_str(&format!(", каб. {}", entry.cabinet));
+    }
+    if let Some(note) = &entry.note {
+        line.push_str(&format!(" — {}", note));
+    }
+
+    line
+}
+
+fn day_title(title: &str, date: NaiveDate) -> String {
+    format!(
+        "{} — {}, {}",
+        title,
+        WEEKDAYS[(weekday_of(date) - 1) as usize].to_lowercase(),
+        date.format("%d.%m.%Y")
+    )
+}
+
+async fn day_reply(
+    app_state: &AppState,
+    chat_id: i64,
+    group: &str,
+    offset: u64,
+) -> Result<String, sqlx::Error> {
+    let group = match resolve_group(app_state, chat_id, group).await? {
+        Ok(group) => group,
+        Err(message) => return Ok(message),
+    };
+    let date = Local::now().date_naive() + Days::new(offset);
+    let entries = group_day(app_state, group.id, date).await?;
+
+    let mut lines = vec![day_title(&group.name, date)];
+    if entries.is_empty() {
+        lines.push(String::from("Пар нет"));
+    }
+    lines.extend(entries.iter().map(|e| format_entry(e, false)));
+
+    Ok(lines.join("\n"))
+}
+
+async fn week_reply(
+    app_state: &AppState,
+    chat_id: i64,
+    group: &str,
+) -> Result<String, sqlx::Error> {
+    let group = match resolve_group(app_state, chat_id, group).await? {
+        Ok(group) => group,
+        Err(message) => return Ok(message),
+    };
+
+    let mut lines = vec![format!("{} — расписание на неделю", group.name)];
+    for day in app_state.db.get_schedule(group.id).await? {
+        lines.push(String::new());
+        lines.push(WEEKDAYS[(day.weekday - 1) as usize].to_string());
+        for pair in day.pairs {
+            lines.push(format!(
+                "{}. {}–{} {} ({}), каб. {}",
+                pair.pair_number,
+                pair.start_time.format("%H:%M"),
+                pair.end_time.format("%H:%M"),
+                subject_name(app_state, pair.subject_id).await,
+                teacher_name(app_state, pair.teacher_id).await,
+                pair.cabinet
+            ));
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
+
+async fn teacher_reply(app_state: &AppState, name: &str) -> Result<String, sqlx::Error> {
+    if name.is_empty() {
+        return Ok(String::from(
+            "Укажите ФИО преподавателя, например: /teacher Иванов",
+        ));
+    }
+
+    let query = name.to_lowercase();
+    let teachers: Vec<_> = app_state
+        .db
+        .get_teachers()
+        .await?
+        .into_iter()
+        .filter(|t| t.full_name.to_lowercase().contains(&query))
+        .collect();
+
+    let teacher = match teachers.as_slice() {
+        [] => return Ok(format!("Преподаватель «{}» не найден", name)),
+        [teacher] => teacher,
+        _ => {
+            let names: Vec<_> = teachers.iter().map(|t| t.full_name.as_str()).collect();
+            return Ok(format!(
+                "Найдено несколько преподавателей:\n{}",
+                names.join("\n")
+            ));
+        }
+    };
+
+    let date = Local::now().date_naive();
+    let entries = teacher_day(app_state, teacher.id, date).await?;
+
+    let mut lines = vec![day_title(&teacher.full_name, date)];
+    if entries.is_empty() {
+        lines.push(String::from("Пар нет"));
+    }
+    lines.extend(entries.iter().map(|e| format_entry(e, true)));
+
+    Ok(lines.join("\n"))
+}
+
+async fn subscribe_reply(
+    app_state: &AppState,
+    chat_id: i64,
+    group: &str,
+) -> Result<String, sqlx::Error> {
+    if group.is_empty() {
+        return Ok(String::from("Укажите группу, например: /subscribe ИС-21"));
+    }
+    let Some(group) = find_group(app_state, group).await? else {
+        return Ok(format!("Группа «{}» не найдена", group));
+    };
+
+    app_state.db.subscribe_chat(chat_id, group.id).await?;
+
+    Ok(format!(
+        "Чат подписан на изменения расписания группы {}",
+        group.name
+    ))
+}
+
+async fn unsubscribe_reply(
+    app_state: &AppState,
+    chat_id: i64,
+    group: &str,
+) -> Result<String, sqlx::Error> {
+    let group = match resolve_group(app_state, chat_id, group).await? {
+        Ok(group) => group,
+        Err(message) => return Ok(message),
+    };
+
+    if app_state.db.unsubscribe_chat(chat_id, group.id).await? == 0 {
+        return Ok(format!("Чат не подписан на группу {}", group.name));
+    }
+
+    Ok(format!("Подписка на группу {} отменена", group.name))
+}
+
+async fn subscriptions_reply(app_state: &AppState, chat_id: i64) -> Result<String, sqlx::Error> {
+    let subscriptions = app_state.db.get_chat_subscriptions(chat_id).await?;
+    if subscriptions.is_empty() {
+        return Ok(String::from("Подписок нет. Отправьте /subscribe <группа>"));
+    }
+
+    let mut lines = vec![String::from("Подписки чата:")];
+    for subscription in subscriptions {
+        if let Ok(group) = app_state.db.get_group_by_id(subscription.group_id).await {
+            lines.push(group.name);
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,116 @@
+pub mod client;
+pub mod commands;
+
+pub use client::TelegramBot;
+
+use chrono::Local;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    config::AppState,
+    services::schedule::group_name,
+    traits::{ScheduleChanges, TelegramSubscriptions},
+};
+use commands::{Command, handle_command};
+
+/// Telegram answers 409 when a bot is polled twice at once, so of several API instances
+/// only the one holding the lock polls. Another takes over once it stops renewing it.
+const POLLING_LOCK: &str = "telegram_polling";
+const POLLING_LOCK_TTL: u64 = TelegramBot::POLL_TIMEOUT * 3;
+
+pub fn spawn_polling(app_state: AppState) {
+    let Some(bot) = app_state.telegram.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let owner = Uuid::new_v4().to_string();
+        let mut offset = 0;
+
+        loop {
+            match app_state
+                .redis
+                .hold_lock(POLLING_LOCK, &owner, POLLING_LOCK_TTL)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    tokio::time::sleep(Duration::from_secs(TelegramBot::POLL_TIMEOUT)).await;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Failed to take the Telegram polling lock: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            }
+
+            let updates = match bot.get_updates(offset).await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    eprintln!("Telegram polling failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                offset = update.update_id + 1;
+
+                let Some(message) = update.message else {
+                    continue;
+                };
+                let Some(command) = message.text.as_deref().and_then(Command::parse) else {
+                    continue;
+                };
+
+                let reply = handle_command(&app_state, message.chat.id, command).await;
+                if let Err(e) = bot.send_message(message.chat.id, &reply).await {
+                    eprintln!("Failed to reply to chat {}: {}", message.chat.id, e);
+                }
+            }
+        }
+    });
+}
+
+pub async fn send_group_alert(app_state: AppState, group_id: i64) {
+    let Some(bot) = app_state.telegram.clone() else {
+        return;
+    };
+
+    let subscribers = match app_state.db.get_group_subscribers(group_id).await {
+        Ok(subscribers) => subscribers,
+        Err(e) => {
+            eprintln!("Failed to load subscribers of group {}: {}", group_id, e);
+            return;
+        }
+    };
+    if subscribers.is_empty() {
+        return;
+    }
+
+    let group = group_name(&app_state, group_id).await;
+    let mut lines = vec![format!("Расписание группы {} обновлено!", group)];
+    let today = Local::now().date_naive();
+    if let Ok(changes) = app_state.db.get_schedule_changes(group_id).await {
+        let mut dates: Vec<_> = changes.iter().map(|c| c.date).collect();
+        dates.sort();
+        dates.dedup();
+        for date in dates {
+            let line = match (date - today).num_days() {
+                0 => format!("Есть изменения на сегодня — /today {}", group),
+                1 => format!("Есть изменения на завтра — /tomorrow {}", group),
+                _ => format!("Есть изменения на {}", date.format("%d.%m.%Y")),
+            };
+            lines.push(line);
+        }
+    }
+    let text = lines.join("\n");
+
+    for subscriber in subscribers {
+        if let Err(e) = bot.send_message(subscriber.chat_id, &text).await {
+            eprintln!("Failed to alert chat {}: {}", subscriber.chat_id, e);
+        }
+    }
+}
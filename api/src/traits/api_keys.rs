@@ -0,0 +1,175 @@
+use crate::{
+    auth::Permission,
+    db::DBState,
+    models::{AddApiKeyRequest, ApiKey},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, mysql::MySqlQueryResult};
+
+#[derive(FromRow)]
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    key_prefix: String,
+    scopes: String,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    created_by: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+impl ApiKeyRow {
+    fn into_api_key(self, group_ids: Vec<i64>) -> ApiKey {
+        ApiKey {
+            id: self.id,
+            name: self.name,
+            key_prefix: self.key_prefix,
+            scopes: self
+                .scopes
+                .split(',')
+                .filter_map(|s| s.parse::<Permission>().ok())
+                // Keys issued before scopes were restricted lose the admin ones.
+                .filter(Permission::is_key_scope)
+                .collect(),
+            group_ids,
+            expires_at: self.expires_at,
+            last_used_at: self.last_used_at,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        }
+    }
+}
+
+const API_KEY_COLUMNS: &str =
+    "id, name, key_prefix, scopes, expires_at, last_used_at, created_by, created_at";
+
+#[async_trait]
+pub trait ApiKeys {
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error>;
+    async fn get_api_key_by_id(&self, id: i64) -> Result<ApiKey, sqlx::Error>;
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, sqlx::Error>;
+    async fn add_api_key(
+        &self,
+        request: &AddApiKeyRequest,
+        key_prefix: &str,
+        key_hash: &str,
+        created_by: Option<i64>,
+    ) -> Result<ApiKey, sqlx::Error>;
+    async fn delete_api_key(&self, id: i64) -> Result<i16, sqlx::Error>;
+    async fn touch_api_key(&self, id: i64) -> Result<(), sqlx::Error>;
+}
+
+impl DBState {
+    async fn api_key_groups(&self, api_key_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT group_id FROM api_key_groups WHERE api_key_id=?")
+            .bind(api_key_id)
+            .fetch_all(&self.db)
+            .await
+    }
+}
+
+#[async_trait]
+impl ApiKeys for DBState {
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, ApiKeyRow>(&format!(
+            "SELECT {} FROM api_keys ORDER BY id",
+            API_KEY_COLUMNS
+        ))
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut keys = Vec::with_capacity(rows.len());
+        for row in rows {
+            let group_ids = self.api_key_groups(row.id).await?;
+            keys.push(row.into_api_key(group_ids));
+        }
+
+        Ok(keys)
+    }
+
+    async fn get_api_key_by_id(&self, id: i64) -> Result<ApiKey, sqlx::Error> {
+        let row = sqlx::query_as::<_, ApiKeyRow>(&format!(
+            "SELECT {} FROM api_keys WHERE id=?",
+            API_KEY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_one(&self.db)
+        .await?;
+
+        let group_ids = self.api_key_groups(row.id).await?;
+        Ok(row.into_api_key(group_ids))
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, sqlx::Error> {
+        let row = sqlx::query_as::<_, ApiKeyRow>(&format!(
+            "SELECT {} FROM api_keys WHERE key_hash=?",
+            API_KEY_COLUMNS
+        ))
+        .bind(key_hash)
+        .fetch_one(&self.db)
+        .await?;
+
+        let group_ids = self.api_key_groups(row.id).await?;
+        Ok(row.into_api_key(group_ids))
+    }
+
+    async fn add_api_key(
+        &self,
+        request: &AddApiKeyRequest,
+        key_prefix: &str,
+        key_hash: &str,
+        created_by: Option<i64>,
+    ) -> Result<ApiKey, sqlx::Error> {
+        let scopes: Vec<_> = request.scopes.iter().map(|s| s.as_str()).collect();
+        let mut tx = self.db.begin().await?;
+
+        let result: MySqlQueryResult = sqlx::query(
+            "INSERT INTO api_keys (name, key_prefix, key_hash, scopes, expires_at, created_by) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&request.name)
+        .bind(key_prefix)
+        .bind(key_hash)
+        .bind(scopes.join(","))
+        .bind(request.expires_at)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?;
+        let id = result.last_insert_id() as i64;
+
+        for group_id in &request.group_ids {
+            sqlx::query("INSERT INTO api_key_groups (api_key_id, group_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(group_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        self.get_api_key_by_id(id).await
+    }
+
+    async fn delete_api_key(&self, id: i64) -> Result<i16, sqlx::Error> {
+        let result: MySqlQueryResult = sqlx::query("DELETE FROM api_keys WHERE id=?")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        Ok(200)
+    }
+
+    async fn touch_api_key(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE api_keys SET last_used_at=NOW() WHERE id=? AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL 1 MINUTE)",
+        )
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    db::DBState,
+    models::{ActorType, AuditEntry, AuditLogQuery},
+    services::audit::{AuditContext, AuditEvent},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, MySql, QueryBuilder};
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+#[derive(FromRow)]
+struct AuditEntryRow {
+    id: i64,
+    #[sqlx(try_from = "String")]
+    actor_type: ActorType,
+    actor_id: Option<i64>,
+    actor_name: Option<String>,
+    impersonator_id: Option<i64>,
+    action: String,
+    entity: String,
+    entity_id: Option<String>,
+    before_data: Option<String>,
+    after_data: Option<String>,
+    request_id: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditEntryRow> for AuditEntry {
+    fn from(row: AuditEntryRow) -> Self {
+        let parse = |data: Option<String>| data.and_then(|d| serde_json::from_str(&d).ok());
+
+        Self {
+            id: row.id,
+            actor_type: row.actor_type,
+            actor_id: row.actor_id,
+            actor_name: row.actor_name,
+            impersonator_id: row.impersonator_id,
+            action: row.action,
+            entity: row.entity,
+            entity_id: row.entity_id,
+            before: parse(row.before_data),
+            after: parse(row.after_data),
+            request_id: row.request_id,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[async_trait]
+pub trait AuditLog {
+    async fn add_audit_entry(
+        &self,
+        context: &AuditContext,
+        event: &AuditEvent,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_audit_entries(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEntry>, sqlx::Error>;
+}
+
+#[async_trait]
+impl AuditLog for DBState {
+    async fn add_audit_entry(
+        &self,
+        context: &AuditContext,
+        event: &AuditEvent,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO audit_log (actor_type, actor_id, actor_name, impersonator_id, action, entity, entity_id, before_data, after_data, request_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(context.actor_type.as_str())
+        .bind(context.actor_id)
+        .bind(&context.actor_name)
+        .bind(context.impersonator_id)
+        .bind(event.action)
+        .bind(event.entity)
+        .bind(&event.entity_id)
+        .bind(event.before.as_ref().map(|v| v.to_string()))
+        .bind(event.after.as_ref().map(|v| v.to_string()))
+        .bind(&context.request_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEntry>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM audit_log WHERE 1=1");
+
+        if let Some(entity) = &query.entity {
+            builder.push(" AND entity=").push_bind(entity);
+        }
+        if let Some(entity_id) = &query.entity_id {
+            builder.push(" AND entity_id=").push_bind(entity_id);
+        }
+        if let Some(actor_type) = query.actor_type {
+            builder
+                .push(" AND actor_type=")
+                .push_bind(actor_type.as_str());
+        }
+        if let Some(actor_id) = query.actor_id {
+            builder.push(" AND actor_id=").push_bind(actor_id);
+        }
+        if let Some(impersonator_id) = query.impersonator_id {
+            builder
+                .push(" AND impersonator_id=")
+                .push_bind(impersonator_id);
+        }
+        if let Some(action) = &query.action {
+            builder.push(" AND action=").push_bind(action);
+        }
+        if let Some(from) = query.from {
+            builder.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = query.to {
+            builder
+                .push(" AND created_at < DATE_ADD(")
+                .push_bind(to)
+                .push(", INTERVAL 1 DAY)");
+        }
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        builder
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(limit);
+
+        let rows = builder
+            .build_query_as::<AuditEntryRow>()
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(rows.into_iter().map(AuditEntry::from).collect())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{db::DBState, models::GroupGrant};
+use async_trait::async_trait;
+use sqlx::mysql::MySqlQueryResult;
+
+#[async_trait]
+pub trait GroupGrants {
+    async fn get_group_grants(&self, teacher_id: i64) -> Result<Vec<GroupGrant>, sqlx::Error>;
+    async fn add_group_grants(
+        &self,
+        teacher_id: i64,
+        group_ids: &[i64],
+    ) -> Result<Vec<GroupGrant>, sqlx::Error>;
+    async fn delete_group_grant(&self, teacher_id: i64, group_id: i64) -> Result<i16, sqlx::Error>;
+}
+
+#[async_trait]
+impl GroupGrants for DBState {
+    async fn get_group_grants(&self, teacher_id: i64) -> Result<Vec<GroupGrant>, sqlx::Error> {
+        let grants = sqlx::query_as::<_, GroupGrant>(
+            "SELECT teacher_id, group_id FROM group_grants WHERE teacher_id=?",
+        )
+        .bind(teacher_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(grants)
+    }
+
+    async fn add_group_grants(
+        &self,
+        teacher_id: i64,
+        group_ids: &[i64],
+    ) -> Result<Vec<GroupGrant>, sqlx::Error> {
+        for group_id in group_ids {
+            sqlx::query(
+                "INSERT INTO group_grants (teacher_id, group_id) VALUES (?, ?) ON DUPLICATE KEY UPDATE teacher_id=teacher_id",
+            )
+            .bind(teacher_id)
+            .bind(group_id)
+            .execute(&self.db)
+            .await?;
+        }
+
+        self.get_group_grants(teacher_id).await
+    }
+
+    async fn delete_group_grant(&self, teacher_id: i64, group_id: i64) -> Result<i16, sqlx::Error> {
+        let result: MySqlQueryResult =
+            sqlx::query("DELETE FROM group_grants WHERE teacher_id=? AND group_id=?")
+                .bind(teacher_id)
+                .bind(group_id)
+                .execute(&self.db)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        Ok(200)
+    }
+}
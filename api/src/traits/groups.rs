@@ -1,17 +1,40 @@
-use crate::{db::DBState, models::Group};
+use crate::{
+    db::{DBState, push_id_list},
+    models::{
+        BundleChange, BundlePair, BundleTeacherLink, Group, GroupBundle, GroupFilter, Subject,
+    },
+    services::listing::{ListQuery, Paged},
+};
 use async_trait::async_trait;
-use sqlx::mysql::MySqlQueryResult;
+use chrono::NaiveDate;
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait Groups {
     async fn add_group(&self, name: &str, shift: i8) -> Result<Group, sqlx::Error>;
     async fn get_groups(&self) -> Result<Vec<Group>, sqlx::Error>;
+    async fn list_groups(
+        &self,
+        query: &ListQuery<GroupFilter>,
+    ) -> Result<Paged<Group>, sqlx::Error>;
     async fn get_group_by_id(&self, id: i64) -> Result<Group, sqlx::Error>;
+    async fn get_groups_by_ids(&self, ids: &[i64]) -> Result<Vec<Group>, sqlx::Error>;
+    /// Loads a group with its subjects, links, pairs held on `weekdays` and changes dated
+    /// between `from` and `to`, each with subject and teacher names joined in.
+    async fn get_group_bundle(
+        &self,
+        id: i64,
+        weekdays: &[i8],
+        from: NaiveDate,
+        to: Option<NaiveDate>,
+    ) -> Result<GroupBundle, sqlx::Error>;
+    /// Edits the group if it is still at `version`, any version when it is `None`.
     async fn update_group(
         &self,
         id: i64,
         new_name: &str,
         new_shift: i8,
+        version: Option<i32>,
     ) -> Result<Group, sqlx::Error>;
     async fn delete_group(&self, id: i64) -> Result<u16, sqlx::Error>;
 }
@@ -44,6 +67,17 @@ impl Groups for DBState {
         Ok(groups)
     }
 
+    async fn list_groups(
+        &self,
+        query: &ListQuery<GroupFilter>,
+    ) -> Result<Paged<Group>, sqlx::Error> {
+        query
+            .fetch(&self.db, "*", |builder| {
+                builder.push("FROM groups WHERE 1=1");
+            })
+            .await
+    }
+
     async fn get_group_by_id(&self, id: i64) -> Result<Group, sqlx::Error> {
         let group = sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id=?")
             .bind(id)
@@ -53,18 +87,91 @@ impl Groups for DBState {
         Ok(group)
     }
 
+    async fn get_groups_by_ids(&self, ids: &[i64]) -> Result<Vec<Group>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM groups WHERE id IN ");
+        push_id_list(&mut builder, ids);
+        builder.build_query_as::<Group>().fetch_all(&self.db).await
+    }
+
+    async fn get_group_bundle(
+        &self,
+        id: i64,
+        weekdays: &[i8],
+        from: NaiveDate,
+        to: Option<NaiveDate>,
+    ) -> Result<GroupBundle, sqlx::Error> {
+        let group = self.get_group_by_id(id).await?;
+
+        let subjects =
+            sqlx::query_as::<_, Subject>("SELECT * FROM subjects WHERE group_id=? ORDER BY name")
+                .bind(id)
+                .fetch_all(&self.db)
+                .await?;
+
+        let teacher_links = sqlx::query_as::<_, BundleTeacherLink>(
+            "SELECT l.subject_id, s.name AS subject_name, l.teacher_id, t.full_name AS teacher_name FROM teacher_links l JOIN subjects s ON s.id = l.subject_id JOIN teachers t ON t.id = l.teacher_id WHERE l.group_id=? ORDER BY s.name",
+        )
+        .bind(id)
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut pairs: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT p.id, p.weekday, p.pair_number, p.subgroup, p.start_time, p.end_time, p.cabinet, p.subject_id, s.name AS subject_name, p.teacher_id, t.full_name AS teacher_name FROM schedule p JOIN subjects s ON s.id = p.subject_id JOIN teachers t ON t.id = p.teacher_id WHERE p.group_id=",
+        );
+        pairs.push_bind(id).push(" AND p.weekday IN (");
+        let mut separated = pairs.separated(", ");
+        for weekday in weekdays {
+            separated.push_bind(*weekday);
+        }
+        pairs.push(") ORDER BY p.weekday, p.pair_number, p.subgroup");
+        let pairs = pairs
+            .build_query_as::<BundlePair>()
+            .fetch_all(&self.db)
+            .await?;
+
+        let mut changes: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT c.schedule_id, c.date, p.pair_number, c.is_canceled, c.new_subject_id, s.name AS new_subject_name, c.new_teacher_id, t.full_name AS new_teacher_name, c.new_start_time, c.new_end_time, c.cabinet FROM schedule_changes c JOIN schedule p ON p.id = c.schedule_id LEFT JOIN subjects s ON s.id = c.new_subject_id LEFT JOIN teachers t ON t.id = c.new_teacher_id WHERE c.group_id=",
+        );
+        changes
+            .push_bind(id)
+            .push(" AND c.date >= ")
+            .push_bind(from);
+        if let Some(to) = to {
+            changes.push(" AND c.date <= ").push_bind(to);
+        }
+        changes.push(" ORDER BY c.date, p.pair_number");
+        let changes = changes
+            .build_query_as::<BundleChange>()
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(GroupBundle {
+            group,
+            subjects,
+            teacher_links,
+            pairs,
+            changes,
+        })
+    }
+
     async fn update_group(
         &self,
         id: i64,
         new_name: &str,
         new_shift: i8,
+        version: Option<i32>,
     ) -> Result<Group, sqlx::Error> {
-        let result: MySqlQueryResult = sqlx::query("UPDATE groups SET name=?, shift=? WHERE id=?")
-            .bind(new_name)
-            .bind(new_shift)
-            .bind(id)
-            .execute(&self.db)
-            .await?;
+        let result: MySqlQueryResult = sqlx::query(
+            "UPDATE groups SET name=?, shift=?, version=version+1 WHERE id=? AND (? IS NULL OR version=?)",
+        )
+        .bind(new_name)
+        .bind(new_shift)
+        .bind(id)
+        .bind(version)
+        .bind(version)
+        .execute(&self.db)
+        .await?;
 
         if result.rows_affected() == 0 {
             return Err(sqlx::Error::RowNotFound);
@@ -1,13 +1,29 @@
+pub mod api_keys;
+pub mod audit_log;
+pub mod group_grants;
 pub mod groups;
+pub mod notification_preferences;
+pub mod recovery_codes;
 pub mod schedule;
 pub mod schedule_changes;
+pub mod students;
 pub mod subjects;
+pub mod sync;
 pub mod teacher_links;
 pub mod teachers;
+pub mod telegram_subscriptions;
 
+pub use api_keys::ApiKeys;
+pub use audit_log::AuditLog;
+pub use group_grants::GroupGrants;
 pub use groups::Groups;
+pub use notification_preferences::NotificationPreferences;
+pub use recovery_codes::RecoveryCodes;
 pub use schedule::Schedules;
 pub use schedule_changes::ScheduleChanges;
+pub use students::Students;
 pub use subjects::Subjects;
+pub use sync::DeltaSync;
 pub use teacher_links::TeacherLinks;
 pub use teachers::Teachers;
+pub use telegram_subscriptions::TelegramSubscriptions;
@@ -0,0 +1,53 @@
+use crate::{
+    db::DBState,
+    models::{EditNotificationPreferenceRequest, NotificationPreference},
+};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait NotificationPreferences {
+    async fn get_notification_preference(
+        &self,
+        teacher_id: i64,
+    ) -> Result<NotificationPreference, sqlx::Error>;
+    async fn update_notification_preference(
+        &self,
+        teacher_id: i64,
+        preference: &EditNotificationPreferenceRequest,
+    ) -> Result<NotificationPreference, sqlx::Error>;
+}
+
+#[async_trait]
+impl NotificationPreferences for DBState {
+    async fn get_notification_preference(
+        &self,
+        teacher_id: i64,
+    ) -> Result<NotificationPreference, sqlx::Error> {
+        let preference = sqlx::query_as::<_, NotificationPreference>(
+            "SELECT * FROM notification_preferences WHERE teacher_id=?",
+        )
+        .bind(teacher_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(preference.unwrap_or_else(|| NotificationPreference::default_for(teacher_id)))
+    }
+
+    async fn update_notification_preference(
+        &self,
+        teacher_id: i64,
+        preference: &EditNotificationPreferenceRequest,
+    ) -> Result<NotificationPreference, sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO notification_preferences (teacher_id, push_changes, email_changes, email_digest) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE push_changes=VALUES(push_changes), email_changes=VALUES(email_changes), email_digest=VALUES(email_digest)",
+        )
+        .bind(teacher_id)
+        .bind(preference.push_changes)
+        .bind(preference.email_changes)
+        .bind(preference.email_digest)
+        .execute(&self.db)
+        .await?;
+
+        self.get_notification_preference(teacher_id).await
+    }
+}
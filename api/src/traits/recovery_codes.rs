@@ -0,0 +1,74 @@
+use crate::{db::DBState, services::auth::verify_password};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait RecoveryCodes {
+    async fn replace_recovery_codes(
+        &self,
+        teacher_id: i64,
+        code_hashes: &[String],
+    ) -> Result<(), sqlx::Error>;
+    async fn delete_recovery_codes(&self, teacher_id: i64) -> Result<(), sqlx::Error>;
+    async fn use_recovery_code(&self, teacher_id: i64, code: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+impl RecoveryCodes for DBState {
+    async fn replace_recovery_codes(
+        &self,
+        teacher_id: i64,
+        code_hashes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("DELETE FROM recovery_codes WHERE teacher_id=?")
+            .bind(teacher_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO recovery_codes (teacher_id, code_hash) VALUES (?, ?)")
+                .bind(teacher_id)
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_recovery_codes(&self, teacher_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM recovery_codes WHERE teacher_id=?")
+            .bind(teacher_id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn use_recovery_code(&self, teacher_id: i64, code: &str) -> Result<bool, sqlx::Error> {
+        let codes: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, code_hash FROM recovery_codes WHERE teacher_id=? AND used_at IS NULL",
+        )
+        .bind(teacher_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        let code = code.trim().to_uppercase();
+        let Some((id, _)) = codes
+            .into_iter()
+            .find(|(_, code_hash)| verify_password(&code, code_hash))
+        else {
+            return Ok(false);
+        };
+
+        let result =
+            sqlx::query("UPDATE recovery_codes SET used_at=NOW() WHERE id=? AND used_at IS NULL")
+                .bind(id)
+                .execute(&self.db)
+                .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -1,18 +1,35 @@
 use crate::{
-    db::DBState,
-    models::{AddPair, AddScheduleRequest, Pair, Schedule, ScheduleRow},
+    db::{DBState, push_id_list},
+    models::{AddScheduleRequest, Pair, Schedule, ScheduleRow},
 };
 use async_trait::async_trait;
-use chrono::NaiveTime;
-use sqlx::mysql::MySqlQueryResult;
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait Schedules {
     async fn get_schedule(&self, group_id: i64) -> Result<Vec<Schedule>, sqlx::Error>;
     async fn add_pairs(&self, schedule: AddScheduleRequest) -> Result<Vec<Schedule>, sqlx::Error>;
+    /// Edits every pair in one transaction. Pairs sent with a version are only written
+    /// while still at it, a pair that isn't fails the whole edit.
     async fn edit_pairs(&self, new_schedule: Schedule) -> Result<Vec<Schedule>, sqlx::Error>;
     async fn delete_day(&self, group_id: i64, weekday: i8) -> Result<i64, sqlx::Error>;
     async fn delete_pair(&self, id: i64) -> Result<i64, sqlx::Error>;
+    async fn get_pair(&self, id: i64) -> Result<ScheduleRow, sqlx::Error>;
+    async fn get_teacher_pairs(&self, teacher_id: i64) -> Result<Vec<ScheduleRow>, sqlx::Error>;
+    async fn get_pairs_by_ids(&self, ids: &[i64]) -> Result<Vec<ScheduleRow>, sqlx::Error>;
+    async fn get_pairs_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error>;
+    async fn get_pairs_by_teacher_ids(
+        &self,
+        teacher_ids: &[i64],
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error>;
+    async fn get_subgroup_pairs(
+        &self,
+        group_id: i64,
+        subgroup: Option<i8>,
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error>;
 }
 
 #[async_trait]
@@ -37,10 +54,12 @@ impl Schedules for DBState {
                     cabinet: row.cabinet,
                     end_time: row.end_time,
                     start_time: row.start_time,
+                    subgroup: row.subgroup,
+                    version: Some(row.version),
                 });
             } else {
                 schedules.push(Schedule {
-                    group_id: group_id,
+                    group_id,
                     weekday: row.weekday,
                     pairs: vec![Pair {
                         id: row.id,
@@ -50,6 +69,8 @@ impl Schedules for DBState {
                         start_time: row.start_time,
                         end_time: row.end_time,
                         cabinet: row.cabinet,
+                        subgroup: row.subgroup,
+                        version: Some(row.version),
                     }],
                 })
             }
@@ -60,7 +81,7 @@ impl Schedules for DBState {
 
     async fn add_pairs(&self, schedule: AddScheduleRequest) -> Result<Vec<Schedule>, sqlx::Error> {
         for pair in &schedule.pairs {
-            sqlx::query("INSERT INTO schedule(pair_number, group_id, subject_id, teacher_id, weekday, start_time, end_time, cabinet) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            sqlx::query("INSERT INTO schedule(pair_number, group_id, subject_id, teacher_id, weekday, start_time, end_time, cabinet, subgroup) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
                 .bind(pair.pair_number)
                 .bind(schedule.group_id)
                 .bind(pair.subject_id)
@@ -69,6 +90,7 @@ impl Schedules for DBState {
                 .bind(pair.start_time)
                 .bind(pair.end_time)
                 .bind(&pair.cabinet)
+                .bind(pair.subgroup)
                 .execute(&self.db)
                 .await?;
         }
@@ -77,8 +99,9 @@ impl Schedules for DBState {
     }
 
     async fn edit_pairs(&self, new_schedule: Schedule) -> Result<Vec<Schedule>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
         for pair in &new_schedule.pairs {
-            let result: MySqlQueryResult = sqlx::query("UPDATE schedule SET weekday=?, pair_number=?, subject_id=?, teacher_id=?, start_time=?, end_time=?, cabinet=? WHERE id=?")
+            let result: MySqlQueryResult = sqlx::query("UPDATE schedule SET weekday=?, pair_number=?, subject_id=?, teacher_id=?, start_time=?, end_time=?, cabinet=?, subgroup=?, version=version+1 WHERE id=? AND (? IS NULL OR version=?)")
                 .bind(new_schedule.weekday)
                 .bind(pair.pair_number)
                 .bind(pair.subject_id)
@@ -86,13 +109,17 @@ impl Schedules for DBState {
                 .bind(pair.start_time)
                 .bind(pair.end_time)
                 .bind(&pair.cabinet)
+                .bind(pair.subgroup)
                 .bind(pair.id)
-                .execute(&self.db)
+                .bind(pair.version)
+                .bind(pair.version)
+                .execute(&mut *tx)
                 .await?;
             if result.rows_affected() == 0 {
                 return Err(sqlx::Error::RowNotFound);
             }
         }
+        tx.commit().await?;
 
         Ok(self.get_schedule(new_schedule.group_id).await?)
     }
@@ -122,4 +149,80 @@ impl Schedules for DBState {
 
         Ok(200)
     }
+
+    async fn get_pair(&self, id: i64) -> Result<ScheduleRow, sqlx::Error> {
+        let pair = sqlx::query_as::<_, ScheduleRow>("SELECT * FROM schedule WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(pair)
+    }
+
+    async fn get_teacher_pairs(&self, teacher_id: i64) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+        let pairs = sqlx::query_as::<_, ScheduleRow>(
+            "SELECT * FROM schedule WHERE teacher_id=? ORDER BY weekday ASC, pair_number ASC",
+        )
+        .bind(teacher_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(pairs)
+    }
+
+    async fn get_pairs_by_ids(&self, ids: &[i64]) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM schedule WHERE id IN ");
+        push_id_list(&mut builder, ids);
+        builder
+            .build_query_as::<ScheduleRow>()
+            .fetch_all(&self.db)
+            .await
+    }
+
+    async fn get_pairs_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM schedule WHERE group_id IN ");
+        push_id_list(&mut builder, group_ids);
+        builder.push(" ORDER BY weekday ASC, pair_number ASC");
+        builder
+            .build_query_as::<ScheduleRow>()
+            .fetch_all(&self.db)
+            .await
+    }
+
+    async fn get_pairs_by_teacher_ids(
+        &self,
+        teacher_ids: &[i64],
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM schedule WHERE teacher_id IN ");
+        push_id_list(&mut builder, teacher_ids);
+        builder.push(" ORDER BY weekday ASC, pair_number ASC");
+        builder
+            .build_query_as::<ScheduleRow>()
+            .fetch_all(&self.db)
+            .await
+    }
+
+    /// Pairs of the group a subgroup attends; without a subgroup every pair is returned.
+    async fn get_subgroup_pairs(
+        &self,
+        group_id: i64,
+        subgroup: Option<i8>,
+    ) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+        let pairs = sqlx::query_as::<_, ScheduleRow>(
+            "SELECT * FROM schedule WHERE group_id=? AND (subgroup IS NULL OR ? IS NULL OR subgroup=?) ORDER BY weekday ASC, pair_number ASC",
+        )
+        .bind(group_id)
+        .bind(subgroup)
+        .bind(subgroup)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(pairs)
+    }
 }
@@ -1,17 +1,28 @@
-use crate::{db::DBState, models::ScheduleChange};
+use crate::{
+    db::{DBState, push_id_list},
+    models::{ChangeFilter, ScheduleChange},
+    services::listing::{ListQuery, Paged},
+};
 use async_trait::async_trait;
-use chrono::Local;
-use sqlx::mysql::MySqlQueryResult;
+use chrono::{Local, NaiveDate};
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait ScheduleChanges {
     async fn get_schedule_changes(&self, group_id: i64)
     -> Result<Vec<ScheduleChange>, sqlx::Error>;
+    async fn list_schedule_changes(
+        &self,
+        group_id: i64,
+        query: &ListQuery<ChangeFilter>,
+    ) -> Result<Paged<ScheduleChange>, sqlx::Error>;
     async fn add_schedule_changes(
         &self,
         schedule_changes: Vec<ScheduleChange>,
     ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
     async fn delete_schedule_changes(&self, schedule_ids: Vec<i64>) -> Result<i64, sqlx::Error>;
+    /// Edits the change if it is still at the version it carries, any version when it
+    /// has none. Returns the stored row.
     async fn edit_schedule_changes(
         &self,
         new_schedule_change: ScheduleChange,
@@ -20,6 +31,26 @@ pub trait ScheduleChanges {
         &self,
         schedule_ids: Vec<i64>,
     ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
+    async fn get_changes_by_date(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
+    /// Changes of the given pairs dated `from` or later; ids without one are left out.
+    async fn get_changes_by_schedule_ids(
+        &self,
+        schedule_ids: &[i64],
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
+    async fn get_changes_by_group_ids(
+        &self,
+        group_ids: &[i64],
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
+    async fn get_teacher_changes(
+        &self,
+        teacher_id: i64,
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error>;
 }
 
 #[async_trait]
@@ -49,11 +80,32 @@ impl ScheduleChanges for DBState {
         Ok(schedule_changes)
     }
 
+    async fn list_schedule_changes(
+        &self,
+        group_id: i64,
+        query: &ListQuery<ChangeFilter>,
+    ) -> Result<Paged<ScheduleChange>, sqlx::Error> {
+        // Past changes are dropped on read, the same as in `get_schedule_changes`.
+        sqlx::query("DELETE FROM schedule_changes WHERE group_id=? AND date < ?")
+            .bind(group_id)
+            .bind(Local::now().date_naive())
+            .execute(&self.db)
+            .await?;
+
+        query
+            .fetch(&self.db, "*", |builder| {
+                builder
+                    .push("FROM schedule_changes WHERE group_id=")
+                    .push_bind(group_id);
+            })
+            .await
+    }
+
     async fn add_schedule_changes(
         &self,
-        schedule_changes: Vec<ScheduleChange>,
+        mut schedule_changes: Vec<ScheduleChange>,
     ) -> Result<Vec<ScheduleChange>, sqlx::Error> {
-        for change in &schedule_changes {
+        for change in &mut schedule_changes {
             sqlx::query("INSERT INTO schedule_changes(schedule_id, group_id, new_subject_id, new_teacher_id, date, new_start_time, new_end_time, cabinet, is_canceled) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
                 .bind(change.schedule_id)
                 .bind(change.group_id)
@@ -63,9 +115,11 @@ impl ScheduleChanges for DBState {
                 .bind(change.new_start_time)
                 .bind(change.new_end_time)
                 .bind(&change.cabinet)
-                .bind(&change.is_canceled)
+                .bind(change.is_canceled)
                 .execute(&self.db)
                 .await?;
+            // New rows start at the column default.
+            change.version = Some(1);
         }
         Ok(schedule_changes)
     }
@@ -89,7 +143,7 @@ impl ScheduleChanges for DBState {
         new_schedule_change: ScheduleChange,
     ) -> Result<ScheduleChange, sqlx::Error> {
         let result: MySqlQueryResult =
-            sqlx::query("UPDATE schedule_changes SET group_id=?, new_subject_id=?, new_teacher_id=?, date=?, new_start_time=?, new_end_time=?, cabinet=?, is_canceled=? WHERE schedule_id=?")
+            sqlx::query("UPDATE schedule_changes SET group_id=?, new_subject_id=?, new_teacher_id=?, date=?, new_start_time=?, new_end_time=?, cabinet=?, is_canceled=?, version=version+1 WHERE schedule_id=? AND (? IS NULL OR version=?)")
                 .bind(new_schedule_change.group_id)
                 .bind(new_schedule_change.new_subject_id)
                 .bind(new_schedule_change.new_teacher_id)
@@ -99,13 +153,22 @@ impl ScheduleChanges for DBState {
                 .bind(&new_schedule_change.cabinet)
                 .bind(new_schedule_change.is_canceled)
                 .bind(new_schedule_change.schedule_id)
+                .bind(new_schedule_change.version)
+                .bind(new_schedule_change.version)
                 .execute(&self.db)
                 .await?;
         if result.rows_affected() == 0 {
             return Err(sqlx::Error::RowNotFound);
         }
 
-        Ok(new_schedule_change)
+        let change = sqlx::query_as::<_, ScheduleChange>(
+            "SELECT * FROM schedule_changes WHERE schedule_id=?",
+        )
+        .bind(new_schedule_change.schedule_id)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(change)
     }
 
     async fn get_changes_by_ids(
@@ -127,4 +190,67 @@ impl ScheduleChanges for DBState {
 
         Ok(changes)
     }
+
+    async fn get_changes_by_date(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error> {
+        let changes =
+            sqlx::query_as::<_, ScheduleChange>("SELECT * FROM schedule_changes WHERE date=?")
+                .bind(date)
+                .fetch_all(&self.db)
+                .await?;
+
+        Ok(changes)
+    }
+
+    async fn get_changes_by_schedule_ids(
+        &self,
+        schedule_ids: &[i64],
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM schedule_changes WHERE schedule_id IN ");
+        push_id_list(&mut builder, schedule_ids);
+        builder.push(" AND date >= ").push_bind(from);
+        builder
+            .build_query_as::<ScheduleChange>()
+            .fetch_all(&self.db)
+            .await
+    }
+
+    async fn get_changes_by_group_ids(
+        &self,
+        group_ids: &[i64],
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT * FROM schedule_changes WHERE group_id IN ");
+        push_id_list(&mut builder, group_ids);
+        builder
+            .push(" AND date >= ")
+            .push_bind(from)
+            .push(" ORDER BY date ASC, new_start_time ASC");
+        builder
+            .build_query_as::<ScheduleChange>()
+            .fetch_all(&self.db)
+            .await
+    }
+
+    async fn get_teacher_changes(
+        &self,
+        teacher_id: i64,
+        from: NaiveDate,
+    ) -> Result<Vec<ScheduleChange>, sqlx::Error> {
+        let changes = sqlx::query_as::<_, ScheduleChange>(
+            "SELECT sc.* FROM schedule_changes sc JOIN schedule s ON s.id = sc.schedule_id WHERE (sc.new_teacher_id=? OR s.teacher_id=?) AND sc.date >= ? ORDER BY sc.date ASC, s.pair_number ASC",
+        )
+        .bind(teacher_id)
+        .bind(teacher_id)
+        .bind(from)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(changes)
+    }
 }
@@ -0,0 +1,137 @@
+use crate::{
+    db::DBState,
+    models::{EditStudentRequest, NewStudent, Student},
+};
+use async_trait::async_trait;
+use sqlx::mysql::MySqlQueryResult;
+
+#[async_trait]
+pub trait Students {
+    async fn get_students(&self, group_id: i64) -> Result<Vec<Student>, sqlx::Error>;
+    async fn get_student_by_id(&self, id: i64) -> Result<Student, sqlx::Error>;
+    async fn get_student_by_login(&self, login: &str) -> Result<Student, sqlx::Error>;
+    /// Inserts the whole batch in one transaction, `password_hashes` lines up with `students`.
+    async fn add_students(
+        &self,
+        group_id: i64,
+        students: &[NewStudent],
+        password_hashes: &[Option<String>],
+    ) -> Result<Vec<Student>, sqlx::Error>;
+    async fn edit_student(&self, student: &EditStudentRequest) -> Result<Student, sqlx::Error>;
+    async fn update_student_hash(
+        &self,
+        id: i64,
+        password_hash: &str,
+        must_change_password: bool,
+    ) -> Result<Student, sqlx::Error>;
+    async fn delete_student(&self, id: i64) -> Result<i64, sqlx::Error>;
+}
+
+#[async_trait]
+impl Students for DBState {
+    async fn get_students(&self, group_id: i64) -> Result<Vec<Student>, sqlx::Error> {
+        let students = sqlx::query_as::<_, Student>(
+            "SELECT * FROM students WHERE group_id=? ORDER BY subgroup ASC, full_name ASC",
+        )
+        .bind(group_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(students)
+    }
+
+    async fn get_student_by_id(&self, id: i64) -> Result<Student, sqlx::Error> {
+        let student = sqlx::query_as::<_, Student>("SELECT * FROM students WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(student)
+    }
+
+    async fn get_student_by_login(&self, login: &str) -> Result<Student, sqlx::Error> {
+        let student = sqlx::query_as::<_, Student>("SELECT * FROM students WHERE login=?")
+            .bind(login)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(student)
+    }
+
+    async fn add_students(
+        &self,
+        group_id: i64,
+        students: &[NewStudent],
+        password_hashes: &[Option<String>],
+    ) -> Result<Vec<Student>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        let mut ids = Vec::with_capacity(students.len());
+        for (student, password_hash) in students.iter().zip(password_hashes) {
+            let result: MySqlQueryResult = sqlx::query(
+                "INSERT INTO students (group_id, subgroup, full_name, login, password_hash) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(group_id)
+            .bind(student.subgroup)
+            .bind(&student.full_name)
+            .bind(&student.login)
+            .bind(password_hash)
+            .execute(&mut *tx)
+            .await?;
+            ids.push(result.last_insert_id() as i64);
+        }
+
+        let mut added = Vec::with_capacity(ids.len());
+        for id in ids {
+            let student = sqlx::query_as::<_, Student>("SELECT * FROM students WHERE id=?")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            added.push(student);
+        }
+
+        tx.commit().await?;
+        Ok(added)
+    }
+
+    async fn edit_student(&self, student: &EditStudentRequest) -> Result<Student, sqlx::Error> {
+        sqlx::query("UPDATE students SET group_id=?, subgroup=?, full_name=?, login=? WHERE id=?")
+            .bind(student.group_id)
+            .bind(student.subgroup)
+            .bind(&student.full_name)
+            .bind(&student.login)
+            .bind(student.id)
+            .execute(&self.db)
+            .await?;
+
+        self.get_student_by_id(student.id).await
+    }
+
+    async fn update_student_hash(
+        &self,
+        id: i64,
+        password_hash: &str,
+        must_change_password: bool,
+    ) -> Result<Student, sqlx::Error> {
+        sqlx::query("UPDATE students SET password_hash=?, must_change_password=? WHERE id=?")
+            .bind(password_hash)
+            .bind(must_change_password)
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        self.get_student_by_id(id).await
+    }
+
+    async fn delete_student(&self, id: i64) -> Result<i64, sqlx::Error> {
+        let result: MySqlQueryResult = sqlx::query("DELETE FROM students WHERE id=?")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        Ok(200)
+    }
+}
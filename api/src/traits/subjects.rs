@@ -1,14 +1,33 @@
-use crate::{db::DBState, models::Subject};
+use crate::{
+    db::{DBState, push_id_list},
+    models::{Subject, SubjectFilter},
+    services::listing::{ListQuery, Paged},
+};
 use async_trait::async_trait;
-use sqlx::mysql::MySqlQueryResult;
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait Subjects {
     async fn add_subject(&self, name: &str, group_id: &i64) -> Result<Subject, sqlx::Error>;
-    async fn edit_subject(&self, id: &i64, new_name: &str) -> Result<Subject, sqlx::Error>;
+    /// Renames the subject if it is still at `version`, any version when it is `None`.
+    async fn edit_subject(
+        &self,
+        id: &i64,
+        new_name: &str,
+        version: Option<i32>,
+    ) -> Result<Subject, sqlx::Error>;
     async fn delete_subject(&self, id: i64) -> Result<i16, sqlx::Error>;
     async fn get_subject_by_id(&self, id: i64) -> Result<Subject, sqlx::Error>;
-    async fn get_subjects_by_group_id(&self, group_id: i64) -> Result<Vec<Subject>, sqlx::Error>;
+    async fn list_subjects_by_group_id(
+        &self,
+        group_id: i64,
+        query: &ListQuery<SubjectFilter>,
+    ) -> Result<Paged<Subject>, sqlx::Error>;
+    async fn get_subjects_by_ids(&self, ids: &[i64]) -> Result<Vec<Subject>, sqlx::Error>;
+    async fn get_subjects_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<Subject>, sqlx::Error>;
 }
 
 #[async_trait]
@@ -23,41 +42,51 @@ impl Subjects for DBState {
 
         let id = result.last_insert_id() as i64;
 
-        let subject =
-            sqlx::query_as::<_, Subject>("SELECT id, name, group_id FROM subjects WHERE id=?")
-                .bind(id)
-                .fetch_one(&self.db)
-                .await?;
+        let subject = sqlx::query_as::<_, Subject>(
+            "SELECT id, name, group_id, version FROM subjects WHERE id=?",
+        )
+        .bind(id)
+        .fetch_one(&self.db)
+        .await?;
 
         Ok(subject)
     }
 
-    async fn edit_subject(&self, id: &i64, new_name: &str) -> Result<Subject, sqlx::Error> {
-        let result: MySqlQueryResult = sqlx::query("UPDATE subjects SET name=? WHERE id=?")
-            .bind(new_name)
-            .bind(id)
-            .execute(&self.db)
-            .await?;
+    async fn edit_subject(
+        &self,
+        id: &i64,
+        new_name: &str,
+        version: Option<i32>,
+    ) -> Result<Subject, sqlx::Error> {
+        let result: MySqlQueryResult = sqlx::query(
+            "UPDATE subjects SET name=?, version=version+1 WHERE id=? AND (? IS NULL OR version=?)",
+        )
+        .bind(new_name)
+        .bind(id)
+        .bind(version)
+        .bind(version)
+        .execute(&self.db)
+        .await?;
 
         if result.rows_affected() == 0 {
             return Err(sqlx::Error::RowNotFound);
         }
 
-        let subject =
-            sqlx::query_as::<_, Subject>("SELECT id, name, group_id FROM subjects WHERE id=?")
-                .bind(id)
-                .fetch_one(&self.db)
-                .await?;
+        let subject = sqlx::query_as::<_, Subject>(
+            "SELECT id, name, group_id, version FROM subjects WHERE id=?",
+        )
+        .bind(id)
+        .fetch_one(&self.db)
+        .await?;
 
         Ok(subject)
     }
 
     async fn delete_subject(&self, id: i64) -> Result<i16, sqlx::Error> {
-        let subject =
-            sqlx::query_as::<_, Subject>("SELECT id, name, group_id FROM subjects WHERE id=?")
-                .bind(id)
-                .fetch_one(&self.db)
-                .await?;
+        sqlx::query_as::<_, Subject>("SELECT id, name, group_id, version FROM subjects WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
 
         let result: MySqlQueryResult = sqlx::query("DELETE FROM subjects WHERE id=?")
             .bind(id)
@@ -72,21 +101,52 @@ impl Subjects for DBState {
     }
 
     async fn get_subject_by_id(&self, id: i64) -> Result<Subject, sqlx::Error> {
-        let subject =
-            sqlx::query_as::<_, Subject>("SELECT id, name, group_id FROM subjects WHERE id=?")
-                .bind(id)
-                .fetch_one(&self.db)
-                .await?;
+        let subject = sqlx::query_as::<_, Subject>(
+            "SELECT id, name, group_id, version FROM subjects WHERE id=?",
+        )
+        .bind(id)
+        .fetch_one(&self.db)
+        .await?;
 
         Ok(subject)
     }
 
-    async fn get_subjects_by_group_id(&self, group_id: i64) -> Result<Vec<Subject>, sqlx::Error> {
-        let subjects = sqlx::query_as::<_, Subject>("SELECT * FROM subjects WHERE group_id=?")
-            .bind(group_id)
+    async fn list_subjects_by_group_id(
+        &self,
+        group_id: i64,
+        query: &ListQuery<SubjectFilter>,
+    ) -> Result<Paged<Subject>, sqlx::Error> {
+        query
+            .fetch(&self.db, "*", |builder| {
+                builder
+                    .push("FROM subjects WHERE group_id=")
+                    .push_bind(group_id);
+            })
+            .await
+    }
+
+    async fn get_subjects_by_ids(&self, ids: &[i64]) -> Result<Vec<Subject>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT id, name, group_id, version FROM subjects WHERE id IN ");
+        push_id_list(&mut builder, ids);
+        builder
+            .build_query_as::<Subject>()
             .fetch_all(&self.db)
-            .await?;
+            .await
+    }
 
-        Ok(subjects)
+    async fn get_subjects_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<Subject>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT id, name, group_id, version FROM subjects WHERE group_id IN ",
+        );
+        push_id_list(&mut builder, group_ids);
+        builder.push(" ORDER BY name");
+        builder
+            .build_query_as::<Subject>()
+            .fetch_all(&self.db)
+            .await
     }
 }
@@ -0,0 +1,144 @@
+use crate::{
+    db::DBState,
+    models::{
+        Group, ScheduleChange, ScheduleRow, Subject, SyncDeletion, SyncEntity, SyncResponse,
+        TeacherLink, TeacherSafe,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use sqlx::{FromRow, MySql, MySqlConnection, QueryBuilder, mysql::MySqlRow};
+
+/// Days deletions are kept, older cursors get a full snapshot.
+const DELETION_RETENTION_DAYS: i64 = 30;
+
+/// The cursor trails the clock so rows written by transactions still running are sent
+/// again next time instead of being skipped.
+const CURSOR_LAG: TimeDelta = TimeDelta::seconds(5);
+
+#[derive(FromRow)]
+struct DeletionRow {
+    #[sqlx(try_from = "String")]
+    entity: SyncEntity,
+    entity_key: String,
+}
+
+/// Rows of `table` updated after `since`, limited to a group when `group_id` is set.
+async fn updated_rows<T>(
+    conn: &mut MySqlConnection,
+    columns: &str,
+    table: &str,
+    since: Option<DateTime<Utc>>,
+    group_id: Option<i64>,
+) -> Result<Vec<T>, sqlx::Error>
+where
+    T: for<'r> FromRow<'r, MySqlRow> + Send + Unpin,
+{
+    let mut builder: QueryBuilder<MySql> =
+        QueryBuilder::new(format!("SELECT {} FROM {} WHERE 1=1", columns, table));
+    if let Some(since) = since {
+        builder.push(" AND updated_at > ").push_bind(since);
+    }
+    if let Some(group_id) = group_id {
+        builder.push(" AND group_id=").push_bind(group_id);
+    }
+    // Past changes are never shown, they only go out as deletions once purged.
+    if table == "schedule_changes" {
+        builder
+            .push(" AND date >= ")
+            .push_bind(Local::now().date_naive());
+    }
+
+    builder.build_query_as::<T>().fetch_all(conn).await
+}
+
+#[async_trait]
+pub trait DeltaSync {
+    /// Returns everything changed after `since`, or a full snapshot when it is missing
+    /// or older than the deletion log.
+    async fn get_sync_delta(
+        &self,
+        since: Option<DateTime<Utc>>,
+        group_id: Option<i64>,
+    ) -> Result<SyncResponse, sqlx::Error>;
+    /// Drops deletions older than the retention, cursors that old get a full snapshot.
+    async fn purge_sync_deletions(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl DeltaSync for DBState {
+    async fn get_sync_delta(
+        &self,
+        since: Option<DateTime<Utc>>,
+        group_id: Option<i64>,
+    ) -> Result<SyncResponse, sqlx::Error> {
+        // One snapshot keeps the tables consistent with each other.
+        let mut tx = self.db.begin().await?;
+        let now: DateTime<Utc> = sqlx::query_scalar("SELECT NOW(6)")
+            .fetch_one(&mut *tx)
+            .await?;
+        let since = since.filter(|since| *since > now - TimeDelta::days(DELETION_RETENTION_DAYS));
+
+        let groups: Vec<Group> = updated_rows(&mut tx, "*", "groups", since, None).await?;
+        let teachers: Vec<TeacherSafe> =
+            updated_rows(&mut tx, "id, full_name, role", "teachers", since, None).await?;
+        let subjects: Vec<Subject> =
+            updated_rows(&mut tx, "*", "subjects", since, group_id).await?;
+        let pairs: Vec<ScheduleRow> =
+            updated_rows(&mut tx, "*", "schedule", since, group_id).await?;
+        let teacher_links: Vec<TeacherLink> =
+            updated_rows(&mut tx, "*", "teacher_links", since, group_id).await?;
+        let changes: Vec<ScheduleChange> =
+            updated_rows(&mut tx, "*", "schedule_changes", since, group_id).await?;
+
+        let deleted = match since {
+            Some(since) => {
+                let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+                    "SELECT entity, entity_key FROM sync_deletions WHERE deleted_at > ",
+                );
+                builder.push_bind(since);
+                if let Some(group_id) = group_id {
+                    builder
+                        .push(" AND (entity IN ('group', 'teacher') OR group_id=")
+                        .push_bind(group_id)
+                        .push(")");
+                }
+                builder
+                    .push(" ORDER BY id ASC")
+                    .build_query_as::<DeletionRow>()
+                    .fetch_all(&mut *tx)
+                    .await?
+                    .into_iter()
+                    .map(|row| SyncDeletion {
+                        entity: row.entity,
+                        key: row.entity_key,
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        tx.commit().await?;
+
+        Ok(SyncResponse {
+            cursor: (now - CURSOR_LAG).timestamp_micros().to_string(),
+            full: since.is_none(),
+            groups,
+            subjects,
+            teachers,
+            pairs,
+            teacher_links,
+            changes,
+            deleted,
+        })
+    }
+
+    async fn purge_sync_deletions(&self) -> Result<u64, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM sync_deletions WHERE deleted_at < NOW(6) - INTERVAL ? DAY")
+                .bind(DELETION_RETENTION_DAYS)
+                .execute(&self.db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{db::DBState, models::TeacherLink};
+use crate::{
+    db::{DBState, push_id_list},
+    models::TeacherLink,
+};
 use async_trait::async_trait;
-use sqlx::mysql::MySqlQueryResult;
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait TeacherLinks {
@@ -11,6 +14,10 @@ pub trait TeacherLinks {
         subject_id: i64,
     ) -> Result<TeacherLink, sqlx::Error>;
     async fn get_teacher_links(&self, group_id: i64) -> Result<Vec<TeacherLink>, sqlx::Error>;
+    async fn get_teacher_links_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<TeacherLink>, sqlx::Error>;
     async fn delete_teacher_link(
         &self,
         group_id: i64,
@@ -58,6 +65,20 @@ impl TeacherLinks for DBState {
         Ok(groups)
     }
 
+    async fn get_teacher_links_by_group_ids(
+        &self,
+        group_ids: &[i64],
+    ) -> Result<Vec<TeacherLink>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT teacher_id, group_id, subject_id FROM teacher_links WHERE group_id IN ",
+        );
+        push_id_list(&mut builder, group_ids);
+        builder
+            .build_query_as::<TeacherLink>()
+            .fetch_all(&self.db)
+            .await
+    }
+
     async fn delete_teacher_link(
         &self,
         group_id: i64,
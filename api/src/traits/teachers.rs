@@ -1,19 +1,28 @@
 use crate::{
-    db::DBState,
-    models::{Teacher, TeacherSafe},
+    auth::Role,
+    db::{DBState, push_id_list},
+    models::{Teacher, TeacherFilter, TeacherSafe},
+    services::listing::{ListQuery, Paged},
 };
 use async_trait::async_trait;
-use sqlx::mysql::MySqlQueryResult;
+use sqlx::{MySql, QueryBuilder, mysql::MySqlQueryResult};
 
 #[async_trait]
 pub trait Teachers {
     async fn get_teachers(&self) -> Result<Vec<TeacherSafe>, sqlx::Error>;
 
+    async fn list_teachers(
+        &self,
+        query: &ListQuery<TeacherFilter>,
+    ) -> Result<Paged<TeacherSafe>, sqlx::Error>;
+
     async fn add_teacher(
         &self,
         login: &str,
         password_hash: &str,
         fullname: &str,
+        email: Option<&str>,
+        must_change_password: bool,
     ) -> Result<Teacher, sqlx::Error>;
 
     async fn delete_teacher(&self, id: i64) -> Result<i16, sqlx::Error>;
@@ -22,6 +31,7 @@ pub trait Teachers {
         &self,
         id: i64,
         password_hash: &str,
+        must_change_password: bool,
     ) -> Result<Teacher, sqlx::Error>;
 
     async fn update_teacher_login(&self, id: i64, login: &str) -> Result<Teacher, sqlx::Error>;
@@ -32,9 +42,32 @@ pub trait Teachers {
         fullname: &str,
     ) -> Result<Teacher, sqlx::Error>;
 
+    async fn update_teacher_email(
+        &self,
+        id: i64,
+        email: Option<&str>,
+    ) -> Result<Teacher, sqlx::Error>;
+
+    async fn update_teacher_role(&self, id: i64, role: Role) -> Result<Teacher, sqlx::Error>;
+
+    async fn count_teachers_with_role(&self, role: Role) -> Result<i64, sqlx::Error>;
+
+    async fn update_teacher_totp(
+        &self,
+        id: i64,
+        secret: Option<&str>,
+        enabled: bool,
+    ) -> Result<Teacher, sqlx::Error>;
+
     async fn get_teacher_by_id(&self, id: i64) -> Result<Teacher, sqlx::Error>;
 
+    async fn get_teachers_by_ids(&self, ids: &[i64]) -> Result<Vec<TeacherSafe>, sqlx::Error>;
+
     async fn get_teacher_by_login(&self, login: &str) -> Result<Teacher, sqlx::Error>;
+
+    async fn get_teacher_by_email(&self, email: &str) -> Result<Teacher, sqlx::Error>;
+
+    async fn get_teachers_with_email(&self) -> Result<Vec<Teacher>, sqlx::Error>;
 }
 
 #[async_trait]
@@ -47,19 +80,35 @@ impl Teachers for DBState {
         Ok(teachers)
     }
 
+    async fn list_teachers(
+        &self,
+        query: &ListQuery<TeacherFilter>,
+    ) -> Result<Paged<TeacherSafe>, sqlx::Error> {
+        query
+            .fetch(&self.db, "id, full_name, role", |builder| {
+                builder.push("FROM teachers WHERE 1=1");
+            })
+            .await
+    }
+
     async fn add_teacher(
         &self,
         login: &str,
         password_hash: &str,
         fullname: &str,
+        email: Option<&str>,
+        must_change_password: bool,
     ) -> Result<Teacher, sqlx::Error> {
-        let result: MySqlQueryResult =
-            sqlx::query("INSERT INTO teachers (login, password_hash, full_name) VALUES (?, ?, ?)")
-                .bind(login)
-                .bind(password_hash)
-                .bind(fullname)
-                .execute(&self.db)
-                .await?;
+        let result: MySqlQueryResult = sqlx::query(
+            "INSERT INTO teachers (login, password_hash, full_name, email, must_change_password) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(login)
+        .bind(password_hash)
+        .bind(fullname)
+        .bind(email)
+        .bind(must_change_password)
+        .execute(&self.db)
+        .await?;
 
         let id = result.last_insert_id() as i64;
 
@@ -72,7 +121,7 @@ impl Teachers for DBState {
     }
 
     async fn delete_teacher(&self, id: i64) -> Result<i16, sqlx::Error> {
-        let teacher = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
+        sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
             .bind(id)
             .fetch_one(&self.db)
             .await?;
@@ -89,10 +138,16 @@ impl Teachers for DBState {
         Ok(200)
     }
 
-    async fn update_teacher_hash(&self, id: i64, new_hash: &str) -> Result<Teacher, sqlx::Error> {
+    async fn update_teacher_hash(
+        &self,
+        id: i64,
+        new_hash: &str,
+        must_change_password: bool,
+    ) -> Result<Teacher, sqlx::Error> {
         let result: MySqlQueryResult =
-            sqlx::query("UPDATE teachers SET password_hash=? WHERE id=?")
+            sqlx::query("UPDATE teachers SET password_hash=?, must_change_password=? WHERE id=?")
                 .bind(new_hash)
+                .bind(must_change_password)
                 .bind(id)
                 .execute(&self.db)
                 .await?;
@@ -151,6 +206,83 @@ impl Teachers for DBState {
         Ok(teacher)
     }
 
+    async fn update_teacher_email(
+        &self,
+        id: i64,
+        new_email: Option<&str>,
+    ) -> Result<Teacher, sqlx::Error> {
+        let result: MySqlQueryResult = sqlx::query("UPDATE teachers SET email=? WHERE id=?")
+            .bind(new_email)
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        let teacher = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(teacher)
+    }
+
+    async fn update_teacher_role(&self, id: i64, role: Role) -> Result<Teacher, sqlx::Error> {
+        let result: MySqlQueryResult = sqlx::query("UPDATE teachers SET role=? WHERE id=?")
+            .bind(role.as_str())
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        let teacher = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(teacher)
+    }
+
+    async fn count_teachers_with_role(&self, role: Role) -> Result<i64, sqlx::Error> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM teachers WHERE role=?")
+            .bind(role.as_str())
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn update_teacher_totp(
+        &self,
+        id: i64,
+        secret: Option<&str>,
+        enabled: bool,
+    ) -> Result<Teacher, sqlx::Error> {
+        let result: MySqlQueryResult =
+            sqlx::query("UPDATE teachers SET totp_secret=?, totp_enabled=? WHERE id=?")
+                .bind(secret)
+                .bind(enabled)
+                .bind(id)
+                .execute(&self.db)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        let teacher = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(teacher)
+    }
+
     async fn get_teacher_by_id(&self, id: i64) -> Result<Teacher, sqlx::Error> {
         let teacher = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE id=?")
             .bind(id)
@@ -160,6 +292,16 @@ impl Teachers for DBState {
         Ok(teacher)
     }
 
+    async fn get_teachers_by_ids(&self, ids: &[i64]) -> Result<Vec<TeacherSafe>, sqlx::Error> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT id, full_name, role FROM teachers WHERE id IN ");
+        push_id_list(&mut builder, ids);
+        builder
+            .build_query_as::<TeacherSafe>()
+            .fetch_all(&self.db)
+            .await
+    }
+
     async fn get_teacher_by_login(&self, login: &str) -> Result<Teacher, sqlx::Error> {
         let hash = sqlx::query_as::<_, Teacher>("SELECT * FROM teachers WHERE login=?")
             .bind(login)
@@ -168,4 +310,25 @@ impl Teachers for DBState {
 
         Ok(hash)
     }
+
+    async fn get_teacher_by_email(&self, email: &str) -> Result<Teacher, sqlx::Error> {
+        let teacher = sqlx::query_as::<_, Teacher>(
+            "SELECT * FROM teachers WHERE email=? ORDER BY id LIMIT 1",
+        )
+        .bind(email)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(teacher)
+    }
+
+    async fn get_teachers_with_email(&self) -> Result<Vec<Teacher>, sqlx::Error> {
+        let teachers = sqlx::query_as::<_, Teacher>(
+            "SELECT * FROM teachers WHERE email IS NOT NULL AND email <> ''",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(teachers)
+    }
 }
@@ -0,0 +1,71 @@
+use crate::{db::DBState, models::TelegramSubscription};
+use async_trait::async_trait;
+use sqlx::mysql::MySqlQueryResult;
+
+#[async_trait]
+pub trait TelegramSubscriptions {
+    async fn subscribe_chat(&self, chat_id: i64, group_id: i64) -> Result<(), sqlx::Error>;
+    async fn unsubscribe_chat(&self, chat_id: i64, group_id: i64) -> Result<u64, sqlx::Error>;
+    async fn get_chat_subscriptions(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<TelegramSubscription>, sqlx::Error>;
+    async fn get_group_subscribers(
+        &self,
+        group_id: i64,
+    ) -> Result<Vec<TelegramSubscription>, sqlx::Error>;
+}
+
+#[async_trait]
+impl TelegramSubscriptions for DBState {
+    async fn subscribe_chat(&self, chat_id: i64, group_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO telegram_subscriptions (chat_id, group_id) VALUES (?, ?) ON DUPLICATE KEY UPDATE chat_id=chat_id",
+        )
+        .bind(chat_id)
+        .bind(group_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unsubscribe_chat(&self, chat_id: i64, group_id: i64) -> Result<u64, sqlx::Error> {
+        let result: MySqlQueryResult =
+            sqlx::query("DELETE FROM telegram_subscriptions WHERE chat_id=? AND group_id=?")
+                .bind(chat_id)
+                .bind(group_id)
+                .execute(&self.db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn get_chat_subscriptions(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<TelegramSubscription>, sqlx::Error> {
+        let subscriptions = sqlx::query_as::<_, TelegramSubscription>(
+            "SELECT chat_id, group_id FROM telegram_subscriptions WHERE chat_id=? ORDER BY created_at ASC",
+        )
+        .bind(chat_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    async fn get_group_subscribers(
+        &self,
+        group_id: i64,
+    ) -> Result<Vec<TelegramSubscription>, sqlx::Error> {
+        let subscriptions = sqlx::query_as::<_, TelegramSubscription>(
+            "SELECT chat_id, group_id FROM telegram_subscriptions WHERE group_id=?",
+        )
+        .bind(group_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(subscriptions)
+    }
+}